@@ -1,20 +1,27 @@
 #![allow(dead_code)]
 
 use crate::ffmpeg;
+use crate::trim::Trim;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
 /// Audio enhancement options
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AudioEnhanceOptions {
     pub denoise: bool,
     pub normalize: bool,
+    #[serde(with = "crate::preset::or_off")]
     pub highpass_freq: Option<u32>,
     pub lowpass_freq: Option<u32>,
     pub notch_freq: Option<u32>, // 50 or 60 Hz hum removal
     pub compressor: bool,
     pub gate: bool,
+    #[serde(serialize_with = "crate::preset::serialize_f32")]
     pub gate_threshold: f32,
+    #[serde(skip)]
+    pub trim: Trim, // Part of the input to process
 }
 
 impl Default for AudioEnhanceOptions {
@@ -28,6 +35,7 @@ impl Default for AudioEnhanceOptions {
             compressor: true,
             gate: true,
             gate_threshold: -50.0,
+            trim: Trim::default(),
         }
     }
 }
@@ -86,7 +94,9 @@ pub fn enhance_audio(input: &Path, output: &Path, opts: &AudioEnhanceOptions) ->
     let filters = build_audio_filters(opts);
     let filter_str = filters.join(",");
 
-    let args = vec![
+    let trim_args = opts.trim.input_args();
+    let mut args: Vec<&str> = trim_args.iter().map(|s| s.as_str()).collect();
+    args.extend([
         "-i",
         input.to_str().unwrap(),
         "-af",
@@ -99,7 +109,7 @@ pub fn enhance_audio(input: &Path, output: &Path, opts: &AudioEnhanceOptions) ->
         "192k",
         "-y",
         output.to_str().unwrap(),
-    ];
+    ]);
 
     ffmpeg::execute_ffmpeg(&args)?;
     Ok(())
@@ -112,7 +122,9 @@ pub fn enhance_audio_only(input: &Path, output: &Path, opts: &AudioEnhanceOption
     let filters = build_audio_filters(opts);
     let filter_str = filters.join(",");
 
-    let args = vec![
+    let trim_args = opts.trim.input_args();
+    let mut args: Vec<&str> = trim_args.iter().map(|s| s.as_str()).collect();
+    args.extend([
         "-i",
         input.to_str().unwrap(),
         "-af",
@@ -121,7 +133,7 @@ pub fn enhance_audio_only(input: &Path, output: &Path, opts: &AudioEnhanceOption
         "flac", // Use lossless for audio-only
         "-y",
         output.to_str().unwrap(),
-    ];
+    ]);
 
     ffmpeg::execute_ffmpeg(&args)?;
     Ok(())
@@ -2,16 +2,22 @@ use eframe::egui;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
 use fluxara_avc::audio::{self, AudioEnhanceOptions};
-use fluxara_avc::video::{self, VideoEnhanceOptions, DenoiseType};
 use fluxara_avc::capture;
 use fluxara_avc::ffmpeg;
+use fluxara_avc::queue::{self, Job, JobQueue, JobState};
+use fluxara_avc::video::crop::PreviewFrame;
+use fluxara_avc::video::{
+    self, AspectHandling, AspectOptions, CropRect, DeinterlaceMethod, DenoiseType, DisplayAspect,
+    FieldRate, FrameRate, RateControl, SharpenMethod, SharpenOptions, VideoCodec, VideoEncoder,
+    VideoEnhanceOptions,
+};
 
 fn main() -> Result<(), eframe::Error> {
     let options = eframe::NativeOptions {
-        viewport: egui::ViewportBuilder::default()
-            .with_inner_size([1200.0, 800.0]),
+        viewport: egui::ViewportBuilder::default().with_inner_size([1200.0, 800.0]),
         ..Default::default()
     };
 
@@ -29,12 +35,21 @@ enum Tab {
     VhsRescue,
     Capture,
     Convert,
+    Queue,
     Info,
 }
 
+/// State of the border detection, which runs off the UI thread
+enum CropDetection {
+    Running,
+    Found(CropRect, Option<PreviewFrame>),
+    NoBorders,
+    Failed(String),
+}
+
 pub struct AvcApp {
     current_tab: Tab,
-    
+
     // Audio Enhancement
     audio_input: String,
     audio_output: String,
@@ -46,38 +61,54 @@ pub struct AvcApp {
     audio_compressor: bool,
     audio_gate: bool,
     audio_gate_threshold: f32,
-    
+
     // Video Enhancement
     video_input: String,
     video_output: String,
     video_deinterlace: bool,
+    video_deinterlace_method: String,
+    video_nnedi_weights: String,
+    video_field_rate: String,
     video_stabilize: bool,
     video_denoise: String,
     video_sharpen: bool,
+    video_sharpening: SharpenOptions,
     video_color: bool,
     video_width: Option<u32>,
     video_height: Option<u32>,
     video_aspect: String,
-    
+    video_crop: Option<CropRect>,
+    video_crop_candidate: Option<CropRect>,
+    video_crop_preview: Option<egui::TextureHandle>,
+    video_crop_detection: Arc<Mutex<Option<CropDetection>>>,
+    video_codec: String,
+    video_crf: u32,
+
     // VHS Rescue
     vhs_input: String,
     vhs_output: String,
     vhs_notch: Option<u32>,
-    
+    vhs_codec: String,
+    vhs_widescreen: bool,
+    vhs_square_pixels: bool,
+
     // Capture
     capture_output: String,
     capture_video_device: String,
     capture_audio_device: String,
     capture_format: String,
     capture_deinterlace: bool,
+    capture_deinterlace_method: String,
+    capture_nnedi_weights: String,
+    capture_field_rate: String,
     capture_stabilize: bool,
     capture_denoise: String,
     capture_width: Option<u32>,
     capture_height: Option<u32>,
-    capture_fps: Option<u32>,
+    capture_fps: Option<FrameRate>,
     capture_audio_bitrate: String,
     capture_archival: bool,
-    
+
     // Convert
     convert_input: String,
     convert_output: String,
@@ -85,11 +116,19 @@ pub struct AvcApp {
     convert_quality: String,
     convert_codec: String,
     convert_recursive: bool,
-    
+
     // Info
     info_input: String,
     info_output: String,
-    
+
+    // Queue
+    queue_path: Option<PathBuf>,
+    queue_jobs: Vec<Job>,
+    queue_loaded: Option<Instant>,
+    queue_priority: i32,
+    queue_retries: u32,
+    queue_running: Arc<Mutex<bool>>,
+
     // Status
     status_message: String,
     is_processing: Arc<Mutex<bool>>,
@@ -99,7 +138,7 @@ impl Default for AvcApp {
     fn default() -> Self {
         Self {
             current_tab: Tab::AudioEnhance,
-            
+
             audio_input: String::new(),
             audio_output: String::new(),
             audio_denoise: true,
@@ -110,45 +149,68 @@ impl Default for AvcApp {
             audio_compressor: true,
             audio_gate: true,
             audio_gate_threshold: -50.0,
-            
+
             video_input: String::new(),
             video_output: String::new(),
             video_deinterlace: true,
+            video_deinterlace_method: "bwdif".to_string(),
+            video_nnedi_weights: String::new(),
+            video_field_rate: "field".to_string(),
             video_stabilize: false,
             video_denoise: "hqdn3d".to_string(),
             video_sharpen: true,
+            video_sharpening: SharpenOptions::default(),
             video_color: true,
             video_width: None,
             video_height: None,
             video_aspect: "16:9".to_string(),
-            
+            video_crop: None,
+            video_crop_candidate: None,
+            video_crop_preview: None,
+            video_crop_detection: Arc::new(Mutex::new(None)),
+            video_codec: "x264".to_string(),
+            video_crf: 18,
+
             vhs_input: String::new(),
             vhs_output: String::new(),
             vhs_notch: None,
-            
+            vhs_codec: "x264".to_string(),
+            vhs_widescreen: false,
+            vhs_square_pixels: false,
+
             capture_output: String::new(),
             capture_video_device: "/dev/video0".to_string(),
             capture_audio_device: "hw:0,0".to_string(),
             capture_format: "mp4".to_string(),
             capture_deinterlace: true,
+            capture_deinterlace_method: "bwdif".to_string(),
+            capture_nnedi_weights: String::new(),
+            capture_field_rate: "field".to_string(),
             capture_stabilize: false,
             capture_denoise: "hqdn3d".to_string(),
             capture_width: Some(720),
             capture_height: Some(480),
-            capture_fps: Some(30),
+            capture_fps: Some(FrameRate::NTSC),
             capture_audio_bitrate: "192k".to_string(),
             capture_archival: false,
-            
+
             convert_input: String::new(),
             convert_output: String::new(),
             convert_format: "mp4".to_string(),
             convert_quality: "192k".to_string(),
             convert_codec: "libx264".to_string(),
             convert_recursive: false,
-            
+
             info_input: String::new(),
             info_output: String::new(),
-            
+
+            queue_path: queue::default_queue_path(),
+            queue_jobs: Vec::new(),
+            queue_loaded: None,
+            queue_priority: 0,
+            queue_retries: 1,
+            queue_running: Arc::new(Mutex::new(false)),
+
             status_message: "Ready".to_string(),
             is_processing: Arc::new(Mutex::new(false)),
         }
@@ -163,22 +225,46 @@ impl eframe::App for AvcApp {
 
             // Tab selector
             ui.horizontal(|ui| {
-                if ui.selectable_label(self.current_tab == Tab::AudioEnhance, "🎵 Audio Enhance").clicked() {
+                if ui
+                    .selectable_label(self.current_tab == Tab::AudioEnhance, "🎵 Audio Enhance")
+                    .clicked()
+                {
                     self.current_tab = Tab::AudioEnhance;
                 }
-                if ui.selectable_label(self.current_tab == Tab::VideoEnhance, "🎥 Video Enhance").clicked() {
+                if ui
+                    .selectable_label(self.current_tab == Tab::VideoEnhance, "🎥 Video Enhance")
+                    .clicked()
+                {
                     self.current_tab = Tab::VideoEnhance;
                 }
-                if ui.selectable_label(self.current_tab == Tab::VhsRescue, "📼 VHS Rescue").clicked() {
+                if ui
+                    .selectable_label(self.current_tab == Tab::VhsRescue, "📼 VHS Rescue")
+                    .clicked()
+                {
                     self.current_tab = Tab::VhsRescue;
                 }
-                if ui.selectable_label(self.current_tab == Tab::Capture, "📹 Capture").clicked() {
+                if ui
+                    .selectable_label(self.current_tab == Tab::Capture, "📹 Capture")
+                    .clicked()
+                {
                     self.current_tab = Tab::Capture;
                 }
-                if ui.selectable_label(self.current_tab == Tab::Convert, "🔄 Convert").clicked() {
+                if ui
+                    .selectable_label(self.current_tab == Tab::Convert, "🔄 Convert")
+                    .clicked()
+                {
                     self.current_tab = Tab::Convert;
                 }
-                if ui.selectable_label(self.current_tab == Tab::Info, "ℹ️ Info").clicked() {
+                if ui
+                    .selectable_label(self.current_tab == Tab::Queue, "📋 Queue")
+                    .clicked()
+                {
+                    self.current_tab = Tab::Queue;
+                }
+                if ui
+                    .selectable_label(self.current_tab == Tab::Info, "ℹ️ Info")
+                    .clicked()
+                {
                     self.current_tab = Tab::Info;
                 }
             });
@@ -192,6 +278,7 @@ impl eframe::App for AvcApp {
                 Tab::VhsRescue => self.show_vhs_rescue(ui),
                 Tab::Capture => self.show_capture(ui),
                 Tab::Convert => self.show_convert(ui),
+                Tab::Queue => self.show_queue(ui),
                 Tab::Info => self.show_info(ui),
             }
 
@@ -260,257 +347,1080 @@ impl AvcApp {
             if ui.checkbox(&mut notch_enabled, "Enable").clicked() {
                 if notch_enabled && self.audio_notch.is_none() {
                     self.audio_notch = Some(50);
-                    } else if !notch_enabled {
-                        self.audio_notch = None;
-                    }
-                }
-                if let Some(ref mut freq) = self.audio_notch {
-                    ui.add(egui::Slider::new(freq, 40..=60));
+                } else if !notch_enabled {
+                    self.audio_notch = None;
                 }
-            });
+            }
+            if let Some(ref mut freq) = self.audio_notch {
+                ui.add(egui::Slider::new(freq, 40..=60));
+            }
+        });
 
-            ui.horizontal(|ui| {
-                ui.label("Gate Threshold (dB):");
-                ui.add(egui::Slider::new(&mut self.audio_gate_threshold, -80.0..=-10.0));
-            });
+        ui.horizontal(|ui| {
+            ui.label("Gate Threshold (dB):");
+            ui.add(egui::Slider::new(
+                &mut self.audio_gate_threshold,
+                -80.0..=-10.0,
+            ));
+        });
 
-            ui.separator();
+        ui.separator();
 
-            if ui.button("▶ Enhance Audio").clicked() {
-                self.enhance_audio();
-            }
+        if ui.button("▶ Enhance Audio").clicked() {
+            self.enhance_audio();
         }
+    }
 
-        fn show_video_enhance(&mut self, ui: &mut egui::Ui) {
-            ui.label("Video Enhancement");
-            ui.separator();
+    fn show_video_enhance(&mut self, ui: &mut egui::Ui) {
+        ui.label("Video Enhancement");
+        ui.separator();
 
-            ui.horizontal(|ui| {
-                ui.label("Input File:");
-                ui.text_edit_singleline(&mut self.video_input);
-                if ui.button("Browse...").clicked() {
-                    if let Some(path) = rfd::FileDialog::new().pick_file() {
-                        self.video_input = path.to_string_lossy().to_string();
-                    }
+        ui.horizontal(|ui| {
+            ui.label("Input File:");
+            ui.text_edit_singleline(&mut self.video_input);
+            if ui.button("Browse...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                    self.video_input = path.to_string_lossy().to_string();
                 }
-            });
+            }
+        });
 
+        ui.horizontal(|ui| {
+            ui.label("Output File:");
+            ui.text_edit_singleline(&mut self.video_output);
+            if ui.button("Browse...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().save_file() {
+                    self.video_output = path.to_string_lossy().to_string();
+                }
+            }
+        });
+
+        ui.separator();
+        ui.label("Enhancement Options:");
+
+        ui.checkbox(&mut self.video_deinterlace, "Deinterlace");
+        ui.checkbox(&mut self.video_stabilize, "Stabilize (deshake)");
+        ui.checkbox(&mut self.video_sharpen, "Sharpen");
+        ui.checkbox(&mut self.video_color, "Color Adjustment");
+
+        ui.horizontal(|ui| {
+            ui.label("Deinterlacer:");
+            ui.selectable_value(
+                &mut self.video_deinterlace_method,
+                "bwdif".to_string(),
+                "BWDIF",
+            );
+            ui.selectable_value(
+                &mut self.video_deinterlace_method,
+                "yadif".to_string(),
+                "YADIF",
+            );
+            ui.selectable_value(
+                &mut self.video_deinterlace_method,
+                "w3fdif".to_string(),
+                "W3FDIF",
+            );
+            ui.selectable_value(
+                &mut self.video_deinterlace_method,
+                "estdif".to_string(),
+                "ESTDIF",
+            );
+            ui.selectable_value(
+                &mut self.video_deinterlace_method,
+                "nnedi".to_string(),
+                "NNEDI",
+            );
+        });
+
+        if self.video_deinterlace_method == "nnedi" {
             ui.horizontal(|ui| {
-                ui.label("Output File:");
-                ui.text_edit_singleline(&mut self.video_output);
+                ui.label("NNEDI Weights:");
+                ui.text_edit_singleline(&mut self.video_nnedi_weights);
                 if ui.button("Browse...").clicked() {
-                    if let Some(path) = rfd::FileDialog::new().save_file() {
-                        self.video_output = path.to_string_lossy().to_string();
+                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                        self.video_nnedi_weights = path.to_string_lossy().to_string();
                     }
                 }
             });
+        }
 
-            ui.separator();
-            ui.label("Enhancement Options:");
-
-            ui.checkbox(&mut self.video_deinterlace, "Deinterlace (bwdif)");
-            ui.checkbox(&mut self.video_stabilize, "Stabilize (deshake)");
-            ui.checkbox(&mut self.video_sharpen, "Sharpen (unsharp)");
-            ui.checkbox(&mut self.video_color, "Color Adjustment");
+        ui.horizontal(|ui| {
+            ui.label("Output Rate:");
+            ui.selectable_value(
+                &mut self.video_field_rate,
+                "field".to_string(),
+                "Frame per field",
+            );
+            ui.selectable_value(
+                &mut self.video_field_rate,
+                "frame".to_string(),
+                "Frame per frame",
+            );
+        });
 
-            ui.horizontal(|ui| {
-                ui.label("Denoise Type:");
-                ui.selectable_value(&mut self.video_denoise, "none".to_string(), "None");
-                ui.selectable_value(&mut self.video_denoise, "hqdn3d".to_string(), "HQDN3D");
-                ui.selectable_value(&mut self.video_denoise, "nlmeans".to_string(), "NLMeans");
-            });
+        ui.horizontal(|ui| {
+            ui.label("Denoise Type:");
+            ui.selectable_value(&mut self.video_denoise, "none".to_string(), "None");
+            ui.selectable_value(&mut self.video_denoise, "hqdn3d".to_string(), "HQDN3D");
+            ui.selectable_value(&mut self.video_denoise, "nlmeans".to_string(), "NLMeans");
+        });
 
+        if self.video_sharpen {
             ui.horizontal(|ui| {
-                ui.label("Width:");
-                let mut width_enabled = self.video_width.is_some();
-                if ui.checkbox(&mut width_enabled, "Enable").clicked() {
-                    if width_enabled && self.video_width.is_none() {
-                        self.video_width = Some(1920);
-                    } else if !width_enabled {
-                        self.video_width = None;
+                ui.label("Sharpening:");
+                for (method, label) in [
+                    (SharpenMethod::Unsharp, "Unsharp"),
+                    (SharpenMethod::Cas, "CAS (contrast-adaptive)"),
+                ] {
+                    let current = self.video_sharpening.method;
+                    let clicked = ui.selectable_label(current == method, label).clicked();
+                    if clicked && current != method {
+                        // Start from the method's own defaults
+                        self.video_sharpening = SharpenOptions {
+                            protect_noise: self.video_sharpening.protect_noise,
+                            ..SharpenOptions::new(method)
+                        };
                     }
                 }
-                if let Some(ref mut w) = self.video_width {
-                    ui.add(egui::Slider::new(w, 320..=4096));
-                }
             });
 
+            let (max_amount, has_radius) = match self.video_sharpening.method {
+                SharpenMethod::Unsharp => (2.0, true),
+                SharpenMethod::Cas => (1.0, false),
+            };
             ui.horizontal(|ui| {
-                ui.label("Height:");
-                let mut height_enabled = self.video_height.is_some();
-                if ui.checkbox(&mut height_enabled, "Enable").clicked() {
-                    if height_enabled && self.video_height.is_none() {
-                        self.video_height = Some(1080);
-                    } else if !height_enabled {
-                        self.video_height = None;
-                    }
-                }
-                if let Some(ref mut h) = self.video_height {
-                    ui.add(egui::Slider::new(h, 240..=2160));
+                ui.label("Luma Strength:");
+                ui.add(egui::Slider::new(
+                    &mut self.video_sharpening.luma_amount,
+                    0.0..=max_amount,
+                ));
+                if has_radius {
+                    ui.label("Radius:");
+                    let radius = &mut self.video_sharpening.luma_radius;
+                    ui.add(egui::Slider::new(radius, 1..=11));
                 }
             });
-
             ui.horizontal(|ui| {
-                ui.label("Aspect Ratio:");
-                ui.text_edit_singleline(&mut self.video_aspect);
+                ui.label("Chroma Strength:");
+                ui.add(egui::Slider::new(
+                    &mut self.video_sharpening.chroma_amount,
+                    0.0..=max_amount,
+                ));
+                if has_radius {
+                    ui.label("Radius:");
+                    let radius = &mut self.video_sharpening.chroma_radius;
+                    ui.add(egui::Slider::new(radius, 1..=11));
+                }
             });
+            ui.checkbox(
+                &mut self.video_sharpening.protect_noise,
+                "Skip noisy regions (found by the denoiser)",
+            );
+        }
 
-            ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Width:");
+            let mut width_enabled = self.video_width.is_some();
+            if ui.checkbox(&mut width_enabled, "Enable").clicked() {
+                if width_enabled && self.video_width.is_none() {
+                    self.video_width = Some(1920);
+                } else if !width_enabled {
+                    self.video_width = None;
+                }
+            }
+            if let Some(ref mut w) = self.video_width {
+                ui.add(egui::Slider::new(w, 320..=4096));
+            }
+        });
 
-            if ui.button("▶ Enhance Video").clicked() {
-                self.enhance_video();
+        ui.horizontal(|ui| {
+            ui.label("Height:");
+            let mut height_enabled = self.video_height.is_some();
+            if ui.checkbox(&mut height_enabled, "Enable").clicked() {
+                if height_enabled && self.video_height.is_none() {
+                    self.video_height = Some(1080);
+                } else if !height_enabled {
+                    self.video_height = None;
+                }
             }
-        }
+            if let Some(ref mut h) = self.video_height {
+                ui.add(egui::Slider::new(h, 240..=2160));
+            }
+        });
 
-        fn show_vhs_rescue(&mut self, ui: &mut egui::Ui) {
-            ui.label("VHS Rescue - One-Click Restoration");
-            ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Aspect Ratio:");
+            ui.text_edit_singleline(&mut self.video_aspect);
+        });
 
-            ui.horizontal(|ui| {
-                ui.label("Input File:");
-                ui.text_edit_singleline(&mut self.vhs_input);
-                if ui.button("Browse...").clicked() {
-                    if let Some(path) = rfd::FileDialog::new().pick_file() {
-                        self.vhs_input = path.to_string_lossy().to_string();
-                    }
+        ui.horizontal(|ui| {
+            ui.label("Encoder:");
+            if codec_selector(ui, &mut self.video_codec) {
+                // Start from the codec's own default quality
+                if let RateControl::Crf(crf) = video_encoder(&self.video_codec).rate {
+                    self.video_crf = crf;
                 }
-            });
+            }
+        });
 
+        if !video_encoder(&self.video_codec).codec.is_intra() {
             ui.horizontal(|ui| {
-                ui.label("Output File:");
-                ui.text_edit_singleline(&mut self.vhs_output);
-                if ui.button("Browse...").clicked() {
-                    if let Some(path) = rfd::FileDialog::new().save_file() {
-                        self.vhs_output = path.to_string_lossy().to_string();
+                ui.label("Quality (CRF):");
+                ui.add(egui::Slider::new(&mut self.video_crf, 0..=63));
+            });
+        }
+
+        self.poll_crop_detection(ui.ctx());
+        ui.horizontal(|ui| {
+            ui.label("Crop:");
+            match self.video_crop {
+                Some(crop) => {
+                    ui.label(crop.filter());
+                    if ui.button("Clear").clicked() {
+                        self.video_crop = None;
                     }
                 }
+                None => {
+                    ui.label("None");
+                }
+            }
+            let detecting = matches!(
+                *self.video_crop_detection.lock().unwrap(),
+                Some(CropDetection::Running)
+            );
+            if ui
+                .add_enabled(!detecting, egui::Button::new("🔍 Detect Borders"))
+                .clicked()
+            {
+                self.detect_crop(ui.ctx());
+            }
+        });
+
+        if let Some(candidate) = self.video_crop_candidate {
+            ui.group(|ui| {
+                ui.label(format!(
+                    "Detected crop {} (outlined in red):",
+                    candidate.filter()
+                ));
+                if let Some(ref texture) = self.video_crop_preview {
+                    ui.image((texture.id(), texture.size_vec2()));
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("✔ Apply Crop").clicked() {
+                        self.video_crop = Some(candidate);
+                        self.video_crop_candidate = None;
+                        self.video_crop_preview = None;
+                    }
+                    if ui.button("✖ Discard").clicked() {
+                        self.video_crop_candidate = None;
+                        self.video_crop_preview = None;
+                    }
+                });
             });
+        }
 
-            ui.separator();
-            ui.label("Options:");
+        ui.separator();
 
-            ui.horizontal(|ui| {
-                ui.label("Notch Filter (Hz):");
-                let mut notch_enabled = self.vhs_notch.is_some();
-                if ui.checkbox(&mut notch_enabled, "Enable").clicked() {
-                    if notch_enabled && self.vhs_notch.is_none() {
-                        self.vhs_notch = Some(50);
-                    } else if !notch_enabled {
-                        self.vhs_notch = None;
-                    }
+        if ui.button("▶ Enhance Video").clicked() {
+            self.enhance_video();
+        }
+    }
+
+    fn show_vhs_rescue(&mut self, ui: &mut egui::Ui) {
+        ui.label("VHS Rescue - One-Click Restoration");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Input File:");
+            ui.text_edit_singleline(&mut self.vhs_input);
+            if ui.button("Browse...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                    self.vhs_input = path.to_string_lossy().to_string();
                 }
-                if let Some(ref mut freq) = self.vhs_notch {
-                    ui.add(egui::Slider::new(freq, 40..=60));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Output File:");
+            ui.text_edit_singleline(&mut self.vhs_output);
+            if ui.button("Browse...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().save_file() {
+                    self.vhs_output = path.to_string_lossy().to_string();
                 }
-            });
+            }
+        });
 
-            ui.separator();
-            ui.label("VHS Rescue applies:");
-            ui.label("• Video: Deinterlace, Stabilize, Denoise, Sharpen, Color Adjust");
-            ui.label("• Audio: Denoise, Normalize, Compressor, Gate");
+        ui.separator();
+        ui.label("Options:");
 
-            ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Notch Filter (Hz):");
+            let mut notch_enabled = self.vhs_notch.is_some();
+            if ui.checkbox(&mut notch_enabled, "Enable").clicked() {
+                if notch_enabled && self.vhs_notch.is_none() {
+                    self.vhs_notch = Some(50);
+                } else if !notch_enabled {
+                    self.vhs_notch = None;
+                }
+            }
+            if let Some(ref mut freq) = self.vhs_notch {
+                ui.add(egui::Slider::new(freq, 40..=60));
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Encoder:");
+            codec_selector(ui, &mut self.vhs_codec);
+        });
+
+        ui.checkbox(
+            &mut self.vhs_widescreen,
+            "16:9 source (anamorphic widescreen)",
+        );
+        ui.checkbox(&mut self.vhs_square_pixels, "Convert to square pixels");
 
+        ui.separator();
+        ui.label("VHS Rescue applies:");
+        ui.label(
+            "• Video: Deinterlace, Head-Switching Mask, Stabilize, Denoise, Sharpen, Color Adjust",
+        );
+        ui.label("• Audio: Denoise, Normalize, Compressor, Gate");
+
+        ui.separator();
+
+        ui.horizontal(|ui| {
             if ui.button("▶ Start VHS Rescue").clicked() {
                 self.vhs_rescue();
             }
+            if ui.button("➕ Add to Queue").clicked() {
+                self.queue_vhs_rescue();
+            }
+            ui.label("Priority:");
+            ui.add(egui::DragValue::new(&mut self.queue_priority));
+            ui.label("Retries:");
+            ui.add(egui::DragValue::new(&mut self.queue_retries).clamp_range(0..=10));
+        });
+    }
+
+    fn show_queue(&mut self, ui: &mut egui::Ui) {
+        ui.label("Job Queue");
+        ui.separator();
+
+        // The CLI or another window may change the queue too
+        if self
+            .queue_loaded
+            .is_none_or(|t| t.elapsed() > Duration::from_secs(1))
+        {
+            self.load_queue();
         }
+        ui.ctx().request_repaint_after(Duration::from_secs(1));
 
-        fn capture(&mut self) {
-            if self.capture_output.is_empty() {
-                self.status_message = "Error: Output file required".to_string();
-                return;
+        let running = *self.queue_running.lock().unwrap();
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!running, egui::Button::new("▶ Run Queue"))
+                .clicked()
+            {
+                self.run_queue();
+            }
+            if ui.button("🔄 Refresh").clicked() {
+                self.load_queue();
             }
+            if running {
+                ui.spinner();
+            }
+        });
+        if let Some(ref path) = self.queue_path {
+            ui.label(format!("Queue file: {}", path.display()));
+        }
 
-            let output = PathBuf::from(self.capture_output.clone());
-            let opts = capture::CaptureOptions {
-                format: if self.capture_format == "mkv" {
-                    capture::CaptureFormat::Mkv
-                } else {
-                    capture::CaptureFormat::Mp4
-                },
-                video_device: self.capture_video_device.clone(),
-                audio_device: self.capture_audio_device.clone(),
-                deinterlace: self.capture_deinterlace,
-                stabilize: self.capture_stabilize,
-                denoise: if self.capture_denoise.is_empty() {
-                    None
-                } else {
-                    Some(self.capture_denoise.clone())
-                },
-                video_bitrate: None,
-                crf: Some(23),
-                width: self.capture_width,
-                height: self.capture_height,
-                fps: self.capture_fps,
-                audio_bitrate: self.capture_audio_bitrate.clone(),
-                archival_mode: self.capture_archival,
-            };
+        ui.separator();
 
-            self.status_message = "Starting capture...".to_string();
-            let is_processing = Arc::clone(&self.is_processing);
+        let mut action = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("queue-jobs").striped(true).show(ui, |ui| {
+                for heading in ["#", "State", "Priority", "Attempts", "Command", ""] {
+                    ui.strong(heading);
+                }
+                ui.end_row();
 
-            thread::spawn(move || {
-                *is_processing.lock().unwrap() = true;
-                match capture::capture(&output, &opts) {
-                    Ok(_) => println!("Capture completed!"),
-                    Err(e) => eprintln!("Error: {}", e),
+                for job in &self.queue_jobs {
+                    ui.label(job.id.to_string());
+                    ui.label(job.state.name())
+                        .on_hover_text(job.error.clone().unwrap_or_default());
+                    ui.label(job.priority.to_string());
+                    ui.label(format!("{}/{}", job.attempts, job.max_attempts));
+                    ui.label(job.command_line());
+                    ui.horizontal(|ui| match job.state {
+                        JobState::Queued | JobState::Running => {
+                            if ui.button("✖ Cancel").clicked() {
+                                action = Some((job.id, false));
+                            }
+                        }
+                        JobState::Failed | JobState::Cancelled => {
+                            if ui.button("↻ Retry").clicked() {
+                                action = Some((job.id, true));
+                            }
+                        }
+                        JobState::Done => {}
+                    });
+                    ui.end_row();
                 }
-                *is_processing.lock().unwrap() = false;
             });
+        });
+
+        if let Some((id, retry)) = action {
+            self.update_job(id, retry);
+        }
+    }
 
-            self.status_message = "Capture started!".to_string();
+    fn capture(&mut self) {
+        if self.capture_output.is_empty() {
+            self.status_message = "Error: Output file required".to_string();
+            return;
         }
 
-        fn convert(&mut self) {
-            if self.convert_input.is_empty() || self.convert_output.is_empty() {
-                self.status_message = "Error: Input and output paths required".to_string();
+        let deinterlace_method = match deinterlace_method(
+            &self.capture_deinterlace_method,
+            &self.capture_nnedi_weights,
+        ) {
+            Ok(method) => method,
+            Err(e) => {
+                self.status_message = e;
                 return;
             }
+        };
+
+        let output = PathBuf::from(self.capture_output.clone());
+        let opts = capture::CaptureOptions {
+            format: if self.capture_format == "mkv" {
+                capture::CaptureFormat::Mkv
+            } else {
+                capture::CaptureFormat::Mp4
+            },
+            video_device: self.capture_video_device.clone(),
+            audio_device: self.capture_audio_device.clone(),
+            deinterlace: self.capture_deinterlace,
+            deinterlace_method,
+            field_rate: field_rate(&self.capture_field_rate),
+            stabilize: self.capture_stabilize,
+            denoise: if self.capture_denoise.is_empty() {
+                None
+            } else {
+                Some(self.capture_denoise.clone())
+            },
+            video_bitrate: None,
+            crf: Some(23),
+            width: self.capture_width,
+            height: self.capture_height,
+            fps: self.capture_fps,
+            audio_bitrate: self.capture_audio_bitrate.clone(),
+            archival_mode: self.capture_archival,
+            vitc_log: None,
+        };
+
+        self.status_message = "Starting capture...".to_string();
+        let is_processing = Arc::clone(&self.is_processing);
+
+        thread::spawn(move || {
+            *is_processing.lock().unwrap() = true;
+            match capture::capture(&output, &opts) {
+                Ok(_) => println!("Capture completed!"),
+                Err(e) => eprintln!("Error: {}", e),
+            }
+            *is_processing.lock().unwrap() = false;
+        });
+
+        self.status_message = "Capture started!".to_string();
+    }
+
+    fn convert(&mut self) {
+        if self.convert_input.is_empty() || self.convert_output.is_empty() {
+            self.status_message = "Error: Input and output paths required".to_string();
+            return;
+        }
+
+        let input = PathBuf::from(self.convert_input.clone());
+        let output = PathBuf::from(self.convert_output.clone());
+        let format = self.convert_format.clone();
+        let quality = self.convert_quality.clone();
+        let codec = self.convert_codec.clone();
+        let recursive = self.convert_recursive;
+
+        self.status_message = "Starting conversion...".to_string();
+        let is_processing = Arc::clone(&self.is_processing);
+
+        thread::spawn(move || {
+            *is_processing.lock().unwrap() = true;
+            match ffmpeg::convert_media(&input, &output, &format, &quality, &codec, recursive) {
+                Ok(_) => println!("Conversion completed!"),
+                Err(e) => eprintln!("Error: {}", e),
+            }
+            *is_processing.lock().unwrap() = false;
+        });
+
+        self.status_message = "Conversion started!".to_string();
+    }
+
+    fn get_info(&mut self) {
+        if self.info_input.is_empty() {
+            self.info_output = "Error: Input file required".to_string();
+            return;
+        }
+
+        let input = PathBuf::from(self.info_input.clone());
+        match ffmpeg::get_media_info(&input) {
+            Ok(info) => {
+                self.info_output = serde_json::to_string_pretty(&info).unwrap_or_default();
+            }
+            Err(e) => {
+                self.info_output = format!("Error: {}", e);
+            }
+        }
+    }
+    fn show_capture(&mut self, ui: &mut egui::Ui) {
+        ui.label("Capture from V4L2/ALSA Devices");
+        ui.separator();
 
-            let input = PathBuf::from(self.convert_input.clone());
-            let output = PathBuf::from(self.convert_output.clone());
-            let format = self.convert_format.clone();
-            let quality = self.convert_quality.clone();
-            let codec = self.convert_codec.clone();
-            let recursive = self.convert_recursive;
-
-            self.status_message = "Starting conversion...".to_string();
-            let is_processing = Arc::clone(&self.is_processing);
-
-            thread::spawn(move || {
-                *is_processing.lock().unwrap() = true;
-                match ffmpeg::convert_media(
-                    &input,
-                    &output,
-                    &format,
-                    &quality,
-                    &codec,
-                    recursive,
-                ) {
-                    Ok(_) => println!("Conversion completed!"),
-                    Err(e) => eprintln!("Error: {}", e),
-                }
-                *is_processing.lock().unwrap() = false;
+        ui.horizontal(|ui| {
+            ui.label("Output File:");
+            ui.text_edit_singleline(&mut self.capture_output);
+            if ui.button("Browse...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().save_file() {
+                    self.capture_output = path.to_string_lossy().to_string();
+                }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Video Device:");
+            ui.text_edit_singleline(&mut self.capture_video_device);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Audio Device:");
+            ui.text_edit_singleline(&mut self.capture_audio_device);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Format:");
+            ui.selectable_value(&mut self.capture_format, "mp4".to_string(), "MP4");
+            ui.selectable_value(&mut self.capture_format, "mkv".to_string(), "MKV");
+        });
+
+        ui.separator();
+        ui.label("Capture Options:");
+
+        ui.checkbox(&mut self.capture_deinterlace, "Deinterlace");
+        ui.checkbox(&mut self.capture_stabilize, "Stabilize (deshake)");
+        ui.checkbox(&mut self.capture_archival, "Archival Mode (near-lossless)");
+
+        ui.horizontal(|ui| {
+            ui.label("Deinterlacer:");
+            ui.selectable_value(
+                &mut self.capture_deinterlace_method,
+                "bwdif".to_string(),
+                "BWDIF",
+            );
+            ui.selectable_value(
+                &mut self.capture_deinterlace_method,
+                "yadif".to_string(),
+                "YADIF",
+            );
+            ui.selectable_value(
+                &mut self.capture_deinterlace_method,
+                "w3fdif".to_string(),
+                "W3FDIF",
+            );
+            ui.selectable_value(
+                &mut self.capture_deinterlace_method,
+                "estdif".to_string(),
+                "ESTDIF",
+            );
+            ui.selectable_value(
+                &mut self.capture_deinterlace_method,
+                "nnedi".to_string(),
+                "NNEDI",
+            );
+        });
+
+        if self.capture_deinterlace_method == "nnedi" {
+            ui.horizontal(|ui| {
+                ui.label("NNEDI Weights:");
+                ui.text_edit_singleline(&mut self.capture_nnedi_weights);
+                if ui.button("Browse...").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                        self.capture_nnedi_weights = path.to_string_lossy().to_string();
+                    }
+                }
             });
+        }
 
-            self.status_message = "Conversion started!".to_string();
+        ui.horizontal(|ui| {
+            ui.label("Output Rate:");
+            ui.selectable_value(
+                &mut self.capture_field_rate,
+                "field".to_string(),
+                "Frame per field",
+            );
+            ui.selectable_value(
+                &mut self.capture_field_rate,
+                "frame".to_string(),
+                "Frame per frame",
+            );
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Denoise Type:");
+            ui.selectable_value(&mut self.capture_denoise, String::new(), "None");
+            ui.selectable_value(&mut self.capture_denoise, "hqdn3d".to_string(), "HQDN3D");
+            ui.selectable_value(&mut self.capture_denoise, "nlmeans".to_string(), "NLMeans");
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Audio Bitrate:");
+            ui.text_edit_singleline(&mut self.capture_audio_bitrate);
+        });
+
+        ui.separator();
+
+        if ui.button("⏺ Start Capture").clicked() {
+            self.capture();
         }
+    }
 
-        fn get_info(&mut self) {
-            if self.info_input.is_empty() {
-                self.info_output = "Error: Input file required".to_string();
-                return;
+    fn show_convert(&mut self, ui: &mut egui::Ui) {
+        ui.label("Convert Media");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Input File:");
+            ui.text_edit_singleline(&mut self.convert_input);
+            if ui.button("Browse...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                    self.convert_input = path.to_string_lossy().to_string();
+                }
             }
+        });
 
-            let input = PathBuf::from(self.info_input.clone());
-            match ffmpeg::get_media_info(&input) {
-                Ok(info) => {
-                    self.info_output = serde_json::to_string_pretty(&info).unwrap_or_default();
+        ui.horizontal(|ui| {
+            ui.label("Output File:");
+            ui.text_edit_singleline(&mut self.convert_output);
+            if ui.button("Browse...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().save_file() {
+                    self.convert_output = path.to_string_lossy().to_string();
                 }
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Format:");
+            ui.text_edit_singleline(&mut self.convert_format);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Bitrate:");
+            ui.text_edit_singleline(&mut self.convert_quality);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Video Codec:");
+            ui.text_edit_singleline(&mut self.convert_codec);
+        });
+
+        ui.checkbox(&mut self.convert_recursive, "Recursive");
+
+        ui.separator();
+
+        if ui.button("▶ Convert").clicked() {
+            self.convert();
+        }
+    }
+
+    fn show_info(&mut self, ui: &mut egui::Ui) {
+        ui.label("Media Information");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Input File:");
+            ui.text_edit_singleline(&mut self.info_input);
+            if ui.button("Browse...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_file() {
+                    self.info_input = path.to_string_lossy().to_string();
+                }
+            }
+        });
+
+        if ui.button("ℹ Get Info").clicked() {
+            self.get_info();
+        }
+
+        ui.separator();
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.monospace(&self.info_output);
+        });
+    }
+
+    fn enhance_audio(&mut self) {
+        if self.audio_input.is_empty() || self.audio_output.is_empty() {
+            self.status_message = "Error: Input and output files required".to_string();
+            return;
+        }
+
+        let input = PathBuf::from(self.audio_input.clone());
+        let output = PathBuf::from(self.audio_output.clone());
+        let opts = AudioEnhanceOptions {
+            denoise: self.audio_denoise,
+            normalize: self.audio_normalize,
+            highpass_freq: Some(self.audio_highpass),
+            lowpass_freq: self.audio_lowpass,
+            notch_freq: self.audio_notch,
+            compressor: self.audio_compressor,
+            gate: self.audio_gate,
+            gate_threshold: self.audio_gate_threshold,
+            trim: Default::default(),
+        };
+
+        self.status_message = "Enhancing audio...".to_string();
+        let is_processing = Arc::clone(&self.is_processing);
+
+        thread::spawn(move || {
+            *is_processing.lock().unwrap() = true;
+            match audio::enhance_audio(&input, &output, &opts) {
+                Ok(_) => println!("Audio enhancement completed!"),
+                Err(e) => eprintln!("Error: {}", e),
+            }
+            *is_processing.lock().unwrap() = false;
+        });
+
+        self.status_message = "Audio enhancement started!".to_string();
+    }
+
+    fn enhance_video(&mut self) {
+        if self.video_input.is_empty() || self.video_output.is_empty() {
+            self.status_message = "Error: Input and output files required".to_string();
+            return;
+        }
+
+        let deinterlace_method =
+            match deinterlace_method(&self.video_deinterlace_method, &self.video_nnedi_weights) {
+                Ok(method) => method,
                 Err(e) => {
-                    self.info_output = format!("Error: {}", e);
+                    self.status_message = e;
+                    return;
+                }
+            };
+
+        let input = PathBuf::from(self.video_input.clone());
+        let output = PathBuf::from(self.video_output.clone());
+        let opts = VideoEnhanceOptions {
+            deinterlace: self.video_deinterlace,
+            scan_type: None,
+            deinterlace_method,
+            field_rate: field_rate(&self.video_field_rate),
+            dropout_repair: None,
+            crop: self.video_crop,
+            overscan: None,
+            chroma: None,
+            stabilize: self.video_stabilize,
+            denoise: match self.video_denoise.as_str() {
+                "none" => DenoiseType::None,
+                "nlmeans" => DenoiseType::Nlmeans,
+                _ => DenoiseType::Hqdn3d,
+            },
+            sharpen: self.video_sharpen.then_some(self.video_sharpening),
+            color_adjust: self.video_color,
+            frame_rate: None,
+            auto_color: None,
+            color_conversion: None,
+            pixel_aspect: None,
+            scale: None,
+            scale_width: self.video_width,
+            scale_height: self.video_height,
+            aspect_ratio: if self.video_aspect.is_empty() {
+                None
+            } else {
+                Some(self.video_aspect.clone())
+            },
+            encoder: {
+                let mut encoder = video_encoder(&self.video_codec);
+                if !encoder.codec.is_intra() {
+                    encoder.rate = RateControl::Crf(self.video_crf);
                 }
+                encoder
+            },
+            depth: None,
+            trim: Default::default(),
+            timecode: None,
+        };
+
+        self.status_message = "Enhancing video...".to_string();
+        let is_processing = Arc::clone(&self.is_processing);
+
+        thread::spawn(move || {
+            *is_processing.lock().unwrap() = true;
+            match video::enhance_video(&input, &output, &opts) {
+                Ok(_) => println!("Video enhancement completed!"),
+                Err(e) => eprintln!("Error: {}", e),
+            }
+            *is_processing.lock().unwrap() = false;
+        });
+
+        self.status_message = "Video enhancement started!".to_string();
+    }
+
+    fn detect_crop(&mut self, ctx: &egui::Context) {
+        if self.video_input.is_empty() {
+            self.status_message = "Error: Input file required".to_string();
+            return;
+        }
+
+        let input = PathBuf::from(self.video_input.clone());
+        self.status_message = "Detecting borders...".to_string();
+        *self.video_crop_detection.lock().unwrap() = Some(CropDetection::Running);
+        let detection = Arc::clone(&self.video_crop_detection);
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let result = match video::crop::detect_crop(&input, &Default::default()) {
+                Ok(Some(crop)) => {
+                    let preview = match video::crop::render_crop_preview(&input, &crop, 480) {
+                        Ok(frame) => Some(frame),
+                        Err(e) => {
+                            eprintln!("Crop preview failed: {}", e);
+                            None
+                        }
+                    };
+                    CropDetection::Found(crop, preview)
+                }
+                Ok(None) => CropDetection::NoBorders,
+                Err(e) => CropDetection::Failed(e.to_string()),
+            };
+            *detection.lock().unwrap() = Some(result);
+            ctx.request_repaint();
+        });
+    }
+
+    /// Pick up a finished border detection and show its preview
+    fn poll_crop_detection(&mut self, ctx: &egui::Context) {
+        let result = {
+            let mut detection = self.video_crop_detection.lock().unwrap();
+            match *detection {
+                None | Some(CropDetection::Running) => return,
+                _ => detection.take(),
+            }
+        };
+
+        match result {
+            Some(CropDetection::Found(crop, preview)) => {
+                self.video_crop_preview = preview.map(|frame| {
+                    let image = egui::ColorImage::from_rgba_unmultiplied(
+                        [frame.width as usize, frame.height as usize],
+                        &frame.rgba,
+                    );
+                    ctx.load_texture("crop-preview", image, egui::TextureOptions::default())
+                });
+                self.video_crop_candidate = Some(crop);
+                self.status_message = format!("Detected crop {}, confirm to apply", crop.filter());
+            }
+            Some(CropDetection::NoBorders) => {
+                self.status_message = "No black borders detected".to_string();
             }
+            Some(CropDetection::Failed(e)) => self.status_message = format!("Error: {}", e),
+            None | Some(CropDetection::Running) => {}
         }
     }
+
+    fn load_queue(&mut self) {
+        let Some(ref path) = self.queue_path else {
+            self.status_message = "Error: Cannot find the queue directory, set HOME".to_string();
+            return;
+        };
+        match JobQueue::load(path) {
+            Ok(mut q) => {
+                q.recover(queue::job_alive);
+                self.queue_jobs = q.jobs;
+            }
+            Err(e) => self.status_message = format!("Error: {}", e),
+        }
+        self.queue_loaded = Some(Instant::now());
+    }
+
+    fn add_to_queue(&mut self, args: Vec<String>) {
+        let Some(path) = self.queue_path.clone() else {
+            self.status_message = "Error: Cannot find the queue directory, set HOME".to_string();
+            return;
+        };
+        let dir = std::env::current_dir().unwrap_or_default();
+        let (priority, retries) = (self.queue_priority, self.queue_retries);
+        self.status_message =
+            match JobQueue::update(&path, |q| Ok(q.add(args, dir, priority, retries))) {
+                Ok(id) => format!("Queued job {}", id),
+                Err(e) => format!("Error: {}", e),
+            };
+        self.queue_loaded = None;
+    }
+
+    /// Cancel a queued/running job, or queue a failed/cancelled one again
+    fn update_job(&mut self, id: u64, retry: bool) {
+        let Some(path) = self.queue_path.clone() else {
+            return;
+        };
+        let result = if retry {
+            JobQueue::update(&path, |q| q.retry(id)).map(|_| format!("Queued job {} again", id))
+        } else {
+            JobQueue::update(&path, |q| {
+                let alive = q.get(id).is_some_and(queue::job_alive);
+                Ok(q.cancel(id)?.filter(|_| alive))
+            })
+            .and_then(|pid| pid.map_or(Ok(()), queue::kill_job))
+            .map(|_| format!("Cancelled job {}", id))
+        };
+        self.status_message = result.unwrap_or_else(|e| format!("Error: {}", e));
+        self.load_queue();
+    }
+
+    fn run_queue(&mut self) {
+        let Some(path) = self.queue_path.clone() else {
+            return;
+        };
+        let program = queue::cli_program();
+
+        self.status_message = "Running queue...".to_string();
+        let queue_running = Arc::clone(&self.queue_running);
+
+        thread::spawn(move || {
+            *queue_running.lock().unwrap() = true;
+            match queue::run_queue(&path, &program, |job| {
+                println!(
+                    "Job {} {}: {}",
+                    job.id,
+                    job.state.name(),
+                    job.command_line()
+                )
+            }) {
+                Ok(count) => println!("Queue finished, {} job(s) run", count),
+                Err(e) => eprintln!("Error: {}", e),
+            }
+            *queue_running.lock().unwrap() = false;
+        });
+    }
+
+    /// Queue the VHS Rescue settings as a `vhs-rescue` command
+    fn queue_vhs_rescue(&mut self) {
+        if self.vhs_input.is_empty() || self.vhs_output.is_empty() {
+            self.status_message = "Error: Input and output files required".to_string();
+            return;
+        }
+
+        let mut args = vec![
+            "vhs-rescue".to_string(),
+            "--input".to_string(),
+            self.vhs_input.clone(),
+            "--output".to_string(),
+            self.vhs_output.clone(),
+        ];
+        if let Some(freq) = self.vhs_notch {
+            args.extend(["--notch".to_string(), freq.to_string()]);
+        }
+        if self.vhs_codec != "x264" {
+            args.extend(["--vcodec".to_string(), self.vhs_codec.clone()]);
+        }
+        if self.vhs_widescreen {
+            args.extend(["--display-aspect".to_string(), "16:9".to_string()]);
+        }
+        if self.vhs_square_pixels {
+            args.extend(["--anamorphic".to_string(), "square".to_string()]);
+        }
+        self.add_to_queue(args);
+    }
+
+    fn vhs_rescue(&mut self) {
+        if self.vhs_input.is_empty() || self.vhs_output.is_empty() {
+            self.status_message = "Error: Input and output files required".to_string();
+            return;
+        }
+
+        let input = PathBuf::from(self.vhs_input.clone());
+        let output = PathBuf::from(self.vhs_output.clone());
+        let opts = video::VhsRescueOptions {
+            notch_freq: self.vhs_notch,
+            encoder: match self.vhs_codec.as_str() {
+                "x264" => None, // Archival x264 default
+                codec => Some(video_encoder(codec)),
+            },
+            aspect: AspectOptions {
+                // Unticked keeps what the source is flagged with
+                display: self.vhs_widescreen.then_some(DisplayAspect::Widescreen),
+                handling: if self.vhs_square_pixels {
+                    AspectHandling::SquarePixels
+                } else {
+                    AspectHandling::Anamorphic
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        self.status_message = "Starting VHS Rescue...".to_string();
+        let is_processing = Arc::clone(&self.is_processing);
+
+        thread::spawn(move || {
+            *is_processing.lock().unwrap() = true;
+            match video::vhs_rescue(&input, &output, &opts) {
+                Ok(_) => println!("VHS Rescue completed!"),
+                Err(e) => eprintln!("Error: {}", e),
+            }
+            *is_processing.lock().unwrap() = false;
+        });
+
+        self.status_message = "VHS Rescue started!".to_string();
+    }
+}
+
+fn deinterlace_method(name: &str, nnedi_weights: &str) -> Result<DeinterlaceMethod, String> {
+    Ok(match name {
+        "yadif" => DeinterlaceMethod::Yadif,
+        "w3fdif" => DeinterlaceMethod::W3fdif,
+        "estdif" => DeinterlaceMethod::Estdif,
+        "nnedi" if nnedi_weights.is_empty() => {
+            return Err("Error: NNEDI requires a weights file".to_string());
+        }
+        "nnedi" => DeinterlaceMethod::Nnedi {
+            weights: PathBuf::from(nnedi_weights),
+        },
+        _ => DeinterlaceMethod::Bwdif,
+    })
+}
+
+fn field_rate(rate: &str) -> FieldRate {
+    match rate {
+        "frame" => FieldRate::Frame,
+        _ => FieldRate::Field,
+    }
+}
+
+/// Codec buttons; returns true when the selection changed
+fn codec_selector(ui: &mut egui::Ui, codec: &mut String) -> bool {
+    let mut changed = false;
+    for (name, label) in [
+        ("x264", "H.264"),
+        ("x265", "HEVC"),
+        ("svt-av1", "AV1 (SVT)"),
+        ("aom", "AV1 (libaom)"),
+        ("vp9", "VP9"),
+        ("ffv1", "FFV1"),
+        ("prores", "ProRes"),
+        ("dnxhr", "DNxHR"),
+    ] {
+        changed |= ui
+            .selectable_value(codec, name.to_string(), label)
+            .changed();
+    }
+    changed
+}
+
+fn video_encoder(codec: &str) -> VideoEncoder {
+    VideoEncoder::new(VideoCodec::from_name(codec).unwrap_or(VideoCodec::X264))
+}
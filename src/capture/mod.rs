@@ -1,6 +1,9 @@
 use crate::ffmpeg;
+use crate::video::framerate::FrameRate;
+use crate::video::interlace::{self, DeinterlaceMethod, FieldRate};
+use crate::video::timecode;
 use anyhow::{Context, Result};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
 /// Capture format options
@@ -17,15 +20,18 @@ pub struct CaptureOptions {
     pub video_device: String, // e.g., "/dev/video0"
     pub audio_device: String, // e.g., "hw:1,0"
     pub deinterlace: bool,
+    pub deinterlace_method: DeinterlaceMethod,
+    pub field_rate: FieldRate,
     pub stabilize: bool,
     pub denoise: Option<String>,       // "hqdn3d" or "nlmeans"
     pub video_bitrate: Option<String>, // e.g., "5M"
     pub crf: Option<u32>,              // CRF value (18-28)
     pub width: Option<u32>,
     pub height: Option<u32>,
-    pub fps: Option<u32>,
-    pub audio_bitrate: String, // e.g., "192k"
-    pub archival_mode: bool,   // Lossless/near-lossless
+    pub fps: Option<FrameRate>,
+    pub audio_bitrate: String,     // e.g., "192k"
+    pub archival_mode: bool,       // Lossless/near-lossless
+    pub vitc_log: Option<PathBuf>, // Log the VITC of every frame here (professional tapes)
 }
 
 impl Default for CaptureOptions {
@@ -35,15 +41,18 @@ impl Default for CaptureOptions {
             video_device: "/dev/video0".to_string(),
             audio_device: "hw:1,0".to_string(),
             deinterlace: true,
+            deinterlace_method: DeinterlaceMethod::Bwdif,
+            field_rate: FieldRate::Field,
             stabilize: false,
             denoise: Some("hqdn3d".to_string()),
             video_bitrate: None,
             crf: Some(23),
             width: Some(720),
             height: Some(480),
-            fps: Some(30),
+            fps: Some(FrameRate::NTSC),
             audio_bitrate: "192k".to_string(),
             archival_mode: false,
+            vitc_log: None,
         }
     }
 }
@@ -178,8 +187,17 @@ pub fn capture(output: &Path, opts: &CaptureOptions) -> Result<()> {
 
     // Build video filters
     let mut vfilters = Vec::new();
+    // VITC sits in the vertical interval, read it before anything touches the picture.
+    // It goes to a file so the log survives stopping the capture with Ctrl+C.
+    if let Some(ref log) = opts.vitc_log {
+        vfilters.extend(timecode::readvitc_filters(Some(log)));
+    }
     if opts.deinterlace {
-        vfilters.push("bwdif".to_string());
+        vfilters.push(interlace::deinterlace_filter(
+            &opts.deinterlace_method,
+            opts.field_rate,
+            "auto",
+        ));
     }
     if opts.stabilize {
         vfilters.push("deshake".to_string());
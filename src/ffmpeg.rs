@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use crate::video::framerate::FrameRate;
 use anyhow::{Context, Result};
 use std::path::Path;
 use std::process::{Command, Stdio};
@@ -34,6 +35,46 @@ pub fn execute_ffmpeg(args: &[&str]) -> Result<()> {
     Ok(())
 }
 
+/// Execute FFmpeg command synchronously and return its log output (stderr).
+/// Used by analysis passes whose filters report statistics on the log.
+pub fn execute_ffmpeg_with_log(args: &[&str]) -> Result<String> {
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(args).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    let output = cmd.output().context("Failed to execute ffmpeg")?;
+    let log = String::from_utf8_lossy(&output.stderr).to_string();
+
+    if !output.status.success() {
+        anyhow::bail!("FFmpeg error: {}", log);
+    }
+
+    Ok(log)
+}
+
+/// Execute FFmpeg command synchronously and return what it wrote to stdout,
+/// e.g. raw frames for analysis in Rust
+pub fn execute_ffmpeg_raw(args: &[&str]) -> Result<Vec<u8>> {
+    let mut cmd = Command::new("ffmpeg");
+    cmd.args(args).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    let output = cmd.output().context("Failed to execute ffmpeg")?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        anyhow::bail!("FFmpeg error: {}", error);
+    }
+
+    Ok(output.stdout)
+}
+
+/// True if the ffmpeg build has a filter (e.g. libvmaf)
+pub fn has_filter(name: &str) -> Result<bool> {
+    let list = execute_ffmpeg_raw(&["-hide_banner", "-filters"])?;
+    Ok(String::from_utf8_lossy(&list)
+        .lines()
+        .any(|line| line.split_whitespace().nth(1) == Some(name)))
+}
+
 /// Execute FFmpeg command asynchronously
 pub async fn execute_ffmpeg_async(args: Vec<String>) -> Result<()> {
     let mut cmd = TokioCommand::new("ffmpeg");
@@ -74,6 +115,119 @@ pub fn get_media_info(input: &Path) -> Result<serde_json::Value> {
     Ok(info)
 }
 
+/// Properties of the first video stream of a file
+#[derive(Debug, Clone)]
+pub struct VideoStreamInfo {
+    pub width: u32,
+    pub height: u32,
+    pub duration: f64,                 // Seconds, 0.0 if unknown
+    pub frame_rate: Option<FrameRate>, // r_frame_rate, e.g. 30000/1001
+    pub color_primaries: Option<String>,
+    pub color_space: Option<String>, // Matrix coefficients
+    pub color_transfer: Option<String>,
+    pub color_range: Option<String>,        // "tv" or "pc"
+    pub sample_aspect: Option<(u32, u32)>,  // Flagged SAR, e.g. 32:27
+    pub display_aspect: Option<(u32, u32)>, // Flagged DAR, e.g. 16:9
+}
+
+/// Probe the first video stream of a file
+pub fn probe_video_stream(input: &Path) -> Result<VideoStreamInfo> {
+    let info = get_media_info(input)?;
+    let stream = info
+        .get("streams")
+        .and_then(|s| s.as_array())
+        .and_then(|streams| {
+            streams
+                .iter()
+                .find(|s| s.get("codec_type").and_then(|c| c.as_str()) == Some("video"))
+        })
+        .with_context(|| format!("No video stream found in {}", input.display()))?;
+
+    Ok(VideoStreamInfo {
+        width: stream.get("width").and_then(|w| w.as_u64()).unwrap_or(0) as u32,
+        height: stream.get("height").and_then(|h| h.as_u64()).unwrap_or(0) as u32,
+        duration: info
+            .get("format")
+            .and_then(|f| f.get("duration"))
+            .and_then(|d| d.as_str())
+            .and_then(|d| d.parse().ok())
+            .unwrap_or(0.0),
+        frame_rate: stream_tag(stream, "r_frame_rate").and_then(|r| FrameRate::parse(&r)),
+        color_primaries: stream_tag(stream, "color_primaries"),
+        color_space: stream_tag(stream, "color_space"),
+        color_transfer: stream_tag(stream, "color_transfer"),
+        color_range: stream_tag(stream, "color_range"),
+        sample_aspect: stream_tag(stream, "sample_aspect_ratio").and_then(|r| parse_ratio(&r)),
+        display_aspect: stream_tag(stream, "display_aspect_ratio").and_then(|r| parse_ratio(&r)),
+    })
+}
+
+/// Parse an ffprobe ratio such as "32:27"; "0:1" means unset
+fn parse_ratio(ratio: &str) -> Option<(u32, u32)> {
+    let (num, den) = ratio.split_once(':')?;
+    let (num, den) = (num.parse().ok()?, den.parse().ok()?);
+    (num > 0 && den > 0).then_some((num, den))
+}
+
+/// Number of streams of a type ("video", "audio", "subtitle") in a file
+pub fn count_streams(input: &Path, codec_type: &str) -> Result<usize> {
+    let info = get_media_info(input)?;
+    Ok(info
+        .get("streams")
+        .and_then(|s| s.as_array())
+        .map(|streams| {
+            streams
+                .iter()
+                .filter(|s| s.get("codec_type").and_then(|c| c.as_str()) == Some(codec_type))
+                .count()
+        })
+        .unwrap_or(0))
+}
+
+/// Duration of a stream ("v:0", "a:0") from the end of its last packet, for
+/// files whose container does not record one (e.g. raw or unfinished captures)
+pub fn probe_packet_duration(input: &Path, stream: &str) -> Result<f64> {
+    check_ffprobe()?;
+
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            stream,
+            "-show_entries",
+            "packet=pts_time,duration_time",
+            "-of",
+            "csv=p=0",
+        ])
+        .arg(input)
+        .output()
+        .context("Failed to execute ffprobe")?;
+
+    if !output.status.success() {
+        anyhow::bail!("Failed to read packets of {}", input.display());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.trim().trim_end_matches(',').split(',');
+            let pts: f64 = fields.next()?.parse().ok()?;
+            let duration: f64 = fields.next().and_then(|d| d.parse().ok()).unwrap_or(0.0);
+            Some(pts + duration)
+        })
+        .fold(0.0, f64::max))
+}
+
+/// Read a string property of a stream, treating "unknown" as absent
+fn stream_tag(stream: &serde_json::Value, key: &str) -> Option<String> {
+    stream
+        .get(key)
+        .and_then(|v| v.as_str())
+        .filter(|v| !v.is_empty() && *v != "unknown")
+        .map(|v| v.to_string())
+}
+
 /// Convert media files
 pub fn convert_media(
     input: &Path,
@@ -85,7 +239,7 @@ pub fn convert_media(
 ) -> Result<()> {
     check_ffmpeg()?;
 
-    let mut args = vec![
+    let args = vec![
         "-i".to_string(),
         input.to_string_lossy().to_string(),
         "-c:v".to_string(),
@@ -113,6 +267,23 @@ pub fn build_filtergraph(filters: &[&str]) -> String {
     filters.join(",")
 }
 
+/// A path as a filter option value (e.g. `stats_file=`) inside a
+/// filtergraph: escaped once for the option parser (`\ ' :`) and again for
+/// the graph parser (`\ ' [ ] , ;`)
+pub fn filter_path(path: &Path) -> String {
+    let escape = |s: &str, special: &str| {
+        let mut out = String::with_capacity(s.len());
+        for c in s.chars() {
+            if special.contains(c) {
+                out.push('\\');
+            }
+            out.push(c);
+        }
+        out
+    };
+    escape(&escape(&path.display().to_string(), "\\':"), "\\'[],;")
+}
+
 /// Build a complex filtergraph with multiple inputs/outputs
 pub fn build_complex_filtergraph(
     video_filters: &[&str],
@@ -151,8 +322,16 @@ pub fn probe_device_caps(device: &str) -> Result<DeviceCaps> {
             anyhow::bail!("v4l2-ctl failed: {} (device: {})", stderr.trim(), device)
         }
         Err(e) => {
-            eprintln!("v4l2-ctl not available: {}. Install v4l-utils for better detection.", e);
-            Ok(DeviceCaps { width: 640, height: 480, fps: 30, formats: vec!["yuyv422".into()] })
+            eprintln!(
+                "v4l2-ctl not available: {}. Install v4l-utils for better detection.",
+                e
+            );
+            Ok(DeviceCaps {
+                width: 640,
+                height: 480,
+                fps: FrameRate::NTSC,
+                formats: vec!["yuyv422".into()],
+            })
         }
     }
 }
@@ -162,7 +341,7 @@ fn parse_v4l2_output(output: &str) -> Result<DeviceCaps> {
     let mut formats = Vec::new();
     let mut width = 0;
     let mut height = 0;
-    let mut fps_num: Option<f32> = None;
+    let mut fps_num: Option<FrameRate> = None;
 
     for line in output.lines() {
         if line.contains("Pixel Format") {
@@ -180,9 +359,10 @@ fn parse_v4l2_output(output: &str) -> Result<DeviceCaps> {
         } else if line.contains("Interval: Discrete") {
             if let Some(fps_str) = line.split('(').nth(1) {
                 if let Some(fps_val) = fps_str.split_whitespace().next() {
-                    if let Ok(v) = fps_val.parse::<f32>() {
-                        if v.is_finite() && v > 0.0 && v < 1000.0 {
-                            fps_num = Some(v);
+                    if let Ok(v) = fps_val.parse::<f64>() {
+                        // 29.970 fps is reported as a decimal, keep it exact
+                        if let Some(rate) = FrameRate::from_f64(v) {
+                            fps_num = Some(rate);
                         }
                     }
                 }
@@ -190,11 +370,10 @@ fn parse_v4l2_output(output: &str) -> Result<DeviceCaps> {
         }
     }
 
-    let fps_u32 = fps_num.map(|v| v.round() as u32).unwrap_or(30);
     Ok(DeviceCaps {
         width,
         height,
-        fps: fps_u32,
+        fps: fps_num.unwrap_or(FrameRate::NTSC),
         formats,
     })
 }
@@ -203,6 +382,6 @@ fn parse_v4l2_output(output: &str) -> Result<DeviceCaps> {
 pub struct DeviceCaps {
     pub width: u32,
     pub height: u32,
-    pub fps: u32,
+    pub fps: FrameRate,
     pub formats: Vec<String>,
 }
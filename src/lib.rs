@@ -4,4 +4,7 @@ pub mod ffmpeg;
 pub mod audio;
 pub mod video;
 pub mod capture;
+pub mod trim;
+pub mod preset;
+pub mod queue;
 pub mod ai;
@@ -1,5 +1,5 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use colored::*;
 
 use rayon::prelude::*;
@@ -11,6 +11,9 @@ mod ai;
 mod audio;
 mod capture;
 mod ffmpeg;
+mod preset;
+mod queue;
+mod trim;
 mod video;
 
 #[derive(Parser)]
@@ -22,7 +25,292 @@ struct Cli {
     command: Commands,
 }
 
+/// Video encoder settings shared by the enhancement commands
+#[derive(Args)]
+struct EncoderArgs {
+    /// Video codec: x264, x265, svt-av1, aom, vp9, ffv1, prores, dnxhr
+    #[arg(long)]
+    vcodec: Option<String>,
+
+    /// Constant quality (CRF) for x264/x265/AV1/VP9
+    #[arg(long, conflicts_with = "vbitrate")]
+    crf: Option<u32>,
+
+    /// Average video bitrate instead of CRF (e.g., 8M)
+    #[arg(long)]
+    vbitrate: Option<String>,
+
+    /// Encoder preset (x264/x265 name, SVT-AV1/libaom speed, VP9 deadline)
+    #[arg(long)]
+    encoder_preset: Option<String>,
+
+    /// Encoder tuning (e.g., film, grain)
+    #[arg(long)]
+    tune: Option<String>,
+
+    /// Pixel format: yuv420p, yuv420p10le, yuv422p, yuv422p10le, yuv422p16le (FFV1)
+    #[arg(long)]
+    pix_fmt: Option<String>,
+
+    /// Keyframe interval (GOP length) in frames
+    #[arg(long)]
+    gop: Option<u32>,
+
+    /// Use closed GOPs
+    #[arg(long)]
+    closed_gop: bool,
+
+    /// Codec profile (e.g., high10, main10, hq, dnxhr_hq)
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Codec level (e.g., 4.1)
+    #[arg(long)]
+    level: Option<String>,
+}
+
+/// Pixel aspect settings for SD (720/704 wide) sources
+#[derive(Args)]
+struct AspectArgs {
+    /// Non-square SD pixels: keep (anamorphic, flag the SAR) or square (resample the width)
+    #[arg(long)]
+    anamorphic: Option<String>,
+
+    /// Display aspect of the SD source: 4:3 or 16:9 [default: from the source's SAR/DAR flags, else 4:3]
+    #[arg(long)]
+    display_aspect: Option<String>,
+
+    /// Pixel aspect convention: itu (704 active samples, 10:11 / 12:11) or mpeg (full 720 raster, 8:9 / 16:15)
+    #[arg(long, default_value = "itu")]
+    par: String,
+}
+
+/// Frame rate conversion settings
+#[derive(Args)]
+struct RateArgs {
+    /// Convert to this frame rate (e.g., 25, 30000/1001, 29.97, ntsc, pal)
+    #[arg(long, conflicts_with = "standards")]
+    fps: Option<String>,
+
+    /// Frame rate conversion: drop (drop/duplicate), blend or interpolate (motion-compensated)
+    #[arg(long, default_value = "drop")]
+    fps_mode: String,
+
+    /// Film-speed standards conversion with pitch-corrected audio: pal-to-ntsc (25 -> 23.976) or ntsc-to-pal (23.976 -> 25)
+    #[arg(long)]
+    standards: Option<String>,
+}
+
+/// Target-based scaling settings
+#[derive(Args)]
+struct ScaleArgs {
+    /// Scale to a target raster keeping the aspect ratio: 480p, 576p, 720p, 1080p, 1440p, 2160p or WxH
+    #[arg(long)]
+    target: Option<String>,
+
+    /// Fit inside the target (fit) or pillarbox/letterbox to exactly the target raster (pad)
+    #[arg(long, default_value = "fit")]
+    fit: String,
+
+    /// Scaler: lanczos, spline36 (zscale) or bicubic
+    #[arg(long, default_value = "lanczos")]
+    scaler: String,
+}
+
+/// Sharpening settings
+#[derive(Args)]
+struct SharpenArgs {
+    /// Sharpening method: unsharp or cas (contrast-adaptive) [default: unsharp]
+    #[arg(long)]
+    sharpen_method: Option<String>,
+
+    /// Luma sharpening strength (unsharp 0.0-2.0, default 0.8; cas 0.0-1.0, default 0.5)
+    #[arg(long)]
+    sharpen_luma: Option<f32>,
+
+    /// Luma sharpening radius in pixels, 1-11 (unsharp only, default 2)
+    #[arg(long)]
+    sharpen_luma_radius: Option<u32>,
+
+    /// Chroma sharpening strength (unsharp default 0.4; cas default 0, off)
+    #[arg(long)]
+    sharpen_chroma: Option<f32>,
+
+    /// Chroma sharpening radius in pixels, 1-11 (unsharp only, default 1)
+    #[arg(long)]
+    sharpen_chroma_radius: Option<u32>,
+
+    /// Do not sharpen regions the denoiser found noisy (--sharpen-protect-noise
+    /// false turns off a preset's protection)
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    sharpen_protect_noise: Option<bool>,
+}
+
+/// Part of the input to process
+#[derive(Args)]
+struct TrimArgs {
+    /// Start processing at this time (seconds, MM:SS or HH:MM:SS.ms)
+    #[arg(long)]
+    start: Option<String>,
+
+    /// Stop processing at this time on the source timeline
+    #[arg(long)]
+    end: Option<String>,
+
+    /// Ranges to remove, e.g. 0-0:12,1:02:00-1:03:10 (an open end cuts to the end)
+    #[arg(long)]
+    cut: Option<String>,
+
+    /// Crossfade the audio at each --cut join (seconds)
+    #[arg(long, requires = "cut")]
+    crossfade: Option<f64>,
+}
+
+#[derive(Args)]
+struct EnhanceVideoArgs {
+    /// Restoration preset: built-in or user preset name, or a .toml/.json file
+    #[arg(long)]
+    preset: Option<String>,
+
+    /// Deinterlace (on by default; --deinterlace false turns it off)
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    deinterlace: Option<bool>,
+
+    /// Source scan type: detect, progressive, tff, bff, telecine (default: auto parity)
+    #[arg(long)]
+    scan_type: Option<String>,
+
+    /// Deinterlacing algorithm: bwdif, yadif, w3fdif, estdif, nnedi [default: bwdif]
+    #[arg(long)]
+    deinterlace_method: Option<String>,
+
+    /// Weights file for the nnedi deinterlacer (nnedi3_weights.bin)
+    #[arg(long)]
+    nnedi_weights: Option<PathBuf>,
+
+    /// Deinterlacer output rate: field (one frame per field) or frame (original rate) [default: field]
+    #[arg(long)]
+    field_rate: Option<String>,
+
+    /// Detect and remove black borders/letterboxing (cropdetect)
+    #[arg(long)]
+    autocrop: bool,
+
+    /// Crop rectangle w:h:x:y (overrides --autocrop)
+    #[arg(long)]
+    crop: Option<String>,
+
+    /// Mask head-switching noise/overscan: auto, none, or top,bottom,left,right lines
+    #[arg(long)]
+    overscan: Option<String>,
+
+    /// Overscan handling: pad (black, keep raster) or crop
+    #[arg(long, default_value = "pad")]
+    overscan_mode: String,
+
+    /// Chroma delay correction: auto (edge alignment) or x,y in chroma pixels
+    #[arg(long)]
+    chroma_shift: Option<String>,
+
+    /// Chroma-only denoise strength: spatial[:temporal], independent of luma denoise
+    #[arg(long)]
+    chroma_denoise: Option<String>,
+
+    /// Stabilize (deshake); off by default
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    stabilize: Option<bool>,
+
+    /// Denoise type: none, hqdn3d, nlmeans [default: hqdn3d]
+    #[arg(long)]
+    denoise: Option<String>,
+
+    /// Sharpen (on by default; --sharpen false turns it off)
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    sharpen: Option<bool>,
+
+    #[command(flatten)]
+    sharpening: SharpenArgs,
+
+    /// Color adjustment (on by default; --color false turns it off)
+    #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+    color: Option<bool>,
+
+    /// Measure the source and apply automatic levels and gray-world white balance (replaces --color)
+    #[arg(long)]
+    auto_color: bool,
+
+    /// Auto color mode: smooth (10 s windows, smoothed) or scene (per detected scene)
+    #[arg(long, default_value = "smooth")]
+    auto_color_mode: String,
+
+    /// Auto color strength (0.0-1.0)
+    #[arg(long, default_value = "0.7")]
+    auto_color_strength: f32,
+
+    /// Scale width
+    #[arg(long)]
+    width: Option<u32>,
+
+    /// Scale height
+    #[arg(long)]
+    height: Option<u32>,
+
+    /// Display aspect ratio (e.g., 4:3, 16:9)
+    #[arg(long)]
+    aspect: Option<String>,
+
+    #[command(flatten)]
+    pixel_aspect: AspectArgs,
+
+    #[command(flatten)]
+    rate: RateArgs,
+
+    #[command(flatten)]
+    scale: ScaleArgs,
+
+    /// Filter at 10 or 16 bits in 4:2:2 (kept for x265/FFV1/ProRes, dithered for 8-bit outputs)
+    #[arg(long)]
+    bit_depth: Option<u32>,
+}
+
+/// Audio enhancement settings for `restore`, named apart from the video flags
+#[derive(Args)]
+struct RestoreAudioArgs {
+    /// Skip the audio denoise (afftdn)
+    #[arg(long)]
+    no_audio_denoise: bool,
+
+    /// Skip loudness normalization
+    #[arg(long)]
+    no_normalize: bool,
+
+    /// High-pass filter frequency (Hz) [default: 80]
+    #[arg(long)]
+    highpass: Option<u32>,
+
+    /// Low-pass filter frequency (Hz, optional)
+    #[arg(long)]
+    lowpass: Option<u32>,
+
+    /// Notch filter for hum removal (50 or 60 Hz)
+    #[arg(long)]
+    notch: Option<u32>,
+
+    /// Skip the compressor
+    #[arg(long)]
+    no_compressor: bool,
+
+    /// Skip the noise gate
+    #[arg(long)]
+    no_gate: bool,
+
+    /// AAC bitrate of the restored audio
+    #[arg(long, default_value = "192k")]
+    audio_bitrate: String,
+}
+
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 enum Commands {
     /// Convert audio/video files to different formats
     Convert {
@@ -50,9 +338,20 @@ enum Commands {
         #[arg(short = 'c', long)]
         codec: Option<String>,
 
+        /// Take the video encoder from a preset: name or .toml/.json file;
+        /// --codec overrides it
+        #[arg(long)]
+        preset: Option<String>,
+
         /// Number of parallel jobs
         #[arg(short = 'j', long, default_value = "4")]
         jobs: usize,
+
+        #[command(flatten)]
+        scale: ScaleArgs,
+
+        #[command(flatten)]
+        trim: TrimArgs,
     },
     /// Enhance audio with denoise, normalization, and compression
     EnhanceAudio {
@@ -64,17 +363,17 @@ enum Commands {
         #[arg(short, long)]
         output: PathBuf,
 
-        /// Enable denoising (afftdn)
-        #[arg(long, default_value = "true")]
-        denoise: bool,
+        /// Denoise with afftdn (on by default; --denoise false turns it off)
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        denoise: Option<bool>,
 
-        /// Enable loudness normalization
-        #[arg(long, default_value = "true")]
-        normalize: bool,
+        /// Loudness normalization (on by default; --normalize false turns it off)
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        normalize: Option<bool>,
 
-        /// High-pass filter frequency (Hz)
-        #[arg(long, default_value = "80")]
-        highpass: u32,
+        /// High-pass filter frequency (Hz) [default: 80]
+        #[arg(long)]
+        highpass: Option<u32>,
 
         /// Low-pass filter frequency (Hz, optional)
         #[arg(long)]
@@ -84,13 +383,20 @@ enum Commands {
         #[arg(long)]
         notch: Option<u32>,
 
-        /// Enable compressor
-        #[arg(long, default_value = "true")]
-        compressor: bool,
+        /// Compressor (on by default; --compressor false turns it off)
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        compressor: Option<bool>,
+
+        /// Noise gate (on by default; --gate false turns it off)
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        gate: Option<bool>,
+
+        /// Restoration preset: built-in or user preset name, or a .toml/.json file
+        #[arg(long)]
+        preset: Option<String>,
 
-        /// Enable noise gate
-        #[arg(long, default_value = "true")]
-        gate: bool,
+        #[command(flatten)]
+        trim: TrimArgs,
     },
     /// Enhance video with deinterlace, stabilization, denoise, and sharpening
     EnhanceVideo {
@@ -102,37 +408,61 @@ enum Commands {
         #[arg(short, long)]
         output: PathBuf,
 
-        /// Enable deinterlacing (bwdif)
-        #[arg(long, default_value = "true")]
-        deinterlace: bool,
+        #[command(flatten)]
+        enhance: EnhanceVideoArgs,
 
-        /// Enable stabilization (deshake)
+        /// Read line-21 (EIA-608) captions from the input and add them as a subtitle track
         #[arg(long)]
-        stabilize: bool,
+        captions: bool,
 
-        /// Denoise type: none, hqdn3d, nlmeans
-        #[arg(long, default_value = "hqdn3d")]
-        denoise: String,
+        /// Read VITC from the input, report discontinuities and set the output start timecode
+        #[arg(long)]
+        vitc: bool,
 
-        /// Enable sharpening
-        #[arg(long, default_value = "true")]
-        sharpen: bool,
+        #[command(flatten)]
+        encoder: EncoderArgs,
 
-        /// Enable color adjustment
-        #[arg(long, default_value = "true")]
-        color: bool,
+        #[command(flatten)]
+        trim: TrimArgs,
+    },
+    /// Render a short before/after comparison using the enhance-video options
+    Compare {
+        /// Input file
+        #[arg(short, long)]
+        input: PathBuf,
 
-        /// Scale width
-        #[arg(long)]
-        width: Option<u32>,
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
 
-        /// Scale height
-        #[arg(long)]
-        height: Option<u32>,
+        /// Layout: side (side by side), stacked, or wipe (split screen)
+        #[arg(long, default_value = "side")]
+        layout: String,
+
+        /// Height of each pane
+        #[arg(long, default_value = "480")]
+        pane_height: u32,
 
-        /// Display aspect ratio (e.g., 4:3, 16:9)
+        /// Wipe position as a fraction of the width (0.1-0.9)
+        #[arg(long, default_value = "0.5")]
+        wipe: f32,
+
+        /// Length of the segment in seconds when --end is not given
+        #[arg(long, default_value = "10")]
+        duration: f64,
+
+        /// Leave out the Source/Enhanced captions
         #[arg(long)]
-        aspect: Option<String>,
+        no_labels: bool,
+
+        #[command(flatten)]
+        enhance: EnhanceVideoArgs,
+
+        #[command(flatten)]
+        encoder: EncoderArgs,
+
+        #[command(flatten)]
+        trim: TrimArgs,
     },
     /// VHS Rescue: One-click preset for analog capture cleanup
     VhsRescue {
@@ -144,9 +474,366 @@ enum Commands {
         #[arg(short, long)]
         output: PathBuf,
 
+        /// Restoration preset: built-in or user preset name, or a .toml/.json file
+        #[arg(long)]
+        preset: Option<String>,
+
         /// Notch filter for hum removal (50 or 60 Hz)
         #[arg(long)]
         notch: Option<u32>,
+
+        /// Override detected scan type: progressive, tff, bff, telecine
+        #[arg(long)]
+        scan_type: Option<String>,
+
+        /// Mask head-switching noise/overscan: auto (default), none, or top,bottom,left,right lines
+        #[arg(long)]
+        overscan: Option<String>,
+
+        /// Overscan handling: pad (black, keep raster) or crop
+        #[arg(long, default_value = "pad")]
+        overscan_mode: String,
+
+        /// Replace frames damaged by tape dropouts: interpolate or neighbour
+        #[arg(long)]
+        repair_dropouts: Option<String>,
+
+        /// Read line-21 (EIA-608) captions from the input and add them as a subtitle track
+        #[arg(long)]
+        captions: bool,
+
+        /// Read VITC from the input, report discontinuities and set the output start timecode
+        #[arg(long)]
+        vitc: bool,
+
+        #[command(flatten)]
+        pixel_aspect: AspectArgs,
+
+        #[command(flatten)]
+        rate: RateArgs,
+
+        #[command(flatten)]
+        scale: ScaleArgs,
+
+        /// Filter at 10 or 16 bits in 4:2:2 (kept for x265/FFV1/ProRes, dithered for 8-bit outputs)
+        #[arg(long)]
+        bit_depth: Option<u32>,
+
+        /// Stabilize (deshake; on by default, --stabilize false turns it off)
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        stabilize: Option<bool>,
+
+        #[command(flatten)]
+        encoder: EncoderArgs,
+
+        #[command(flatten)]
+        trim: TrimArgs,
+    },
+    /// Film Rescue: preset for telecined or scanned 8mm/Super 8/16mm film
+    FilmRescue {
+        /// Input file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// How the film was digitised: telecine (video transfer with pulldown) or scan
+        #[arg(long, default_value = "telecine")]
+        source: String,
+
+        /// Film speed in frames per second: 16, 18 (Super 8) or 24 (sound film)
+        #[arg(long, default_value = "18")]
+        speed: String,
+
+        /// Dust and scratch reduction: off, light or strong
+        #[arg(long, default_value = "light")]
+        dust: String,
+
+        /// Skip deflickering
+        #[arg(long)]
+        no_deflicker: bool,
+
+        /// Skip gate weave stabilization
+        #[arg(long)]
+        no_stabilize: bool,
+
+        /// Skip the grain-preserving denoise
+        #[arg(long)]
+        no_denoise: bool,
+
+        /// Enhance the soundtrack (otherwise any audio is copied)
+        #[arg(long)]
+        audio: bool,
+
+        /// Restoration preset for the soundtrack and encoder: name or .toml/.json file
+        #[arg(long)]
+        preset: Option<String>,
+
+        /// Filter at 10 or 16 bits in 4:2:2 (kept for x265/FFV1/ProRes, dithered for 8-bit outputs)
+        #[arg(long)]
+        bit_depth: Option<u32>,
+
+        #[command(flatten)]
+        encoder: EncoderArgs,
+
+        #[command(flatten)]
+        trim: TrimArgs,
+    },
+    /// Restore video and audio in one pass with the enhance-video and audio options
+    Restore {
+        /// Input file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        #[command(flatten)]
+        enhance: EnhanceVideoArgs,
+
+        #[command(flatten)]
+        audio: RestoreAudioArgs,
+
+        /// Copy the video stream instead of restoring it
+        #[arg(long, conflicts_with = "cut")]
+        copy_video: bool,
+
+        /// Copy the audio stream instead of restoring it
+        #[arg(long, conflicts_with_all = ["no_audio", "cut"])]
+        copy_audio: bool,
+
+        /// Leave the audio out
+        #[arg(long)]
+        no_audio: bool,
+
+        /// Input video stream to use (0 = first)
+        #[arg(long, default_value = "0", conflicts_with = "cut")]
+        video_stream: usize,
+
+        /// Input audio stream to use (0 = first)
+        #[arg(long, default_value = "0", conflicts_with = "cut")]
+        audio_stream: usize,
+
+        /// Read line-21 (EIA-608) captions from the input and add them as a subtitle track
+        #[arg(long)]
+        captions: bool,
+
+        /// Read VITC from the input, report discontinuities and set the output start timecode
+        #[arg(long, conflicts_with = "copy_video")]
+        vitc: bool,
+
+        #[command(flatten)]
+        encoder: EncoderArgs,
+
+        #[command(flatten)]
+        trim: TrimArgs,
+    },
+    /// Remove time ranges (blue screen, snow, blank stretches) and join the rest
+    Cut {
+        /// Input file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Output file
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Re-encode even when the cuts fall on keyframes
+        #[arg(long)]
+        reencode: bool,
+
+        /// Take the encoder for re-encoding from a preset: name or .toml/.json file
+        #[arg(long)]
+        preset: Option<String>,
+
+        #[command(flatten)]
+        trim: TrimArgs,
+
+        #[command(flatten)]
+        encoder: EncoderArgs,
+    },
+    /// Extract line-21 (EIA-608) closed captions to SRT/WebVTT
+    Captions {
+        /// Unscaled, uncropped capture that still has line 21
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Subtitle file to write (.srt or .vtt)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Subtitle format: srt or vtt (default: from the output extension)
+        #[arg(long)]
+        format: Option<String>,
+
+        /// Caption channel: 1 (CC1) or 2 (CC2)
+        #[arg(long, default_value = "1")]
+        channel: u8,
+
+        /// Add the captions as a subtitle track to this file (e.g. the enhanced output)
+        #[arg(long)]
+        mux: Option<PathBuf>,
+    },
+    /// Read VITC timecode, report discontinuities and set the start timecode
+    Timecode {
+        /// Unscaled, uncropped capture that still has the vertical interval
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Use the VITC log written by `capture --vitc-log` instead of reading the input
+        #[arg(long)]
+        log: Option<PathBuf>,
+
+        /// Write the report to a file instead of only printing it
+        #[arg(long)]
+        report: Option<PathBuf>,
+
+        /// Write a copy of the input with the start timecode set
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Compare a file with its reference: PSNR, SSIM and VMAF (when ffmpeg has libvmaf)
+    Metrics {
+        /// File to check (e.g. a re-encode)
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Reference file (e.g. the master)
+        #[arg(short, long)]
+        reference: PathBuf,
+
+        /// Write per-frame values to a CSV file
+        #[arg(long)]
+        csv: Option<PathBuf>,
+
+        /// Write per-frame values and the summary to a JSON file
+        #[arg(long)]
+        json: Option<PathBuf>,
+
+        /// Skip VMAF
+        #[arg(long)]
+        no_vmaf: bool,
+    },
+    /// Detect tape dropouts (signalstats TOUT/VREP/BRNG) and optionally repair them
+    Dropouts {
+        /// Input file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Write the report to a file instead of only printing it
+        #[arg(long)]
+        report: Option<PathBuf>,
+
+        /// Write a repaired copy with badly damaged frames replaced
+        #[arg(long)]
+        repair: Option<PathBuf>,
+
+        /// Repair method: interpolate (motion-compensated) or neighbour (repeat previous frame)
+        #[arg(long, default_value = "interpolate")]
+        repair_mode: String,
+
+        /// TOUT (temporal outlier fraction) above which a frame is suspect
+        #[arg(long, default_value = "0.005")]
+        tout_threshold: f32,
+
+        /// TOUT above which a frame is replaced when repairing
+        #[arg(long, default_value = "0.02")]
+        repair_threshold: f32,
+
+        /// Take the encoder for the repaired copy from a preset: name or .toml/.json file
+        #[arg(long)]
+        preset: Option<String>,
+
+        #[command(flatten)]
+        encoder: EncoderArgs,
+
+        #[command(flatten)]
+        trim: TrimArgs,
+    },
+    /// Detect scene cuts and write them as chapters, a chapter list or clips
+    Scenes {
+        /// Input file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Scene change score (0-1) above which a frame starts a new scene
+        #[arg(long, default_value = "0.3")]
+        threshold: f32,
+
+        /// Shortest scene in seconds; cuts closer than this are ignored
+        #[arg(long, default_value = "5")]
+        min_length: f64,
+
+        /// Copy the input to this MKV/MP4 file with the scenes as chapters
+        #[arg(long)]
+        chapters: Option<PathBuf>,
+
+        /// Export the chapter list to a file
+        #[arg(long)]
+        export: Option<PathBuf>,
+
+        /// Chapter list format: text, ogm or ffmetadata
+        #[arg(long, default_value = "text")]
+        format: String,
+
+        /// Split the input into one clip per scene in this directory
+        #[arg(long)]
+        split: Option<PathBuf>,
+
+        /// Stream-copy the clips instead of re-encoding (cuts snap to keyframes)
+        #[arg(long)]
+        copy: bool,
+
+        /// Take the encoder for the clips from a preset: name or .toml/.json file
+        #[arg(long)]
+        preset: Option<String>,
+
+        #[command(flatten)]
+        encoder: EncoderArgs,
+
+        #[command(flatten)]
+        trim: TrimArgs,
+    },
+    /// Write a contact sheet and/or one thumbnail per scene
+    Thumbnails {
+        /// Input file
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Contact sheet file (default: <input>_sheet.<format> unless --scenes is given)
+        #[arg(long)]
+        sheet: Option<PathBuf>,
+
+        /// Write one thumbnail per detected scene to this directory
+        #[arg(long)]
+        scenes: Option<PathBuf>,
+
+        /// Contact sheet grid, columns x rows
+        #[arg(long, default_value = "4x4")]
+        grid: String,
+
+        /// Width of each thumbnail in pixels
+        #[arg(long, default_value = "320")]
+        width: u32,
+
+        /// Image format: jpg, png or webp
+        #[arg(long, default_value = "jpg")]
+        format: String,
+
+        /// Leave the timestamps off the contact sheet
+        #[arg(long)]
+        no_timestamps: bool,
+
+        /// Scene change score (0-1) for --scenes
+        #[arg(long, default_value = "0.3")]
+        threshold: f32,
+
+        /// Shortest scene in seconds for --scenes
+        #[arg(long, default_value = "5")]
+        min_length: f64,
     },
     /// List available V4L2 video and ALSA audio capture devices
     CaptureList,
@@ -168,13 +855,25 @@ enum Commands {
         #[arg(long, default_value = "mp4")]
         format: String,
 
-        /// Enable deinterlacing
-        #[arg(long, default_value = "true")]
-        deinterlace: bool,
+        /// Deinterlace (on by default; --deinterlace false turns it off)
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        deinterlace: Option<bool>,
+
+        /// Deinterlacing algorithm: bwdif, yadif, w3fdif, estdif, nnedi [default: bwdif]
+        #[arg(long)]
+        deinterlace_method: Option<String>,
+
+        /// Weights file for the nnedi deinterlacer (nnedi3_weights.bin)
+        #[arg(long)]
+        nnedi_weights: Option<PathBuf>,
 
-        /// Enable stabilization
+        /// Deinterlacer output rate: field (one frame per field) or frame (original rate) [default: field]
         #[arg(long)]
-        stabilize: bool,
+        field_rate: Option<String>,
+
+        /// Stabilize (deshake); off by default
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        stabilize: Option<bool>,
 
         /// Denoise type: none, hqdn3d, nlmeans
         #[arg(long)]
@@ -196,9 +895,9 @@ enum Commands {
         #[arg(long)]
         height: Option<u32>,
 
-        /// Frame rate
+        /// Frame rate (e.g., 30000/1001, 29.97, 25, ntsc, pal)
         #[arg(long)]
-        fps: Option<u32>,
+        fps: Option<String>,
 
         /// Audio bitrate (e.g., 192k)
         #[arg(long, default_value = "192k")]
@@ -207,6 +906,28 @@ enum Commands {
         /// Archival mode (lossless/near-lossless)
         #[arg(long)]
         archival: bool,
+
+        /// Read VITC (professional tapes) and log each frame's timecode to this file
+        #[arg(long)]
+        vitc_log: Option<PathBuf>,
+
+        /// Take the deinterlacing, stabilization and denoise settings from a preset
+        #[arg(long)]
+        preset: Option<String>,
+    },
+    /// List, show and save restoration presets
+    Preset {
+        #[command(subcommand)]
+        action: PresetCommand,
+    },
+    /// Queue commands to run one after another, surviving crashes and reboots
+    Queue {
+        /// Queue file (default: ~/.local/state/fluxara-avc/queue.json)
+        #[arg(long, global = true)]
+        file: Option<PathBuf>,
+
+        #[command(subcommand)]
+        action: QueueCommand,
     },
     /// Clean and optimize media files
     Clean {
@@ -236,6 +957,78 @@ enum Commands {
     Formats,
 }
 
+#[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
+enum PresetCommand {
+    /// List built-in and user presets
+    List,
+    /// Print a preset's settings
+    Show {
+        /// Preset name or .toml/.json file
+        name: String,
+
+        /// Output format: toml or json
+        #[arg(long, default_value = "toml")]
+        format: String,
+    },
+    /// Save a preset to the user preset directory (or to a .toml/.json path)
+    Save {
+        /// New preset name or .toml/.json file
+        name: String,
+
+        /// Preset to start from (default: the enhance-video/enhance-audio defaults)
+        #[arg(long)]
+        from: Option<String>,
+
+        /// Description shown by `preset list`
+        #[arg(long)]
+        description: Option<String>,
+
+        /// File format when saving by name: toml or json
+        #[arg(long, default_value = "toml")]
+        format: String,
+
+        /// Replace an existing preset file
+        #[arg(long)]
+        force: bool,
+
+        #[command(flatten)]
+        encoder: EncoderArgs,
+    },
+}
+
+#[derive(Subcommand)]
+enum QueueCommand {
+    /// Add a command, e.g. `queue add --priority 5 -- vhs-rescue -i tape.mkv -o out.mkv`
+    Add {
+        /// Higher priorities run first
+        #[arg(long, default_value = "0", allow_hyphen_values = true)]
+        priority: i32,
+
+        /// Times to run the job again after it fails
+        #[arg(long, default_value = "0")]
+        retries: u32,
+
+        /// fluxara-avc command and its options
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// List jobs and their states
+    List,
+    /// Run queued jobs until none are left, resuming interrupted ones
+    Run,
+    /// Cancel queued or running jobs
+    Cancel {
+        #[arg(required = true)]
+        ids: Vec<u64>,
+    },
+    /// Queue failed or cancelled jobs again
+    Retry {
+        #[arg(required = true)]
+        ids: Vec<u64>,
+    },
+}
+
 fn main() -> Result<()> {
     print_banner();
 
@@ -244,87 +1037,617 @@ fn main() -> Result<()> {
     match &cli.command {
         Commands::Convert {
             input,
-            format,
+            format,
+            output,
+            recursive,
+            quality,
+            codec,
+            preset,
+            jobs,
+            scale,
+            trim,
+        } => {
+            let encoder = match preset {
+                Some(spec) => Some(
+                    load_preset(spec)?
+                        .video
+                        .with_context(|| format!("Preset '{}' has no video settings", spec))?
+                        .encoder,
+                ),
+                None => None,
+            };
+            convert_files(
+                input,
+                format,
+                output.as_ref(),
+                *recursive,
+                quality,
+                codec.as_ref(),
+                encoder.as_ref(),
+                parse_scale_options(scale)?.as_ref(),
+                &parse_trim(trim)?,
+                parse_cut_list(trim)?.as_ref(),
+                *jobs,
+            )?;
+        }
+        Commands::EnhanceAudio {
+            input,
+            output,
+            denoise,
+            normalize,
+            highpass,
+            lowpass,
+            notch,
+            compressor,
+            gate,
+            preset,
+            trim,
+        } => {
+            // Flags given on the command line override the preset
+            let base = match preset {
+                Some(spec) => load_preset(spec)?
+                    .audio
+                    .with_context(|| format!("Preset '{}' has no audio settings", spec))?,
+                None => audio::AudioEnhanceOptions::default(),
+            };
+            let opts = audio::AudioEnhanceOptions {
+                denoise: denoise.unwrap_or(base.denoise),
+                normalize: normalize.unwrap_or(base.normalize),
+                highpass_freq: highpass.or(base.highpass_freq),
+                lowpass_freq: lowpass.or(base.lowpass_freq),
+                notch_freq: notch.or(base.notch_freq),
+                compressor: compressor.unwrap_or(base.compressor),
+                gate: gate.unwrap_or(base.gate),
+                ..base
+            };
+            with_cuts(input, output, trim, |input, trim| {
+                println!("{} Enhancing audio...", "✓".green());
+                audio::enhance_audio(input, output, &audio::AudioEnhanceOptions { trim, ..opts })
+            })?;
+            println!("{} Audio enhancement completed!", "✓".green());
+        }
+        Commands::EnhanceVideo {
+            input,
+            output,
+            enhance,
+            captions,
+            vitc,
+            encoder,
+            trim,
+        } => {
+            with_cuts(input, output, trim, |input, trim| {
+                let opts = build_enhance_options(input, enhance, trim)?;
+                let mut opts = video::VideoEnhanceOptions {
+                    encoder: build_encoder(encoder, opts.encoder.clone())?,
+                    ..opts
+                };
+                if *vitc {
+                    opts.timecode = read_timecode(input, &opts.trim, opts.frame_rate.as_ref())?;
+                }
+                print_color_warnings(input)?;
+                println!("{} Enhancing video...", "✓".green());
+                video::enhance_video(input, output, &opts)?;
+                if *captions {
+                    add_captions(input, output, &opts.trim, opts.frame_rate.as_ref())?;
+                }
+                Ok(())
+            })?;
+            println!("{} Video enhancement completed!", "✓".green());
+        }
+        Commands::Compare {
+            input,
+            output,
+            layout,
+            pane_height,
+            wipe,
+            duration,
+            no_labels,
+            enhance,
+            encoder,
+            trim,
+        } => {
+            let compare = video::CompareOptions {
+                layout: video::CompareLayout::from_name(layout)
+                    .with_context(|| format!("Unknown layout '{}'", layout))?,
+                height: *pane_height,
+                wipe: *wipe,
+                labels: !*no_labels,
+                duration: *duration,
+            };
+            with_cuts(input, output, trim, |input, trim| {
+                let opts = build_enhance_options(input, enhance, trim)?;
+                let opts = video::VideoEnhanceOptions {
+                    encoder: build_encoder(encoder, opts.encoder.clone())?,
+                    ..opts
+                };
+                println!("{} Rendering comparison...", "🎬".bright_cyan());
+                video::compare::render_comparison(input, output, &opts, &compare)
+            })?;
+            println!("{} Comparison written to {}", "✓".green(), output.display());
+        }
+        Commands::VhsRescue {
+            input,
+            output,
+            preset,
+            notch,
+            scan_type,
+            overscan,
+            overscan_mode,
+            repair_dropouts,
+            captions,
+            vitc,
+            pixel_aspect,
+            rate,
+            scale,
+            bit_depth,
+            stabilize,
+            encoder,
+            trim,
+        } => {
+            let overscan = match overscan.as_deref() {
+                Some("auto") | None => None,
+                Some(spec) => Some(parse_overscan(spec, overscan_mode)?),
+            };
+            let (base_video, base_audio) = match preset {
+                Some(spec) => {
+                    let preset = load_preset(spec)?;
+                    let video = preset
+                        .video
+                        .with_context(|| format!("Preset '{}' has no video settings", spec))?;
+                    (video, preset.audio)
+                }
+                None => (
+                    video::VhsRescueOptions::default_video(),
+                    Some(video::VhsRescueOptions::default_audio()),
+                ),
+            };
+            let mut opts = video::VhsRescueOptions {
+                encoder: Some(build_encoder(encoder, base_video.encoder.clone())?),
+                video: video::VideoEnhanceOptions {
+                    stabilize: stabilize.unwrap_or(base_video.stabilize),
+                    ..base_video
+                },
+                audio: base_audio,
+                notch_freq: *notch,
+                scan_type: scan_type.as_deref().map(parse_scan_type).transpose()?,
+                overscan,
+                overscan_mode: parse_overscan_mode(overscan_mode)?,
+                repair_dropouts: repair_dropouts
+                    .as_deref()
+                    .map(parse_repair_mode)
+                    .transpose()?,
+                aspect: parse_aspect_options(pixel_aspect)?,
+                frame_rate: parse_rate_conversion(rate)?,
+                scale: parse_scale_options(scale)?,
+                depth: parse_depth(*bit_depth)?,
+                trim: trim::Trim::default(),
+                timecode: None,
+            };
+            with_cuts(input, output, trim, |input, trim| {
+                opts.trim = trim;
+                if *vitc {
+                    opts.timecode = read_timecode(input, &opts.trim, opts.frame_rate.as_ref())?;
+                }
+                println!("{} Starting VHS Rescue...", "🎬".bright_cyan());
+                print_color_warnings(input)?;
+                video::vhs_rescue(input, output, &opts)?;
+                if *captions {
+                    add_captions(input, output, &opts.trim, opts.frame_rate.as_ref())?;
+                }
+                Ok(())
+            })?;
+            println!("{} VHS Rescue completed!", "✓".green());
+        }
+        Commands::FilmRescue {
+            input,
+            output,
+            source,
+            speed,
+            dust,
+            no_deflicker,
+            no_stabilize,
+            no_denoise,
+            audio,
+            preset,
+            bit_depth,
+            encoder,
+            trim,
+        } => {
+            let preset = preset.as_deref().map(load_preset).transpose()?;
+            let preset_video = preset.as_ref().and_then(|p| p.video.as_ref());
+            let film_encoder = match preset_video {
+                Some(video) => video.encoder.clone(),
+                None => video::VideoEncoder {
+                    tune: Some("grain".to_string()),
+                    ..video::VideoEncoder::archival()
+                },
+            };
+            let soundtrack = match preset.as_ref().and_then(|p| p.audio.clone()) {
+                Some(audio) => Some(audio),
+                None if *audio => Some(video::FilmRescueOptions::soundtrack_audio()),
+                None => None,
+            };
+            let opts = video::FilmRescueOptions {
+                source: video::FilmSource::from_name(source)
+                    .with_context(|| format!("Unknown film source '{}'", source))?,
+                speed: video::FrameRate::parse(speed)
+                    .with_context(|| format!("Invalid film speed '{}'", speed))?,
+                dust: video::DustRemoval::from_name(dust)
+                    .with_context(|| format!("Unknown dust removal '{}'", dust))?,
+                deflicker: !*no_deflicker,
+                stabilize: !*no_stabilize,
+                denoise: !*no_denoise,
+                audio: soundtrack,
+                encoder: Some(build_encoder(encoder, film_encoder)?),
+                depth: parse_depth(*bit_depth)?.or(preset_video.and_then(|v| v.depth)),
+                trim: trim::Trim::default(),
+            };
+            with_cuts(input, output, trim, |input, trim| {
+                println!("{} Starting Film Rescue...", "🎬".bright_cyan());
+                print_color_warnings(input)?;
+                video::film_rescue(input, output, &video::FilmRescueOptions { trim, ..opts })
+            })?;
+            println!("{} Film Rescue completed!", "✓".green());
+        }
+        Commands::Restore {
+            input,
+            output,
+            enhance,
+            audio,
+            copy_video,
+            copy_audio,
+            no_audio,
+            video_stream,
+            audio_stream,
+            captions,
+            vitc,
+            encoder,
+            trim,
+        } => {
+            with_cuts(input, output, trim, |input, trim| {
+                let video_opts = if *copy_video {
+                    None
+                } else {
+                    let opts = build_enhance_options(input, enhance, trim)?;
+                    let mut opts = video::VideoEnhanceOptions {
+                        encoder: build_encoder(encoder, opts.encoder.clone())?,
+                        ..opts
+                    };
+                    if *vitc {
+                        opts.timecode = read_timecode(input, &trim, opts.frame_rate.as_ref())?;
+                    }
+                    Some(opts)
+                };
+                let audio_opts = if *copy_audio || *no_audio {
+                    None
+                } else {
+                    build_restore_audio(audio, enhance.preset.as_deref())?
+                };
+                let opts = video::RestoreOptions {
+                    audio: audio_opts,
+                    video_stream: *video_stream,
+                    audio_stream: (!*no_audio).then_some(*audio_stream),
+                    audio_bitrate: audio.audio_bitrate.clone(),
+                    trim,
+                    video: video_opts,
+                };
+                if opts.video.is_some() {
+                    print_color_warnings(input)?;
+                }
+                println!("{} Restoring video and audio...", "🎬".bright_cyan());
+                video::restore::restore(input, output, &opts)?;
+                if *captions {
+                    let rate = opts.video.as_ref().and_then(|v| v.frame_rate);
+                    add_captions(input, output, &trim, rate.as_ref())?;
+                }
+                Ok(())
+            })?;
+            println!("{} Restore completed!", "✓".green());
+        }
+        Commands::Cut {
+            input,
+            output,
+            reencode,
+            preset,
+            trim,
+            encoder,
+        } => {
+            let cuts = parse_cut_list(trim)?.context("Give the ranges to remove with --cut")?;
+            let opts = trim::CutOptions {
+                crossfade: cuts.crossfade,
+                reencode: *reencode,
+                encoder: build_encoder(encoder, preset_encoder(preset.as_deref())?)?,
+                intermediate: false,
+            };
+            println!(
+                "{} Cutting {} range(s)...",
+                "✂".bright_cyan(),
+                cuts.ranges.len()
+            );
+            match trim::cut_segments(input, output, &cuts.ranges, &opts)? {
+                trim::CutMethod::StreamCopy => {
+                    println!("{} Cut completed without re-encoding!", "✓".green())
+                }
+                trim::CutMethod::Reencode => {
+                    println!("{} Cut completed (re-encoded)!", "✓".green())
+                }
+            }
+        }
+        Commands::Captions {
+            input,
             output,
-            recursive,
-            quality,
-            codec,
-            jobs,
+            format,
+            channel,
+            mux,
         } => {
-            convert_files(
-                input,
-                format,
-                output.as_ref(),
-                *recursive,
-                quality,
-                codec.as_ref(),
-                *jobs,
-            )?;
+            if output.is_none() && mux.is_none() {
+                anyhow::bail!("Nothing to do: give --output and/or --mux");
+            }
+            println!("{} Reading line-21 captions...", "🔍".bright_cyan());
+            let captions = video::captions::extract_captions(input, *channel)?;
+            println!("{} Decoded {} caption(s)", "✓".green(), captions.len());
+            if let Some(path) = output {
+                let format = match format.as_deref() {
+                    Some(name) => video::SubtitleFormat::from_name(name)
+                        .with_context(|| format!("Unknown subtitle format '{}'", name))?,
+                    None => video::SubtitleFormat::from_path(path),
+                };
+                std::fs::write(path, video::captions::subtitle_file(&captions, format))
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                println!("{} Captions written to {}", "✓".green(), path.display());
+            }
+            if let Some(path) = mux {
+                if captions.is_empty() {
+                    println!("{} No captions to add", "ℹ".bright_blue());
+                } else {
+                    video::captions::add_caption_track(path, &captions)?;
+                    println!("{} Caption track added to {}", "✓".green(), path.display());
+                }
+            }
         }
-        Commands::EnhanceAudio {
+        Commands::Timecode {
             input,
+            log,
+            report,
             output,
-            denoise,
-            normalize,
-            highpass,
-            lowpass,
-            notch,
-            compressor,
-            gate,
         } => {
-            let opts = audio::AudioEnhanceOptions {
-                denoise: *denoise,
-                normalize: *normalize,
-                highpass_freq: Some(*highpass),
-                lowpass_freq: *lowpass,
-                notch_freq: *notch,
-                compressor: *compressor,
-                gate: *gate,
-                gate_threshold: -50.0,
+            let vitc = match log {
+                Some(log) => {
+                    // The log counts the source frames; the input's rate is
+                    // only a fallback, as a field-rate capture doubles it
+                    let stream = ffmpeg::probe_video_stream(input)?;
+                    video::timecode::read_vitc_log(log, stream.frame_rate)?
+                }
+                None => {
+                    println!("{} Reading VITC...", "🔍".bright_cyan());
+                    video::timecode::read_vitc(input)?
+                }
             };
-            println!("{} Enhancing audio...", "✓".green());
-            audio::enhance_audio(input, output, &opts)?;
-            println!("{} Audio enhancement completed!", "✓".green());
+            let text = vitc.to_text();
+            print!("{}", text);
+            if let Some(path) = report {
+                std::fs::write(path, &text)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                println!("{} Report written to {}", "✓".green(), path.display());
+            }
+            if let Some(path) = output {
+                let start = vitc.start.context("No VITC found, cannot set a timecode")?;
+                video::timecode::write_timecode(input, path, start)?;
+                println!(
+                    "{} Start timecode {} written to {}",
+                    "✓".green(),
+                    start,
+                    path.display()
+                );
+            }
         }
-        Commands::EnhanceVideo {
+        Commands::Metrics {
             input,
-            output,
-            deinterlace,
-            stabilize,
-            denoise,
-            sharpen,
-            color,
-            width,
-            height,
-            aspect,
+            reference,
+            csv,
+            json,
+            no_vmaf,
         } => {
-            let denoise_type = match denoise.as_str() {
-                "none" => video::DenoiseType::None,
-                "nlmeans" => video::DenoiseType::Nlmeans,
-                _ => video::DenoiseType::Hqdn3d,
+            let opts = video::MetricsOptions {
+                vmaf: !*no_vmaf,
+                ..Default::default()
             };
-            let opts = video::VideoEnhanceOptions {
-                deinterlace: *deinterlace,
-                stabilize: *stabilize,
-                denoise: denoise_type,
-                sharpen: *sharpen,
-                color_adjust: *color,
-                scale_width: *width,
-                scale_height: *height,
-                aspect_ratio: aspect.clone(),
-            };
-            println!("{} Enhancing video...", "✓".green());
-            video::enhance_video(input, output, &opts)?;
-            println!("{} Video enhancement completed!", "✓".green());
+            println!("{} Measuring quality...", "🔍".bright_cyan());
+            let report = video::metrics::compare_metrics(input, reference, &opts)?;
+            print!("{}", report.to_text());
+            if report.vmaf.is_none() && !*no_vmaf {
+                println!("{} VMAF skipped: ffmpeg has no libvmaf", "ℹ".bright_blue());
+            }
+            if let Some(path) = csv {
+                std::fs::write(path, report.to_csv())
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                println!(
+                    "{} Per-frame CSV written to {}",
+                    "✓".green(),
+                    path.display()
+                );
+            }
+            if let Some(path) = json {
+                std::fs::write(path, report.to_json()?)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+                println!("{} JSON report written to {}", "✓".green(), path.display());
+            }
         }
-        Commands::VhsRescue {
+        Commands::Dropouts {
             input,
-            output,
-            notch,
+            report,
+            repair,
+            repair_mode,
+            tout_threshold,
+            repair_threshold,
+            preset,
+            encoder,
+            trim,
         } => {
-            println!("{} Starting VHS Rescue...", "🎬".bright_cyan());
-            video::vhs_rescue(input, output, *notch)?;
-            println!("{} VHS Rescue completed!", "✓".green());
+            // A --cut intermediate goes next to the repaired copy, or the input
+            let beside = repair.as_deref().unwrap_or(input);
+            with_cuts(input, beside, trim, |input, trim| {
+                let opts = video::DropoutOptions {
+                    tout_threshold: *tout_threshold,
+                    repair_threshold: *repair_threshold,
+                    trim,
+                    ..Default::default()
+                };
+                println!("{} Scanning for tape dropouts...", "🔍".bright_cyan());
+                let result = video::dropout::analyze_dropouts(input, &opts)?;
+                let text = result.to_text();
+                print!("{}", text);
+                if let Some(path) = report {
+                    std::fs::write(path, &text)
+                        .with_context(|| format!("Failed to write report {}", path.display()))?;
+                    println!("{} Report written to {}", "✓".green(), path.display());
+                }
+                if let Some(output) = repair {
+                    let plan = video::dropout::plan_repair(
+                        input,
+                        &result,
+                        parse_repair_mode(repair_mode)?,
+                    )?;
+                    println!(
+                        "{} Repairing {} damaged frames...",
+                        "✓".green(),
+                        plan.frames.len()
+                    );
+                    let base = match preset {
+                        Some(spec) => preset_encoder(Some(spec))?,
+                        None => video::VideoEncoder::archival(),
+                    };
+                    let encoder = build_encoder(encoder, base)?;
+                    video::dropout::repair_dropouts(input, output, &plan, &encoder, &trim)?;
+                    println!("{} Dropout repair completed!", "✓".green());
+                }
+                Ok(())
+            })?;
+        }
+        Commands::Scenes {
+            input,
+            threshold,
+            min_length,
+            chapters,
+            export,
+            format,
+            split,
+            copy,
+            preset,
+            encoder,
+            trim,
+        } => {
+            let format = video::ChapterFormat::from_name(format)
+                .with_context(|| format!("Unknown chapter format '{}'", format))?;
+            // Clips are named after the original even when split from the cut
+            let original = input;
+            let cut_next_to = chapters.as_deref().unwrap_or(input);
+            with_cuts(input, cut_next_to, trim, |input, trim| {
+                let opts = video::SceneOptions {
+                    threshold: *threshold,
+                    min_length: *min_length,
+                    trim,
+                };
+                println!("{} Detecting scenes...", "🔍".bright_cyan());
+                let scenes = video::scenes::detect_scenes(input, &opts)?;
+                println!("{} Found {} scene(s)", "✓".green(), scenes.len());
+                print!(
+                    "{}",
+                    video::scenes::chapter_list(&scenes, video::ChapterFormat::Text)
+                );
+
+                if let Some(path) = export {
+                    std::fs::write(path, video::scenes::chapter_list(&scenes, format))
+                        .with_context(|| {
+                            format!("Failed to write chapter list {}", path.display())
+                        })?;
+                    println!("{} Chapter list written to {}", "✓".green(), path.display());
+                }
+                if let Some(output) = chapters {
+                    video::scenes::write_chapters(input, output, &scenes)?;
+                    println!("{} Chapters written to {}", "✓".green(), output.display());
+                }
+                if let Some(dir) = split {
+                    let encoder = if *copy {
+                        None
+                    } else {
+                        Some(build_encoder(encoder, preset_encoder(preset.as_deref())?)?)
+                    };
+                    let clips = video::scenes::split_scenes(
+                        input,
+                        original,
+                        dir,
+                        &scenes,
+                        encoder.as_ref(),
+                    )?;
+                    println!(
+                        "{} Wrote {} clip(s) to {}",
+                        "✓".green(),
+                        clips.len(),
+                        dir.display()
+                    );
+                }
+                Ok(())
+            })?;
+        }
+        Commands::Thumbnails {
+            input,
+            sheet,
+            scenes,
+            grid,
+            width,
+            format,
+            no_timestamps,
+            threshold,
+            min_length,
+        } => {
+            let (columns, rows) = video::thumbnails::parse_grid(grid)
+                .with_context(|| format!("Invalid grid '{}', expected COLSxROWS", grid))?;
+            let opts = video::ThumbnailOptions {
+                columns,
+                rows,
+                width: *width,
+                timestamps: !*no_timestamps,
+                format: video::ImageFormat::from_name(format)
+                    .with_context(|| format!("Unsupported image format '{}'", format))?,
+            };
+
+            let sheet = sheet.clone().or_else(|| {
+                scenes.is_none().then(|| {
+                    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+                    input.with_file_name(format!("{}_sheet.{}", stem, opts.format.extension()))
+                })
+            });
+            if let Some(path) = sheet {
+                println!(
+                    "{} Building {}x{} contact sheet...",
+                    "🖼".bright_cyan(),
+                    columns,
+                    rows
+                );
+                video::thumbnails::contact_sheet(input, &path, &opts)?;
+                println!(
+                    "{} Contact sheet written to {}",
+                    "✓".green(),
+                    path.display()
+                );
+            }
+            if let Some(dir) = scenes {
+                let scene_opts = video::SceneOptions {
+                    threshold: *threshold,
+                    min_length: *min_length,
+                    ..Default::default()
+                };
+                println!("{} Detecting scenes...", "🔍".bright_cyan());
+                let found = video::scenes::detect_scenes(input, &scene_opts)?;
+                let images = video::thumbnails::scene_thumbnails(input, dir, &found, &opts)?;
+                println!(
+                    "{} Wrote {} scene thumbnail(s) to {}",
+                    "✓".green(),
+                    images.len(),
+                    dir.display()
+                );
+            }
         }
         Commands::CaptureList => {
             println!("{} Available V4L2 Video Devices:", "📹".bright_cyan());
@@ -365,6 +1688,9 @@ fn main() -> Result<()> {
             audio_device,
             format,
             deinterlace,
+            deinterlace_method,
+            nnedi_weights,
+            field_rate,
             stabilize,
             denoise,
             vbitrate,
@@ -374,25 +1700,59 @@ fn main() -> Result<()> {
             fps,
             abitrate,
             archival,
+            vitc_log,
+            preset,
         } => {
             let capture_format = match format.as_str() {
                 "mkv" => capture::CaptureFormat::Mkv,
                 _ => capture::CaptureFormat::Mp4,
             };
+            // Flags given on the command line override the preset's
+            // filtering; without a preset capture does not denoise
+            let base = match preset {
+                Some(spec) => Some(
+                    load_preset(spec)?
+                        .video
+                        .with_context(|| format!("Preset '{}' has no video settings", spec))?,
+                ),
+                None => None,
+            };
+            let defaults = base.clone().unwrap_or_default();
             let opts = capture::CaptureOptions {
                 format: capture_format,
                 video_device: video_device.clone(),
                 audio_device: audio_device.clone(),
-                deinterlace: *deinterlace,
-                stabilize: *stabilize,
-                denoise: denoise.clone(),
+                deinterlace: deinterlace.unwrap_or(defaults.deinterlace),
+                deinterlace_method: match deinterlace_method {
+                    Some(name) => parse_deinterlace_method(name, nnedi_weights.as_ref())?,
+                    None => defaults.deinterlace_method,
+                },
+                field_rate: match field_rate {
+                    Some(rate) => parse_field_rate(rate)?,
+                    None => defaults.field_rate,
+                },
+                stabilize: stabilize.unwrap_or(defaults.stabilize),
+                denoise: denoise.clone().or_else(|| {
+                    base.and_then(|b| match b.denoise {
+                        video::DenoiseType::None => None,
+                        video::DenoiseType::Hqdn3d => Some("hqdn3d".to_string()),
+                        video::DenoiseType::Nlmeans => Some("nlmeans".to_string()),
+                    })
+                }),
                 video_bitrate: vbitrate.clone(),
                 crf: *crf,
                 width: *width,
                 height: *height,
-                fps: *fps,
+                fps: match fps.as_deref() {
+                    Some(spec) => Some(
+                        video::FrameRate::parse(spec)
+                            .with_context(|| format!("Invalid frame rate '{}'", spec))?,
+                    ),
+                    None => None,
+                },
                 audio_bitrate: abitrate.clone(),
                 archival_mode: *archival,
+                vitc_log: vitc_log.clone(),
             };
             println!(
                 "{} Starting capture from {} and {}...",
@@ -403,7 +1763,27 @@ fn main() -> Result<()> {
             println!("{}", "Press Ctrl+C to stop recording".yellow());
             capture::capture(output, &opts)?;
             println!("{} Capture completed!", "✓".green());
+            if let Some(log) = vitc_log {
+                let vitc = video::timecode::read_vitc_log(log, opts.fps)?;
+                print!("{}", vitc.to_text());
+                if let Some(start) = vitc.start {
+                    // A field-rate capture counts twice the tape's frames
+                    let start = match ffmpeg::probe_video_stream(output)?.frame_rate {
+                        Some(rate) => start.at_rate(vitc.fps, video::timecode::nominal_fps(rate)),
+                        None => start,
+                    };
+                    video::timecode::set_timecode(output, start)?;
+                    println!(
+                        "{} Start timecode {} set on {}",
+                        "✓".green(),
+                        start,
+                        output.display()
+                    );
+                }
+            }
         }
+        Commands::Preset { action } => run_preset_command(action)?,
+        Commands::Queue { file, action } => run_queue_command(file.as_deref(), action)?,
         Commands::Clean {
             input,
             metadata,
@@ -431,6 +1811,7 @@ fn print_banner() {
     println!();
 }
 
+#[allow(clippy::too_many_arguments)]
 fn convert_files(
     input: &Path,
     format: &str,
@@ -438,6 +1819,10 @@ fn convert_files(
     recursive: bool,
     quality: &str,
     codec: Option<&String>,
+    encoder: Option<&video::VideoEncoder>,
+    scale: Option<&video::ScaleOptions>,
+    trim: &trim::Trim,
+    cuts: Option<&trim::CutList>,
     jobs: usize,
 ) -> Result<()> {
     check_ffmpeg()?;
@@ -464,7 +1849,9 @@ fn convert_files(
 
     pool.install(|| {
         files.par_iter().for_each(|file| {
-            match convert_file(file, format, output_dir, quality, codec) {
+            match convert_file(
+                file, format, output_dir, quality, codec, encoder, scale, trim, cuts,
+            ) {
                 Ok(_) => {
                     println!("{} Converted: {}", "✓".green(), file.display());
                 }
@@ -481,12 +1868,17 @@ fn convert_files(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn convert_file(
     input: &Path,
     format: &str,
     output_dir: &Path,
     quality: &str,
     codec: Option<&String>,
+    encoder: Option<&video::VideoEncoder>,
+    scale: Option<&video::ScaleOptions>,
+    trim: &trim::Trim,
+    cuts: Option<&trim::CutList>,
 ) -> Result<()> {
     let file_stem = input.file_stem().context("Invalid filename")?;
     let output_file = output_dir.join(format!("{}.{}", file_stem.to_string_lossy(), format));
@@ -505,34 +1897,66 @@ fn convert_file(
         }
     }
 
-    let mut cmd = Command::new("ffmpeg");
-    cmd.arg("-i")
-        .arg(input)
-        .arg("-y")
-        .arg("-loglevel")
-        .arg("error");
+    // A --target scale also converts the colour, e.g. BT.601 -> BT.709 for HD
+    let (video_filter, color_args) = match scale {
+        Some(scale) if !is_audio_format(format) => {
+            let stream = ffmpeg::probe_video_stream(input)?;
+            let conversion = video::color::plan_conversion(&stream, scale.target.height);
+            let mut filters: Vec<String> = conversion.filter().into_iter().collect();
+            filters.extend(scale.filters());
+            (Some(filters.join(",")), conversion.output_args())
+        }
+        _ => (None, Vec::new()),
+    };
 
-    if is_audio_format(format) {
-        cmd.arg("-b:a").arg(quality);
-    } else {
-        if let Some(c) = codec {
-            cmd.arg("-c:v").arg(c);
+    let convert = |input: &Path, trim: &trim::Trim| -> Result<()> {
+        let mut cmd = Command::new("ffmpeg");
+        cmd.args(trim.input_args())
+            .arg("-i")
+            .arg(input)
+            .arg("-y")
+            .arg("-loglevel")
+            .arg("error");
+
+        if is_audio_format(format) {
+            cmd.arg("-b:a").arg(quality);
+        } else {
+            match (codec, encoder) {
+                (Some(c), _) => {
+                    cmd.arg("-c:v").arg(c);
+                }
+                (None, Some(encoder)) => {
+                    cmd.args(encoder.args());
+                }
+                (None, None) => {}
+            }
+            if let Some(ref vf) = video_filter {
+                cmd.arg("-vf").arg(vf);
+            }
+            cmd.args(&color_args);
+            cmd.arg("-b:a").arg(quality);
         }
-        cmd.arg("-b:a").arg(quality);
-    }
 
-    cmd.arg(&output_file)
-        .stdout(Stdio::null())
-        .stderr(Stdio::piped());
+        cmd.arg(&output_file)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+
+        let output = cmd.output().context("Failed to execute ffmpeg")?;
 
-    let output = cmd.output().context("Failed to execute ffmpeg")?;
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("FFmpeg error: {}", error);
+        }
 
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("FFmpeg error: {}", error);
-    }
+        Ok(())
+    };
 
-    Ok(())
+    match cuts {
+        Some(cuts) => cuts.process(input, &output_file, |input| {
+            convert(input, &trim::Trim::default())
+        }),
+        None => convert(input, trim),
+    }
 }
 
 fn clean_files(input: &Path, remove_metadata: bool, optimize: bool, recursive: bool) -> Result<()> {
@@ -751,6 +2175,818 @@ fn is_audio_format(format: &str) -> bool {
     )
 }
 
+fn print_color_warnings(input: &Path) -> Result<()> {
+    for warning in video::color::check_color_tags(input)? {
+        println!("{} {}", "⚠".yellow(), warning);
+    }
+    Ok(())
+}
+
+fn parse_scan_type(scan_type: &str) -> Result<video::ScanType> {
+    video::ScanType::from_name(scan_type).with_context(|| {
+        format!(
+            "Unknown scan type '{}', expected progressive, tff, bff or telecine",
+            scan_type
+        )
+    })
+}
+
+fn parse_deinterlace_method(
+    method: &str,
+    nnedi_weights: Option<&PathBuf>,
+) -> Result<video::DeinterlaceMethod> {
+    Ok(match method {
+        "bwdif" => video::DeinterlaceMethod::Bwdif,
+        "yadif" => video::DeinterlaceMethod::Yadif,
+        "w3fdif" => video::DeinterlaceMethod::W3fdif,
+        "estdif" => video::DeinterlaceMethod::Estdif,
+        "nnedi" => video::DeinterlaceMethod::Nnedi {
+            weights: nnedi_weights
+                .cloned()
+                .context("nnedi requires --nnedi-weights <nnedi3_weights.bin>")?,
+        },
+        _ => anyhow::bail!(
+            "Unknown deinterlacing method '{}', expected bwdif, yadif, w3fdif, estdif or nnedi",
+            method
+        ),
+    })
+}
+
+fn parse_field_rate(rate: &str) -> Result<video::FieldRate> {
+    video::FieldRate::from_name(rate)
+        .with_context(|| format!("Unknown field rate '{}', expected field or frame", rate))
+}
+
+/// Parse an overscan mask given as "none" or "top,bottom,left,right"
+fn parse_overscan(spec: &str, mode: &str) -> Result<video::OverscanMask> {
+    let mode = parse_overscan_mode(mode)?;
+    if spec == "none" {
+        return Ok(video::OverscanMask {
+            top: 0,
+            bottom: 0,
+            left: 0,
+            right: 0,
+            mode,
+        });
+    }
+
+    let values = spec
+        .split(',')
+        .map(|v| v.trim().parse::<u32>())
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .with_context(|| format!("Invalid overscan '{}'", spec))?;
+    if values.len() != 4 {
+        anyhow::bail!("Overscan needs top,bottom,left,right (got '{}')", spec);
+    }
+
+    Ok(video::OverscanMask {
+        top: values[0],
+        bottom: values[1],
+        left: values[2],
+        right: values[3],
+        mode,
+    })
+}
+
+fn parse_overscan_mode(mode: &str) -> Result<video::OverscanMode> {
+    video::OverscanMode::from_name(mode)
+        .with_context(|| format!("Unknown overscan mode '{}', expected pad or crop", mode))
+}
+
+/// Build the encoder settings: codec defaults (or `base` when no codec is
+/// given) overridden by the explicit options
+fn build_encoder(args: &EncoderArgs, base: video::VideoEncoder) -> Result<video::VideoEncoder> {
+    let mut encoder = match args.vcodec.as_deref() {
+        Some(name) => video::VideoEncoder::new(
+            video::VideoCodec::from_name(name)
+                .with_context(|| format!("Unknown video codec '{}'", name))?,
+        ),
+        None => base,
+    };
+
+    if let Some(crf) = args.crf {
+        encoder.rate = video::RateControl::Crf(crf);
+    }
+    if let Some(ref bitrate) = args.vbitrate {
+        encoder.rate = video::RateControl::Bitrate(bitrate.clone());
+    }
+    if let Some(ref pix_fmt) = args.pix_fmt {
+        encoder.pix_fmt = Some(
+            video::PixelFormat::from_name(pix_fmt)
+                .with_context(|| format!("Unsupported pixel format '{}'", pix_fmt))?,
+        );
+    }
+    encoder.preset = args.encoder_preset.clone().or(encoder.preset);
+    encoder.tune = args.tune.clone().or(encoder.tune);
+    encoder.gop = args.gop.or(encoder.gop);
+    encoder.closed_gop |= args.closed_gop;
+    encoder.profile = args.profile.clone().or(encoder.profile);
+    encoder.level = args.level.clone().or(encoder.level);
+
+    Ok(encoder)
+}
+
+fn parse_aspect_options(args: &AspectArgs) -> Result<video::AspectOptions> {
+    Ok(video::AspectOptions {
+        display: args
+            .display_aspect
+            .as_deref()
+            .map(|name| {
+                video::DisplayAspect::from_name(name)
+                    .with_context(|| format!("Unknown display aspect '{}'", name))
+            })
+            .transpose()?,
+        convention: video::ParConvention::from_name(&args.par)
+            .with_context(|| format!("Unknown pixel aspect convention '{}'", args.par))?,
+        handling: match args.anamorphic.as_deref() {
+            Some(name) => video::AspectHandling::from_name(name)
+                .with_context(|| format!("Unknown anamorphic handling '{}'", name))?,
+            None => video::AspectHandling::Anamorphic,
+        },
+    })
+}
+
+fn print_pixel_aspect(width: u32, height: u32, par: &video::PixelAspect) {
+    match par.handling {
+        video::AspectHandling::Anamorphic => println!(
+            "{} {}x{} kept anamorphic with SAR {}:{}",
+            "ℹ".bright_blue(),
+            width,
+            height,
+            par.sar.0,
+            par.sar.1
+        ),
+        video::AspectHandling::SquarePixels => println!(
+            "{} {}x{} (SAR {}:{}) resampled to {}x{} square pixels",
+            "ℹ".bright_blue(),
+            width,
+            height,
+            par.sar.0,
+            par.sar.1,
+            video::aspect::square_width(width, par.sar),
+            height
+        ),
+    }
+}
+
+/// Video enhancement options from the enhance-video flags for the kept part of the input
+/// (the per-file analysis samples that part); the encoder is left at its default (or the
+/// preset's encoder)
+fn build_enhance_options(
+    input: &Path,
+    args: &EnhanceVideoArgs,
+    trim: trim::Trim,
+) -> Result<video::VideoEnhanceOptions> {
+    let EnhanceVideoArgs {
+        preset,
+        deinterlace,
+        scan_type,
+        deinterlace_method,
+        nnedi_weights,
+        field_rate,
+        autocrop,
+        crop,
+        overscan,
+        overscan_mode,
+        chroma_shift,
+        chroma_denoise,
+        stabilize,
+        denoise,
+        sharpen,
+        sharpening,
+        color,
+        auto_color,
+        auto_color_mode,
+        auto_color_strength,
+        width,
+        height,
+        aspect,
+        pixel_aspect,
+        rate,
+        scale,
+        bit_depth,
+    } = args;
+
+    // Flags given on the command line override the preset, which replaces
+    // the defaults; the per-file analysis below always comes from the flags
+    let base = match preset {
+        Some(spec) => load_preset(spec)?
+            .video
+            .with_context(|| format!("Preset '{}' has no video settings", spec))?,
+        None => video::VideoEnhanceOptions::default(),
+    };
+    let denoise_type = match denoise.as_deref() {
+        Some("none") => video::DenoiseType::None,
+        Some("hqdn3d") => video::DenoiseType::Hqdn3d,
+        Some("nlmeans") => video::DenoiseType::Nlmeans,
+        Some(other) => anyhow::bail!("Unknown denoise type '{}'", other),
+        None => base.denoise,
+    };
+    let deinterlace_method = match (deinterlace_method, base.deinterlace_method) {
+        (Some(name), _) => parse_deinterlace_method(name, nnedi_weights.as_ref())?,
+        (None, video::DeinterlaceMethod::Nnedi { weights }) => video::DeinterlaceMethod::Nnedi {
+            weights: nnedi_weights.clone().unwrap_or(weights),
+        },
+        (None, method) => method,
+    };
+    let sharpen = if sharpen.unwrap_or(base.sharpen.is_some()) {
+        Some(parse_sharpen_options(
+            sharpening,
+            base.sharpen.unwrap_or_default(),
+        )?)
+    } else {
+        None
+    };
+    let scan_type = match scan_type.as_deref() {
+        Some("detect") => {
+            let detected = video::interlace::detect_scan_type(input, &trim)?;
+            println!("{} Detected scan type: {:?}", "ℹ".bright_blue(), detected);
+            Some(detected)
+        }
+        Some(s) => Some(parse_scan_type(s)?),
+        None => None,
+    };
+    let crop = match crop {
+        Some(spec) => Some(
+            video::crop::parse_crop(spec)
+                .with_context(|| format!("Invalid crop '{}', expected w:h:x:y", spec))?,
+        ),
+        None if *autocrop => {
+            let detected = video::crop::detect_crop(input, &trim)?;
+            match detected {
+                Some(rect) => {
+                    println!("{} Detected crop: {}", "ℹ".bright_blue(), rect.filter())
+                }
+                None => println!("{} No black borders detected", "ℹ".bright_blue()),
+            }
+            detected
+        }
+        None => None,
+    };
+    let overscan = match overscan.as_deref() {
+        Some("auto") => {
+            let mask = video::overscan::detect_overscan(
+                input,
+                &trim,
+                parse_overscan_mode(overscan_mode)?,
+            )?;
+            println!(
+                "{} Detected overscan: top {} bottom {} left {} right {}",
+                "ℹ".bright_blue(),
+                mask.top,
+                mask.bottom,
+                mask.left,
+                mask.right
+            );
+            Some(mask)
+        }
+        Some(spec) => Some(parse_overscan(spec, overscan_mode)?),
+        None => None,
+    };
+    let chroma = if chroma_shift.is_some() || chroma_denoise.is_some() {
+        let (shift_x, shift_y) = match chroma_shift.as_deref() {
+            Some("auto") => {
+                let shift = video::chroma::detect_chroma_shift(input, &trim)?;
+                println!(
+                    "{} Detected chroma shift: {},{}",
+                    "ℹ".bright_blue(),
+                    shift.0,
+                    shift.1
+                );
+                shift
+            }
+            Some(spec) => parse_pair::<i32>(spec)
+                .with_context(|| format!("Invalid chroma shift '{}', expected x,y", spec))?,
+            None => (0, 0),
+        };
+        let (denoise_spatial, denoise_temporal) = match chroma_denoise.as_deref() {
+            Some(spec) => parse_chroma_denoise(spec)?,
+            None => (0.0, 0.0),
+        };
+        Some(video::ChromaCorrection {
+            shift_x,
+            shift_y,
+            denoise_spatial,
+            denoise_temporal,
+        })
+    } else {
+        None
+    };
+    let auto_color = if *auto_color {
+        let color_opts = video::AutoColorOptions {
+            mode: video::AutoColorMode::from_name(auto_color_mode).with_context(|| {
+                format!(
+                    "Unknown auto color mode '{}', expected smooth or scene",
+                    auto_color_mode
+                )
+            })?,
+            strength: auto_color_strength.clamp(0.0, 1.0),
+            ..Default::default()
+        };
+        println!(
+            "{} Measuring levels and white balance...",
+            "ℹ".bright_blue()
+        );
+        let correction = video::autocolor::analyze_color(input, &color_opts)?
+            .after_seek(trim.start.unwrap_or(0.0));
+        println!(
+            "{} Color correction over {} segment(s)",
+            "ℹ".bright_blue(),
+            correction.segments.len()
+        );
+        Some(correction)
+    } else {
+        None
+    };
+    let pixel_aspect = if pixel_aspect.anamorphic.is_some() {
+        let stream = ffmpeg::probe_video_stream(input)?;
+        let planned =
+            video::aspect::plan_pixel_aspect(&stream, &parse_aspect_options(pixel_aspect)?);
+        match planned {
+            Some(par) => print_pixel_aspect(stream.width, stream.height, &par),
+            None => println!(
+                "{} {}x{} is not an SD anamorphic raster, pixels left as they are",
+                "ℹ".bright_blue(),
+                stream.width,
+                stream.height
+            ),
+        }
+        planned
+    } else {
+        None
+    };
+    Ok(video::VideoEnhanceOptions {
+        deinterlace: deinterlace.unwrap_or(base.deinterlace),
+        scan_type: scan_type.or(base.scan_type),
+        deinterlace_method,
+        field_rate: match field_rate {
+            Some(rate) => parse_field_rate(rate)?,
+            None => base.field_rate,
+        },
+        crop,
+        overscan: overscan.or(base.overscan),
+        chroma: chroma.or(base.chroma),
+        stabilize: stabilize.unwrap_or(base.stabilize),
+        denoise: denoise_type,
+        sharpen,
+        color_adjust: color.unwrap_or(base.color_adjust),
+        frame_rate: parse_rate_conversion(rate)?.or(base.frame_rate),
+        auto_color,
+        pixel_aspect,
+        scale: parse_scale_options(scale)?.or(base.scale),
+        scale_width: width.or(base.scale_width),
+        scale_height: height.or(base.scale_height),
+        aspect_ratio: aspect.clone().or(base.aspect_ratio),
+        depth: parse_depth(*bit_depth)?.or(base.depth),
+        trim,
+        ..base
+    })
+}
+
+/// Audio options for `restore`: the preset's audio settings (None copies the
+/// audio) or the defaults, with the flags given on the command line on top
+fn build_restore_audio(
+    args: &RestoreAudioArgs,
+    preset: Option<&str>,
+) -> Result<Option<audio::AudioEnhanceOptions>> {
+    let base = match preset {
+        Some(spec) => match preset::load_preset(spec)?.audio {
+            Some(base) => base,
+            None => return Ok(None),
+        },
+        None => audio::AudioEnhanceOptions::default(),
+    };
+    Ok(Some(audio::AudioEnhanceOptions {
+        denoise: base.denoise && !args.no_audio_denoise,
+        normalize: base.normalize && !args.no_normalize,
+        highpass_freq: args.highpass.or(base.highpass_freq),
+        lowpass_freq: args.lowpass.or(base.lowpass_freq),
+        notch_freq: args.notch.or(base.notch_freq),
+        compressor: base.compressor && !args.no_compressor,
+        gate: base.gate && !args.no_gate,
+        ..base
+    }))
+}
+
+/// Encoder of a preset (the default without one or when it has no video
+/// settings), for commands that only re-encode
+fn preset_encoder(preset: Option<&str>) -> Result<video::VideoEncoder> {
+    Ok(match preset {
+        Some(spec) => load_preset(spec)?
+            .video
+            .map(|v| v.encoder)
+            .unwrap_or_default(),
+        None => video::VideoEncoder::default(),
+    })
+}
+
+fn load_preset(spec: &str) -> Result<preset::Preset> {
+    let (preset, source) = preset::find_preset(spec)?;
+    let origin = match source {
+        preset::PresetSource::BuiltIn => "built-in".to_string(),
+        preset::PresetSource::User(path) | preset::PresetSource::File(path) => {
+            path.display().to_string()
+        }
+    };
+    println!(
+        "{} Using preset {} ({})",
+        "ℹ".bright_blue(),
+        preset.name.bright_white(),
+        origin
+    );
+    Ok(preset)
+}
+
+fn run_preset_command(action: &PresetCommand) -> Result<()> {
+    let parse_format = |name: &str| {
+        preset::PresetFormat::from_name(name)
+            .with_context(|| format!("Unknown preset format '{}', expected toml or json", name))
+    };
+
+    match action {
+        PresetCommand::List => {
+            for (preset, source) in preset::list_presets()? {
+                let origin = match source {
+                    preset::PresetSource::BuiltIn => "built-in".dimmed().to_string(),
+                    preset::PresetSource::User(path) | preset::PresetSource::File(path) => {
+                        path.display().to_string().dimmed().to_string()
+                    }
+                };
+                println!(
+                    "{:<12} {}  {}",
+                    preset.name.bright_white(),
+                    preset.description,
+                    origin
+                );
+            }
+            if let Some(dir) = preset::user_preset_dir() {
+                println!("\nUser presets: {}", dir.display());
+            }
+        }
+        PresetCommand::Show { name, format } => {
+            let preset = preset::load_preset(name)?;
+            print!("{}", preset.to_text(parse_format(format)?)?);
+        }
+        PresetCommand::Save {
+            name,
+            from,
+            description,
+            format,
+            force,
+            encoder,
+        } => {
+            let mut preset = match from {
+                Some(spec) => preset::load_preset(spec)?,
+                None => preset::Preset {
+                    video: Some(video::VideoEnhanceOptions::default()),
+                    audio: Some(audio::AudioEnhanceOptions::default()),
+                    ..Default::default()
+                },
+            };
+            if let Some(description) = description {
+                preset.description = description.clone();
+            }
+            if let Some(ref mut video) = preset.video {
+                video.encoder = build_encoder(encoder, video.encoder.clone())?;
+            }
+
+            let path = Path::new(name);
+            let path = if preset::PresetFormat::from_path(path).is_some() {
+                path.to_path_buf()
+            } else {
+                preset::user_preset_dir()
+                    .context("Cannot find the config directory, set HOME or XDG_CONFIG_HOME")?
+                    .join(format!("{}.{}", name, parse_format(format)?.extension()))
+            };
+            if path.exists() && !*force {
+                anyhow::bail!(
+                    "{} already exists, use --force to replace it",
+                    path.display()
+                );
+            }
+            preset.save(&path)?;
+            println!("{} Preset saved to {}", "✓".green(), path.display());
+        }
+    }
+    Ok(())
+}
+
+fn run_queue_command(file: Option<&Path>, action: &QueueCommand) -> Result<()> {
+    let path = match file {
+        Some(path) => path.to_path_buf(),
+        None => queue::default_queue_path()
+            .context("Cannot find the state directory, set HOME or XDG_STATE_HOME")?,
+    };
+    let state = |state: queue::JobState| match state {
+        queue::JobState::Queued => state.name().bright_blue(),
+        queue::JobState::Running => state.name().bright_yellow(),
+        queue::JobState::Done => state.name().green(),
+        queue::JobState::Failed => state.name().red(),
+        queue::JobState::Cancelled => state.name().dimmed(),
+    };
+
+    match action {
+        QueueCommand::Add {
+            priority,
+            retries,
+            command,
+        } => {
+            // Check the command now rather than when it runs
+            let cli = Cli::try_parse_from(
+                std::iter::once("fluxara-avc").chain(command.iter().map(|s| s.as_str())),
+            )
+            .map_err(|e| anyhow::anyhow!("Invalid queued command: {}", e))?;
+            if matches!(cli.command, Commands::Queue { .. }) {
+                anyhow::bail!("Queue commands cannot be queued");
+            }
+            let dir = std::env::current_dir()?;
+            let id = queue::JobQueue::update(&path, |q| {
+                Ok(q.add(command.clone(), dir, *priority, *retries))
+            })?;
+            println!("{} Queued job {}", "✓".green(), id);
+        }
+        QueueCommand::List => {
+            let mut q = queue::JobQueue::load(&path)?;
+            q.recover(queue::job_alive);
+            if q.jobs.is_empty() {
+                println!("Queue is empty ({})", path.display());
+            }
+            for job in &q.jobs {
+                println!(
+                    "{:>4}  {:<9}  prio {:<3}  try {}/{}  {}",
+                    job.id,
+                    state(job.state),
+                    job.priority,
+                    job.attempts,
+                    job.max_attempts,
+                    job.command_line()
+                );
+                if let Some(ref error) = job.error {
+                    println!("      {}", error.dimmed());
+                }
+                if let (Some(ref log), queue::JobState::Failed) = (&job.log, job.state) {
+                    println!("      log: {}", log.display().to_string().dimmed());
+                }
+            }
+        }
+        QueueCommand::Run => {
+            let count = queue::run_queue(&path, &queue::cli_program(), |job| {
+                let log = job
+                    .log
+                    .as_ref()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_default();
+                // A failed job with attempts left is back in the queue
+                let state = match job.state {
+                    queue::JobState::Queued => "failed, retrying".yellow(),
+                    other => state(other),
+                };
+                println!(
+                    "{} Job {} {}: {}  {}",
+                    "▶".bright_cyan(),
+                    job.id,
+                    state,
+                    job.command_line(),
+                    log.dimmed()
+                );
+            })?;
+            println!("{} Queue finished, {} job(s) run", "✓".green(), count);
+        }
+        QueueCommand::Cancel { ids } => {
+            for id in ids {
+                // A job left running by a crashed runner has no process to stop
+                let pid = queue::JobQueue::update(&path, |q| {
+                    let alive = q.get(*id).is_some_and(queue::job_alive);
+                    Ok(q.cancel(*id)?.filter(|_| alive))
+                })?;
+                if let Some(pid) = pid {
+                    queue::kill_job(pid)?;
+                }
+                println!("{} Cancelled job {}", "✓".green(), id);
+            }
+        }
+        QueueCommand::Retry { ids } => {
+            for id in ids {
+                queue::JobQueue::update(&path, |q| q.retry(*id))?;
+                println!("{} Queued job {} again", "✓".green(), id);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Read the input's VITC and work out the timecode of the first output frame
+fn read_timecode(
+    input: &Path,
+    trim: &trim::Trim,
+    rate: Option<&video::RateConversion>,
+) -> Result<Option<video::Timecode>> {
+    println!("{} Reading VITC...", "🔍".bright_cyan());
+    let vitc = video::timecode::read_vitc(input)?;
+    print!("{}", vitc.to_text());
+    let stream = ffmpeg::probe_video_stream(input)?;
+    let Some(source_rate) = stream.frame_rate else {
+        return Ok(None);
+    };
+    let start = vitc.timecode_at(trim.start.unwrap_or(0.0), source_rate);
+    Ok(start.map(|tc| match rate {
+        Some(conversion) => tc.at_rate(vitc.fps, video::timecode::nominal_fps(conversion.target())),
+        None => tc,
+    }))
+}
+
+/// Add the input's line-21 captions to a finished output
+fn add_captions(
+    input: &Path,
+    output: &Path,
+    trim: &trim::Trim,
+    rate: Option<&video::RateConversion>,
+) -> Result<()> {
+    println!("{} Reading line-21 captions...", "🔍".bright_cyan());
+    match video::captions::embed_captions(input, output, trim, rate)? {
+        0 => println!("{} No closed captions found", "ℹ".bright_blue()),
+        n => println!("{} Added {} caption(s) as a subtitle track", "✓".green(), n),
+    }
+    Ok(())
+}
+
+fn parse_trim(args: &TrimArgs) -> Result<trim::Trim> {
+    let parse = |value: &Option<String>| -> Result<Option<f64>> {
+        match value {
+            Some(ts) => Ok(Some(
+                trim::parse_timestamp(ts).with_context(|| format!("Invalid time '{}'", ts))?,
+            )),
+            None => Ok(None),
+        }
+    };
+    let trim = trim::Trim {
+        start: parse(&args.start)?,
+        end: parse(&args.end)?,
+    };
+    if let (Some(start), Some(end)) = (trim.start, trim.end) {
+        if end <= start {
+            anyhow::bail!("--end must be after --start");
+        }
+    }
+    Ok(trim)
+}
+
+/// The --cut ranges, with --start/--end as cuts of the head and tail
+fn parse_cut_list(args: &TrimArgs) -> Result<Option<trim::CutList>> {
+    let Some(ref cut) = args.cut else {
+        return Ok(None);
+    };
+    let mut ranges = trim::parse_ranges(cut)
+        .with_context(|| format!("Invalid cut list '{}', expected start-end,...", cut))?;
+    let keep = parse_trim(args)?;
+    if let Some(start) = keep.start.filter(|&s| s > 0.0) {
+        ranges.push((0.0, Some(start)));
+    }
+    if let Some(end) = keep.end {
+        ranges.push((end, None));
+    }
+    Ok(Some(trim::CutList {
+        ranges,
+        crossfade: args.crossfade,
+    }))
+}
+
+/// Run a processing command on the input, or with --cut on an intermediate
+/// with the ranges removed (the --start/--end trim is then part of the cut)
+fn with_cuts<T>(
+    input: &Path,
+    output: &Path,
+    args: &TrimArgs,
+    process: impl FnOnce(&Path, trim::Trim) -> Result<T>,
+) -> Result<T> {
+    match parse_cut_list(args)? {
+        Some(cuts) => {
+            println!(
+                "{} Cutting {} range(s)...",
+                "✂".bright_cyan(),
+                cuts.ranges.len()
+            );
+            cuts.process(input, output, |input| process(input, trim::Trim::default()))
+        }
+        None => process(input, parse_trim(args)?),
+    }
+}
+
+fn parse_scale_options(args: &ScaleArgs) -> Result<Option<video::ScaleOptions>> {
+    let Some(ref target) = args.target else {
+        return Ok(None);
+    };
+    Ok(Some(video::ScaleOptions {
+        target: video::ScaleTarget::parse(target).with_context(|| {
+            format!(
+                "Invalid target '{}', expected e.g. 1080p or an even WxH",
+                target
+            )
+        })?,
+        fit: video::ScaleFit::from_name(&args.fit)
+            .with_context(|| format!("Unknown fit '{}', expected fit or pad", args.fit))?,
+        scaler: video::Scaler::from_name(&args.scaler)
+            .with_context(|| format!("Unknown scaler '{}'", args.scaler))?,
+    }))
+}
+
+fn parse_depth(bits: Option<u32>) -> Result<Option<video::ProcessingDepth>> {
+    bits.map(|bits| {
+        video::ProcessingDepth::from_bits(bits)
+            .with_context(|| format!("Unsupported bit depth {}, expected 10 or 16", bits))
+    })
+    .transpose()
+}
+
+/// Sharpening from the flags on top of `base` (the preset's or the default);
+/// a different method starts from that method's defaults
+fn parse_sharpen_options(
+    args: &SharpenArgs,
+    base: video::SharpenOptions,
+) -> Result<video::SharpenOptions> {
+    let defaults = match args.sharpen_method.as_deref() {
+        Some(name) => {
+            let method = video::SharpenMethod::from_name(name)
+                .with_context(|| format!("Unknown sharpening method '{}'", name))?;
+            if method == base.method {
+                base
+            } else {
+                video::SharpenOptions::new(method)
+            }
+        }
+        None => base,
+    };
+    Ok(video::SharpenOptions {
+        luma_amount: args.sharpen_luma.unwrap_or(defaults.luma_amount),
+        luma_radius: args.sharpen_luma_radius.unwrap_or(defaults.luma_radius),
+        chroma_amount: args.sharpen_chroma.unwrap_or(defaults.chroma_amount),
+        chroma_radius: args.sharpen_chroma_radius.unwrap_or(defaults.chroma_radius),
+        protect_noise: args.sharpen_protect_noise.unwrap_or(defaults.protect_noise),
+        ..defaults
+    })
+}
+
+fn parse_rate_conversion(args: &RateArgs) -> Result<Option<video::RateConversion>> {
+    if let Some(ref standards) = args.standards {
+        return match standards.to_lowercase().as_str() {
+            "pal-to-ntsc" => Ok(Some(video::RateConversion::PAL_TO_NTSC)),
+            "ntsc-to-pal" => Ok(Some(video::RateConversion::NTSC_TO_PAL)),
+            _ => anyhow::bail!(
+                "Unknown standards conversion '{}', expected pal-to-ntsc or ntsc-to-pal",
+                standards
+            ),
+        };
+    }
+
+    let Some(ref fps) = args.fps else {
+        return Ok(None);
+    };
+    let target =
+        video::FrameRate::parse(fps).with_context(|| format!("Invalid frame rate '{}'", fps))?;
+    let mode = video::FrameRateMode::from_name(&args.fps_mode).with_context(|| {
+        format!(
+            "Unknown frame rate mode '{}', expected drop, blend or interpolate",
+            args.fps_mode
+        )
+    })?;
+    Ok(Some(video::RateConversion::Resample { target, mode }))
+}
+
+fn parse_repair_mode(s: &str) -> Result<video::RepairMode> {
+    video::RepairMode::from_name(s).with_context(|| {
+        format!(
+            "Unknown dropout repair mode '{}', expected interpolate or neighbour",
+            s
+        )
+    })
+}
+
+/// Parse a comma-separated pair such as "-2,1"
+fn parse_pair<T: std::str::FromStr>(spec: &str) -> Option<(T, T)> {
+    let (a, b) = spec.split_once(',')?;
+    Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+}
+
+/// Parse a chroma denoise strength "spatial[:temporal]"; temporal defaults to 1.5x spatial
+fn parse_chroma_denoise(spec: &str) -> Result<(f32, f32)> {
+    let invalid = || {
+        format!(
+            "Invalid chroma denoise '{}', expected spatial[:temporal]",
+            spec
+        )
+    };
+    let mut parts = spec.split(':');
+    let spatial: f32 = parts
+        .next()
+        .and_then(|v| v.trim().parse().ok())
+        .with_context(invalid)?;
+    let temporal: f32 = match parts.next() {
+        Some(v) => v.trim().parse().ok().with_context(invalid)?,
+        None => spatial * 1.5,
+    };
+    Ok((spatial, temporal))
+}
+
 fn check_ffmpeg() -> Result<()> {
     ffmpeg::check_ffmpeg()
 }
@@ -0,0 +1,337 @@
+#![allow(dead_code)]
+
+use crate::audio::AudioEnhanceOptions;
+use crate::video::{
+    DenoiseType, OverscanMask, ScanType, SharpenMethod, SharpenOptions, VhsRescueOptions,
+    VideoEnhanceOptions,
+};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Saved restoration settings; either part may be left out (an audio-only
+/// preset for cassettes, a video-only one for silent sources)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preset {
+    #[serde(skip)]
+    pub name: String, // File stem or built-in name
+    pub description: String,
+    pub video: Option<VideoEnhanceOptions>,
+    pub audio: Option<AudioEnhanceOptions>,
+}
+
+/// Where a preset was found
+#[derive(Debug, Clone, PartialEq)]
+pub enum PresetSource {
+    BuiltIn,
+    User(PathBuf),
+    File(PathBuf),
+}
+
+/// File format of a preset, from its extension
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PresetFormat {
+    Toml,
+    Json,
+}
+
+impl PresetFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "toml" => Some(PresetFormat::Toml),
+            "json" => Some(PresetFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(Self::from_name)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            PresetFormat::Toml => "toml",
+            PresetFormat::Json => "json",
+        }
+    }
+}
+
+impl Preset {
+    pub fn parse(text: &str, format: PresetFormat) -> Result<Self> {
+        Ok(match format {
+            PresetFormat::Toml => toml::from_str(text)?,
+            PresetFormat::Json => serde_json::from_str(text)?,
+        })
+    }
+
+    pub fn to_text(&self, format: PresetFormat) -> Result<String> {
+        Ok(match format {
+            PresetFormat::Toml => toml::to_string_pretty(self)?,
+            PresetFormat::Json => serde_json::to_string_pretty(self)? + "\n",
+        })
+    }
+
+    /// Read a .toml or .json preset; the name is the file stem
+    pub fn load(path: &Path) -> Result<Self> {
+        let format = PresetFormat::from_path(path)
+            .with_context(|| format!("{} is not a .toml or .json file", path.display()))?;
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read preset {}", path.display()))?;
+        let mut preset = Self::parse(&text, format)
+            .with_context(|| format!("Invalid preset {}", path.display()))?;
+        preset.name = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Ok(preset)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let format = PresetFormat::from_path(path)
+            .with_context(|| format!("{} is not a .toml or .json file", path.display()))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_text(format)?)
+            .with_context(|| format!("Failed to write preset {}", path.display()))
+    }
+}
+
+/// Write an f32 as its shortest decimal form (0.8, not 0.800000011920929)
+pub fn serialize_f32<S: serde::Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(value.to_string().parse().unwrap_or(*value as f64))
+}
+
+/// For options that default to Some: None is written as `false`, since TOML
+/// has no null and a missing field would read back as the default
+pub mod or_off {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OrOff<T> {
+        Off(bool),
+        Null(()),
+        Value(T),
+    }
+
+    pub fn serialize<T: Serialize, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => value.serialize(serializer),
+            None => serializer.serialize_bool(false),
+        }
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<T>, D::Error> {
+        match OrOff::deserialize(deserializer)? {
+            OrOff::Off(false) | OrOff::Null(()) => Ok(None),
+            OrOff::Off(true) => Err(D::Error::custom("expected a value or false")),
+            OrOff::Value(value) => Ok(Some(value)),
+        }
+    }
+}
+
+/// User presets live in $XDG_CONFIG_HOME/fluxara-avc/presets, by default
+/// ~/.config/fluxara-avc/presets
+pub fn user_preset_dir() -> Option<PathBuf> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config.join("fluxara-avc").join("presets"))
+}
+
+/// Find a preset: a path to a .toml/.json file, then a user preset, then a
+/// built-in one (user presets can replace built-ins of the same name)
+pub fn find_preset(spec: &str) -> Result<(Preset, PresetSource)> {
+    let path = Path::new(spec);
+    if PresetFormat::from_path(path).is_some() || path.components().count() > 1 {
+        return Ok((Preset::load(path)?, PresetSource::File(path.to_path_buf())));
+    }
+    if let Some(path) = user_preset_path(spec) {
+        return Ok((Preset::load(&path)?, PresetSource::User(path)));
+    }
+    match builtin_preset(spec) {
+        Some(preset) => Ok((preset, PresetSource::BuiltIn)),
+        None => bail!("Unknown preset '{}' (see `preset list`)", spec),
+    }
+}
+
+pub fn load_preset(spec: &str) -> Result<Preset> {
+    Ok(find_preset(spec)?.0)
+}
+
+fn user_preset_path(name: &str) -> Option<PathBuf> {
+    let dir = user_preset_dir()?;
+    [PresetFormat::Toml, PresetFormat::Json]
+        .iter()
+        .map(|format| dir.join(format!("{}.{}", name, format.extension())))
+        .find(|path| path.is_file())
+}
+
+/// All presets by name: built-ins, then user presets (shadowing built-ins)
+pub fn list_presets() -> Result<Vec<(Preset, PresetSource)>> {
+    let mut presets: Vec<(Preset, PresetSource)> = BUILTIN_PRESETS
+        .iter()
+        .filter_map(|name| builtin_preset(name).map(|p| (p, PresetSource::BuiltIn)))
+        .collect();
+
+    let Some(dir) = user_preset_dir().filter(|d| d.is_dir()) else {
+        return Ok(presets);
+    };
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| PresetFormat::from_path(path).is_some())
+        .collect();
+    paths.sort();
+    for path in paths {
+        let preset = Preset::load(&path)?;
+        presets.retain(|(p, _)| p.name != preset.name);
+        presets.push((preset, PresetSource::User(path)));
+    }
+    Ok(presets)
+}
+
+/// Names of the built-in presets
+pub const BUILTIN_PRESETS: &[&str] = &[
+    "vhs-ntsc",
+    "vhs-pal",
+    "hi8",
+    "video8",
+    "betamax",
+    "laserdisc",
+    "dv",
+    "cassette",
+];
+
+pub fn builtin_preset(name: &str) -> Option<Preset> {
+    let vhs_video = VhsRescueOptions::default_video;
+    let vhs_audio = VhsRescueOptions::default_audio;
+    let head_switching = |bottom| OverscanMask {
+        bottom,
+        ..Default::default()
+    };
+
+    let (description, video, audio) = match name {
+        "vhs-ntsc" => (
+            "VHS/S-VHS, NTSC: 60 Hz hum, head-switching band",
+            Some(VideoEnhanceOptions {
+                overscan: Some(head_switching(8)),
+                ..vhs_video()
+            }),
+            Some(AudioEnhanceOptions {
+                notch_freq: Some(60),
+                ..vhs_audio()
+            }),
+        ),
+        "vhs-pal" => (
+            "VHS/S-VHS, PAL/SECAM: 50 Hz hum, head-switching band",
+            Some(VideoEnhanceOptions {
+                overscan: Some(head_switching(10)),
+                ..vhs_video()
+            }),
+            Some(AudioEnhanceOptions {
+                notch_freq: Some(50),
+                ..vhs_audio()
+            }),
+        ),
+        // Hi8 resolves more than VHS: lighter, contrast-adaptive sharpening
+        // and FM audio with little hiss
+        "hi8" => (
+            "Hi8: sharper than VHS, chroma noise, FM audio",
+            Some(VideoEnhanceOptions {
+                overscan: Some(head_switching(6)),
+                chroma: Some(Default::default()),
+                sharpen: Some(SharpenOptions {
+                    protect_noise: true,
+                    ..SharpenOptions::new(SharpenMethod::Cas)
+                }),
+                ..vhs_video()
+            }),
+            Some(AudioEnhanceOptions {
+                lowpass_freq: None,
+                gate: false,
+                ..vhs_audio()
+            }),
+        ),
+        "video8" => (
+            "Video8: soft, noisy picture and AFM mono audio",
+            Some(VideoEnhanceOptions {
+                overscan: Some(head_switching(6)),
+                chroma: Some(Default::default()),
+                denoise: DenoiseType::Nlmeans,
+                ..vhs_video()
+            }),
+            Some(AudioEnhanceOptions {
+                lowpass_freq: Some(12000),
+                ..vhs_audio()
+            }),
+        ),
+        "betamax" => (
+            "Betamax/Super Beta: cleaner than VHS, narrow head-switching band",
+            Some(VideoEnhanceOptions {
+                overscan: Some(head_switching(6)),
+                ..vhs_video()
+            }),
+            Some(vhs_audio()),
+        ),
+        // Composite but no tape: no head switching, no dropouts, little noise
+        "laserdisc" => (
+            "LaserDisc: composite video, clean picture, digital or analog audio",
+            Some(VideoEnhanceOptions {
+                sharpen: None,
+                color_adjust: false,
+                encoder: crate::video::VideoEncoder::archival(),
+                ..Default::default()
+            }),
+            Some(AudioEnhanceOptions {
+                denoise: false,
+                highpass_freq: Some(20),
+                compressor: false,
+                gate: false,
+                ..Default::default()
+            }),
+        ),
+        // Digital: bottom field first, leave the picture and PCM audio alone
+        "dv" => (
+            "DV/MiniDV/Digital8: bottom field first, no noise reduction",
+            Some(VideoEnhanceOptions {
+                scan_type: Some(ScanType::Bff),
+                denoise: DenoiseType::None,
+                sharpen: None,
+                color_adjust: false,
+                encoder: crate::video::VideoEncoder::archival(),
+                ..Default::default()
+            }),
+            None,
+        ),
+        "cassette" => (
+            "Compact cassette: hiss above 12 kHz, no gate or compression",
+            None,
+            Some(AudioEnhanceOptions {
+                highpass_freq: Some(40),
+                lowpass_freq: Some(12000),
+                compressor: false,
+                gate: false,
+                ..Default::default()
+            }),
+        ),
+        _ => return None,
+    };
+
+    Some(Preset {
+        name: name.to_string(),
+        description: description.to_string(),
+        video,
+        audio,
+    })
+}
@@ -0,0 +1,410 @@
+#![allow(dead_code)]
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where a job is in its life
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobState::Queued => "queued",
+            JobState::Running => "running",
+            JobState::Done => "done",
+            JobState::Failed => "failed",
+            JobState::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// One fluxara-avc command waiting in, or run from, the queue
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    pub args: Vec<String>, // Command line without the program name
+    pub dir: PathBuf,      // Working directory the job was added from
+    pub state: JobState,
+    pub priority: i32,           // Higher runs first, then oldest first
+    pub attempts: u32,           // Times the job has been started
+    pub max_attempts: u32,       // Failed jobs are queued again until this many attempts
+    pub pid: Option<u32>,        // Process running the job
+    pub boot_id: Option<String>, // Boot the process was started in, so a reboot is noticed
+    pub error: Option<String>,
+    pub log: Option<PathBuf>,
+    pub created: u64, // Unix seconds
+    pub started: Option<u64>,
+    pub finished: Option<u64>,
+}
+
+impl Job {
+    pub fn command_line(&self) -> String {
+        self.args.join(" ")
+    }
+}
+
+/// The persisted queue
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct JobQueue {
+    pub next_id: u64,
+    pub jobs: Vec<Job>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl JobQueue {
+    /// Read the queue; a missing file is an empty queue
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read queue {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("Invalid queue {}", path.display()))
+    }
+
+    /// Write the queue through a temporary file, so a crash mid-write
+    /// leaves the previous queue intact
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)? + "\n")?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to write queue {}", path.display()))
+    }
+
+    /// Load, change and save the queue while holding its lock file, so a
+    /// runner and `queue add` do not overwrite each other
+    pub fn update<R>(path: &Path, f: impl FnOnce(&mut JobQueue) -> Result<R>) -> Result<R> {
+        let _lock = QueueLock::acquire(path)?;
+        let mut queue = Self::load(path)?;
+        let result = f(&mut queue)?;
+        queue.save(path)?;
+        Ok(result)
+    }
+
+    pub fn add(&mut self, args: Vec<String>, dir: PathBuf, priority: i32, retries: u32) -> u64 {
+        self.next_id = self
+            .next_id
+            .max(self.jobs.iter().map(|j| j.id).max().unwrap_or(0))
+            + 1;
+        let id = self.next_id;
+        self.jobs.push(Job {
+            id,
+            args,
+            dir,
+            state: JobState::Queued,
+            priority,
+            attempts: 0,
+            max_attempts: retries + 1,
+            pid: None,
+            boot_id: None,
+            error: None,
+            log: None,
+            created: now(),
+            started: None,
+            finished: None,
+        });
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Job> {
+        self.jobs.iter().find(|j| j.id == id)
+    }
+
+    fn get_mut(&mut self, id: u64) -> Result<&mut Job> {
+        self.jobs
+            .iter_mut()
+            .find(|j| j.id == id)
+            .with_context(|| format!("No job {}", id))
+    }
+
+    /// The queued job to run next: highest priority, then oldest
+    pub fn next_job(&self) -> Option<u64> {
+        self.jobs
+            .iter()
+            .filter(|j| j.state == JobState::Queued)
+            .min_by_key(|j| (-(j.priority as i64), j.id))
+            .map(|j| j.id)
+    }
+
+    pub fn start(&mut self, id: u64, pid: u32, log: Option<PathBuf>) -> Result<()> {
+        let job = self.get_mut(id)?;
+        job.state = JobState::Running;
+        job.attempts += 1;
+        job.pid = Some(pid);
+        job.boot_id = boot_id();
+        job.error = None;
+        job.log = log;
+        job.started = Some(now());
+        job.finished = None;
+        Ok(())
+    }
+
+    /// Record how a run ended. A failed job with attempts left is queued
+    /// again; a job cancelled while it ran stays cancelled.
+    pub fn finish(&mut self, id: u64, result: std::result::Result<(), String>) -> Result<()> {
+        let job = self.get_mut(id)?;
+        if job.state != JobState::Running {
+            return Ok(());
+        }
+        job.pid = None;
+        job.finished = Some(now());
+        match result {
+            Ok(()) => job.state = JobState::Done,
+            Err(error) => {
+                job.state = if job.attempts < job.max_attempts {
+                    JobState::Queued
+                } else {
+                    JobState::Failed
+                };
+                job.error = Some(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Cancel a queued or running job; returns the process to stop for a
+    /// running one
+    pub fn cancel(&mut self, id: u64) -> Result<Option<u32>> {
+        let job = self.get_mut(id)?;
+        let pid = match job.state {
+            JobState::Queued => None,
+            JobState::Running => job.pid,
+            state => bail!(
+                "Job {} is {}, only queued or running jobs can be cancelled",
+                id,
+                state.name()
+            ),
+        };
+        job.state = JobState::Cancelled;
+        job.pid = None;
+        job.finished = Some(now());
+        Ok(pid)
+    }
+
+    /// Queue a failed or cancelled job again, with one more attempt allowed
+    pub fn retry(&mut self, id: u64) -> Result<()> {
+        let job = self.get_mut(id)?;
+        if !matches!(job.state, JobState::Failed | JobState::Cancelled) {
+            bail!(
+                "Job {} is {}, only failed or cancelled jobs can be retried",
+                id,
+                job.state.name()
+            );
+        }
+        job.state = JobState::Queued;
+        job.max_attempts = job.max_attempts.max(job.attempts + 1);
+        job.error = None;
+        Ok(())
+    }
+
+    /// Queue again the jobs left running by a runner that crashed or a
+    /// reboot; `alive` tells whether a job's process is still running
+    pub fn recover(&mut self, alive: impl Fn(&Job) -> bool) -> Vec<u64> {
+        let mut recovered = Vec::new();
+        for job in &mut self.jobs {
+            if job.state == JobState::Running && !alive(job) {
+                job.state = JobState::Queued;
+                job.pid = None;
+                job.error = Some("Interrupted".to_string());
+                recovered.push(job.id);
+            }
+        }
+        recovered
+    }
+}
+
+/// Lock file next to the queue, created exclusively
+struct QueueLock {
+    path: PathBuf,
+}
+
+impl QueueLock {
+    fn acquire(queue: &Path) -> Result<Self> {
+        if let Some(dir) = queue.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let path = queue.with_extension("json.lock");
+        for _ in 0..100 {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    // The lock is only held for a load/save; an old one was
+                    // left by a process that died holding it
+                    let stale = std::fs::metadata(&path)
+                        .and_then(|m| m.modified())
+                        .map(|t| t.elapsed().unwrap_or_default() > Duration::from_secs(10))
+                        .unwrap_or(false);
+                    if stale {
+                        let _ = std::fs::remove_file(&path);
+                    } else {
+                        std::thread::sleep(Duration::from_millis(50));
+                    }
+                }
+                Err(e) => return Err(e).context("Failed to lock the queue"),
+            }
+        }
+        bail!("Queue is locked by another process ({})", path.display())
+    }
+}
+
+impl Drop for QueueLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// The queue lives in $XDG_STATE_HOME/fluxara-avc/queue.json, by default
+/// ~/.local/state/fluxara-avc/queue.json
+pub fn default_queue_path() -> Option<PathBuf> {
+    let state = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(state.join("fluxara-avc").join("queue.json"))
+}
+
+/// Kernel boot id; process ids from another boot mean nothing
+#[cfg(target_os = "linux")]
+fn boot_id() -> Option<String> {
+    std::fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn boot_id() -> Option<String> {
+    None
+}
+
+/// Whether the process running a job is still there
+#[cfg(target_os = "linux")]
+pub fn job_alive(job: &Job) -> bool {
+    match job.pid {
+        Some(pid) => job.boot_id == boot_id() && Path::new(&format!("/proc/{}", pid)).exists(),
+        None => false,
+    }
+}
+
+/// Whether the process running a job is still there; without a boot id a
+/// pid reused after a reboot reads as alive until the job is cancelled
+#[cfg(not(target_os = "linux"))]
+pub fn job_alive(job: &Job) -> bool {
+    match job.pid {
+        Some(pid) => Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success()),
+        None => false,
+    }
+}
+
+/// Stop a running job: its process group, so the ffmpeg children it
+/// started stop with it
+pub fn kill_job(pid: u32) -> Result<()> {
+    let output = Command::new("kill")
+        .args(["-TERM", "--", &format!("-{}", pid)])
+        .output()
+        .context("Failed to run kill")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to stop job process group {}: {}",
+            pid,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// The fluxara-avc binary that runs queued jobs: this program when it is
+/// the CLI, otherwise the CLI installed next to it (e.g. from the GUI)
+pub fn cli_program() -> PathBuf {
+    let name = "fluxara-avc";
+    match std::env::current_exe() {
+        Ok(exe) if exe.file_stem().is_some_and(|s| s == name) => exe,
+        Ok(exe) => exe
+            .parent()
+            .map(|dir| dir.join(name))
+            .filter(|path| path.exists())
+            .unwrap_or_else(|| PathBuf::from(name)),
+        Err(_) => PathBuf::from(name),
+    }
+}
+
+/// Run queued jobs one at a time with `program` until none are left, after
+/// re-queuing jobs interrupted by a crash or reboot. Each job's output goes
+/// to a log file next to the queue. Returns the number of jobs run.
+pub fn run_queue(path: &Path, program: &Path, mut on_event: impl FnMut(&Job)) -> Result<usize> {
+    let log_dir = path.with_file_name("logs");
+    std::fs::create_dir_all(&log_dir)?;
+    let mut count = 0;
+
+    loop {
+        let started = JobQueue::update(path, |queue| {
+            queue.recover(job_alive);
+            let Some(id) = queue.next_job() else {
+                return Ok(None);
+            };
+            let job = queue.get(id).cloned().context("Job vanished")?;
+            let log = log_dir.join(format!("job-{}.log", id));
+            let file = File::create(&log)?;
+            let mut command = Command::new(program);
+            command
+                .args(&job.args)
+                .current_dir(&job.dir)
+                .stdin(Stdio::null())
+                .stdout(file.try_clone()?)
+                .stderr(file);
+            // A group of its own, led by the job, so cancelling reaches the
+            // ffmpeg processes it runs
+            #[cfg(unix)]
+            std::os::unix::process::CommandExt::process_group(&mut command, 0);
+            let child = command
+                .spawn()
+                .with_context(|| format!("Failed to start {}", program.display()))?;
+            queue.start(id, child.id(), Some(log))?;
+            Ok(Some((
+                child,
+                queue.get(id).cloned().context("Job vanished")?,
+            )))
+        })?;
+        let Some((mut child, job)) = started else {
+            break;
+        };
+        on_event(&job);
+
+        let result = match child.wait() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(format!("Exited with {}", status)),
+            Err(e) => Err(e.to_string()),
+        };
+        let job = JobQueue::update(path, |queue| {
+            queue.finish(job.id, result)?;
+            queue.get(job.id).cloned().context("Job vanished")
+        })?;
+        on_event(&job);
+        count += 1;
+    }
+    Ok(count)
+}
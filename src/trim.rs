@@ -0,0 +1,472 @@
+#![allow(dead_code)]
+
+use crate::ffmpeg;
+use crate::video::{VideoCodec, VideoEncoder};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Keep only part of the input, in seconds from the start of the file
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Trim {
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+}
+
+impl Trim {
+    pub fn is_empty(&self) -> bool {
+        self.start.is_none() && self.end.is_none()
+    }
+
+    /// Input options (placed before `-i`) seeking to the start and stopping
+    /// at the end, both measured on the source timeline
+    pub fn input_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(start) = self.start {
+            args.push("-ss".to_string());
+            args.push(format!("{:.3}", start));
+        }
+        if let Some(end) = self.end {
+            args.push("-to".to_string());
+            args.push(format!("{:.3}", end));
+        }
+        args
+    }
+
+    /// `count` evenly spaced times inside the kept part of a file of
+    /// `duration` seconds (0 = unknown), for analysis passes that sample the
+    /// input instead of reading only its first frames (on a tape: the
+    /// leader, blue screen or snow). The edges of the range are avoided.
+    pub fn sample_times(&self, duration: f64, count: usize) -> Vec<f64> {
+        let start = self.start.unwrap_or(0.0);
+        let end = match self.end {
+            Some(end) if duration > 0.0 => end.min(duration),
+            Some(end) => end,
+            None => duration,
+        };
+        if count == 0 || end <= start {
+            return vec![start];
+        }
+        let step = (end - start) / count as f64;
+        (0..count)
+            .map(|i| start + step * (i as f64 + 0.5))
+            .collect()
+    }
+}
+
+/// Parse a timestamp: seconds ("90", "12.5"), MM:SS or HH:MM:SS(.ms)
+pub fn parse_timestamp(s: &str) -> Option<f64> {
+    let mut secs = 0.0;
+    let parts: Vec<&str> = s.trim().split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+    for part in parts {
+        let value: f64 = part.trim().parse().ok()?;
+        if !value.is_finite() || value < 0.0 {
+            return None;
+        }
+        secs = secs * 60.0 + value;
+    }
+    Some(secs)
+}
+
+/// Format seconds as HH:MM:SS.mmm
+pub fn format_timestamp(secs: f64) -> String {
+    let ms = (secs * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    )
+}
+
+/// Parse a list of ranges "start-end,start-end"; an empty end means the
+/// end of the file
+pub fn parse_ranges(s: &str) -> Option<Vec<(f64, Option<f64>)>> {
+    s.split(',')
+        .map(|range| {
+            let (start, end) = range.split_once('-')?;
+            let start = parse_timestamp(start)?;
+            let end = match end.trim() {
+                "" => None,
+                end => Some(parse_timestamp(end)?),
+            };
+            match end {
+                Some(end) if end <= start => None,
+                _ => Some((start, end)),
+            }
+        })
+        .collect()
+}
+
+/// Segments left after removing the cut ranges from [0, duration]
+pub fn keep_segments(duration: f64, cuts: &[(f64, Option<f64>)]) -> Vec<(f64, f64)> {
+    let mut cuts: Vec<(f64, f64)> = cuts
+        .iter()
+        .map(|&(start, end)| (start, end.unwrap_or(duration).min(duration)))
+        .collect();
+    cuts.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut segments = Vec::new();
+    let mut pos = 0.0;
+    for (start, end) in cuts {
+        if start > pos {
+            segments.push((pos, start.min(duration)));
+        }
+        pos = f64::max(pos, end);
+    }
+    if pos < duration {
+        segments.push((pos, duration));
+    }
+    segments.retain(|&(start, end)| end - start > 0.001);
+    segments
+}
+
+/// True if every segment starts on a keyframe (within `tolerance` seconds),
+/// so it can be stream-copied without re-encoding
+pub fn starts_on_keyframes(segments: &[(f64, f64)], keyframes: &[f64], tolerance: f64) -> bool {
+    segments.iter().all(|&(start, _)| {
+        start < tolerance || keyframes.iter().any(|&k| (k - start).abs() <= tolerance)
+    })
+}
+
+/// Check that every kept segment is longer than the crossfade, which
+/// `acrossfade` needs on both sides of each join
+pub fn check_crossfade(segments: &[(f64, f64)], crossfade: Option<f64>) -> Result<()> {
+    let Some(d) = crossfade else {
+        return Ok(());
+    };
+    if !d.is_finite() || d <= 0.0 {
+        anyhow::bail!(
+            "--crossfade must be a positive number of seconds, got {}",
+            d
+        );
+    }
+    if segments.len() < 2 {
+        return Ok(());
+    }
+    for &(start, end) in segments {
+        if end - start <= d {
+            anyhow::bail!(
+                "The kept segment {}-{} is {:.3}s long, not longer than the {:.3}s crossfade; \
+                 use a shorter --crossfade or cut that segment too",
+                format_timestamp(start),
+                format_timestamp(end),
+                end - start,
+                d
+            );
+        }
+    }
+    Ok(())
+}
+
+/// filter_complex cutting the segments out of input 0 and joining them.
+/// With a crossfade each audio segment but the last runs `crossfade`
+/// seconds longer so the overlap keeps audio and video in sync.
+pub fn cut_filtergraph(
+    segments: &[(f64, f64)],
+    has_video: bool,
+    has_audio: bool,
+    crossfade: Option<f64>,
+) -> String {
+    let mut graph = Vec::new();
+    let last = segments.len().saturating_sub(1);
+
+    for (i, &(start, end)) in segments.iter().enumerate() {
+        if has_video {
+            graph.push(format!(
+                "[0:v]trim=start={:.3}:end={:.3},setpts=PTS-STARTPTS[v{}]",
+                start, end, i
+            ));
+        }
+        if has_audio {
+            let tail = if i < last {
+                crossfade.unwrap_or(0.0)
+            } else {
+                0.0
+            };
+            graph.push(format!(
+                "[0:a]atrim=start={:.3}:end={:.3},asetpts=PTS-STARTPTS[a{}]",
+                start,
+                end + tail,
+                i
+            ));
+        }
+    }
+
+    if has_video {
+        let inputs: String = (0..segments.len()).map(|i| format!("[v{}]", i)).collect();
+        graph.push(format!("{}concat=n={}:v=1:a=0[v]", inputs, segments.len()));
+    }
+
+    if has_audio {
+        match crossfade {
+            Some(d) if segments.len() > 1 => {
+                let mut prev = "a0".to_string();
+                for i in 1..segments.len() {
+                    let out = if i == last {
+                        "a".to_string()
+                    } else {
+                        format!("x{}", i)
+                    };
+                    graph.push(format!(
+                        "[{}][a{}]acrossfade=d={:.3}:c1=tri:c2=tri[{}]",
+                        prev, i, d, out
+                    ));
+                    prev = out;
+                }
+            }
+            _ => {
+                let inputs: String = (0..segments.len()).map(|i| format!("[a{}]", i)).collect();
+                graph.push(format!("{}concat=n={}:v=0:a=1[a]", inputs, segments.len()));
+            }
+        }
+    }
+
+    graph.join(";")
+}
+
+/// Settings for removing ranges
+#[derive(Debug, Clone, Default)]
+pub struct CutOptions {
+    pub crossfade: Option<f64>, // Audio crossfade at each join, seconds (forces re-encoding)
+    pub reencode: bool,         // Re-encode even when the cuts are on keyframes
+    pub encoder: VideoEncoder,  // Used when re-encoding
+    pub intermediate: bool,     // First video and audio stream only, FLAC when re-encoding
+}
+
+/// How the cut was done
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CutMethod {
+    StreamCopy,
+    Reencode,
+}
+
+/// Keyframe timestamps of the first video stream
+pub fn probe_keyframes(input: &Path) -> Result<Vec<f64>> {
+    ffmpeg::check_ffprobe()?;
+
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "v:0",
+            "-skip_frame",
+            "nokey",
+            "-show_entries",
+            "frame=best_effort_timestamp_time",
+            "-of",
+            "csv=p=0",
+        ])
+        .arg(input)
+        .output()
+        .context("Failed to execute ffprobe")?;
+
+    if !output.status.success() {
+        anyhow::bail!("Failed to read keyframes of {}", input.display());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().trim_end_matches(',').parse().ok())
+        .collect())
+}
+
+/// Remove the cut ranges from the input and join what is left, stream-copying
+/// when every kept segment starts on a keyframe
+pub fn cut_segments(
+    input: &Path,
+    output: &Path,
+    cuts: &[(f64, Option<f64>)],
+    opts: &CutOptions,
+) -> Result<CutMethod> {
+    ffmpeg::check_ffmpeg()?;
+
+    let info = ffmpeg::get_media_info(input)?;
+    let has_stream = |codec_type: &str| {
+        info.get("streams")
+            .and_then(|s| s.as_array())
+            .is_some_and(|streams| {
+                streams
+                    .iter()
+                    .any(|s| s.get("codec_type").and_then(|c| c.as_str()) == Some(codec_type))
+            })
+    };
+    let (has_video, has_audio) = (has_stream("video"), has_stream("audio"));
+    let duration = match info
+        .get("format")
+        .and_then(|f| f.get("duration"))
+        .and_then(|d| d.as_str())
+        .and_then(|d| d.parse().ok())
+    {
+        Some(d) if d > 0.0 => d,
+        // Raw and unfinished captures may have no duration in the container
+        _ => ffmpeg::probe_packet_duration(input, if has_video { "v:0" } else { "a:0" })?,
+    };
+    if duration <= 0.0 {
+        anyhow::bail!("Unknown duration for {}", input.display());
+    }
+
+    let segments = keep_segments(duration, cuts);
+    if segments.is_empty() {
+        anyhow::bail!("The cut ranges remove the whole of {}", input.display());
+    }
+    check_crossfade(&segments, opts.crossfade)?;
+
+    // Every audio packet is a keyframe, so audio-only files always copy
+    let copy = !opts.reencode
+        && opts.crossfade.is_none()
+        && (!has_video || {
+            let stream = ffmpeg::probe_video_stream(input)?;
+            let frame = stream.frame_rate.map(|r| 1.0 / r.as_f64()).unwrap_or(0.04);
+            starts_on_keyframes(&segments, &probe_keyframes(input)?, frame / 2.0)
+        });
+
+    if copy {
+        concat_copy(input, output, &segments, opts.intermediate)?;
+        return Ok(CutMethod::StreamCopy);
+    }
+
+    let graph = cut_filtergraph(&segments, has_video, has_audio, opts.crossfade);
+    let encoder_args = opts.encoder.args();
+    let mut args = vec!["-i", input.to_str().unwrap(), "-filter_complex", &graph];
+    if has_video {
+        args.extend(["-map", "[v]"]);
+        args.extend(encoder_args.iter().map(|s| s.as_str()));
+    }
+    if has_audio {
+        args.extend(["-map", "[a]"]);
+        if opts.intermediate {
+            args.extend(["-c:a", "flac"]);
+        } else {
+            args.extend(["-c:a", "aac", "-b:a", "192k"]);
+        }
+    }
+    args.extend(["-y", output.to_str().unwrap()]);
+
+    ffmpeg::execute_ffmpeg(&args)?;
+    Ok(CutMethod::Reencode)
+}
+
+/// Line of a concat demuxer list; a `'` in the path is closed, escaped and
+/// reopened, as the demuxer's quoting has no other escape inside quotes
+pub fn concat_list_entry(path: &Path) -> String {
+    format!(
+        "file '{}'\n",
+        path.display().to_string().replace('\'', "'\\''")
+    )
+}
+
+/// Stream-copy each segment and join them with the concat demuxer
+fn concat_copy(
+    input: &Path,
+    output: &Path,
+    segments: &[(f64, f64)],
+    intermediate: bool,
+) -> Result<()> {
+    let ext = output.extension().and_then(|e| e.to_str()).unwrap_or("mkv");
+    // The parts are as large as the output, so keep them on its drive
+    let stem = output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let dir = output.with_file_name(format!(".{}.parts", stem));
+    std::fs::create_dir_all(&dir)?;
+    let maps = if intermediate {
+        ["-map", "0:v:0?", "-map", "0:a:0?"].as_slice()
+    } else {
+        ["-map", "0"].as_slice()
+    };
+
+    let result = (|| {
+        let mut list = String::new();
+        for (i, &(start, end)) in segments.iter().enumerate() {
+            let part = dir.join(format!("part{:03}.{}", i, ext));
+            let trim = Trim {
+                start: Some(start),
+                end: Some(end),
+            };
+            let mut args = trim.input_args();
+            args.extend(["-i", input.to_str().unwrap()].map(String::from));
+            args.extend(maps.iter().map(|s| s.to_string()));
+            args.extend(
+                [
+                    "-c",
+                    "copy",
+                    "-avoid_negative_ts",
+                    "make_zero",
+                    "-y",
+                    part.to_str().unwrap(),
+                ]
+                .map(String::from),
+            );
+            ffmpeg::execute_ffmpeg(&args.iter().map(|s| s.as_str()).collect::<Vec<_>>())?;
+            list.push_str(&concat_list_entry(&part));
+        }
+
+        let list_file = dir.join("list.txt");
+        std::fs::write(&list_file, list)?;
+        ffmpeg::execute_ffmpeg(&[
+            "-f",
+            "concat",
+            "-safe",
+            "0",
+            "-i",
+            list_file.to_str().unwrap(),
+            "-map",
+            "0",
+            "-c",
+            "copy",
+            "-y",
+            output.to_str().unwrap(),
+        ])
+    })();
+
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+/// Ranges removed before a processing command runs (`--cut`), with
+/// `--start`/`--end` folded in as cuts of the head and tail
+#[derive(Debug, Clone, Default)]
+pub struct CutList {
+    pub ranges: Vec<(f64, Option<f64>)>,
+    pub crossfade: Option<f64>, // Audio crossfade at each join, seconds
+}
+
+impl CutList {
+    /// Intermediate written next to `output`, so it lands on the same drive
+    pub fn intermediate_path(output: &Path) -> PathBuf {
+        let stem = output
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
+        output.with_file_name(format!(".{}.cut.mkv", stem))
+    }
+
+    /// Cut the ranges out of `input` into an intermediate next to `output`
+    /// (stream-copied when the cuts fall on keyframes, FFV1/FLAC otherwise),
+    /// run `process` on it and delete it again
+    pub fn process<T>(
+        &self,
+        input: &Path,
+        output: &Path,
+        process: impl FnOnce(&Path) -> Result<T>,
+    ) -> Result<T> {
+        let intermediate = Self::intermediate_path(output);
+        let opts = CutOptions {
+            crossfade: self.crossfade,
+            reencode: false,
+            encoder: VideoEncoder::new(VideoCodec::Ffv1),
+            intermediate: true,
+        };
+        let result = cut_segments(input, &intermediate, &self.ranges, &opts)
+            .and_then(|_| process(&intermediate));
+        let _ = std::fs::remove_file(&intermediate);
+        result
+    }
+}
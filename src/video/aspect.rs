@@ -0,0 +1,163 @@
+#![allow(dead_code)]
+
+use crate::ffmpeg::VideoStreamInfo;
+
+/// Shape of the picture as it should be displayed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplayAspect {
+    Standard,   // 4:3
+    Widescreen, // 16:9 (anamorphic camcorder / widescreen switch)
+}
+
+impl DisplayAspect {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "4:3" | "4/3" | "standard" => Some(DisplayAspect::Standard),
+            "16:9" | "16/9" | "wide" | "widescreen" => Some(DisplayAspect::Widescreen),
+            _ => None,
+        }
+    }
+}
+
+/// Which part of an SD raster the display aspect refers to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParConvention {
+    Itu,  // BT.601: the 704 active samples are 4:3 (10:11 NTSC, 12:11 PAL)
+    Mpeg, // The whole 720 raster is 4:3 (8:9 NTSC, 16:15 PAL)
+}
+
+impl ParConvention {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "itu" | "bt601" => Some(ParConvention::Itu),
+            "mpeg" | "generic" => Some(ParConvention::Mpeg),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with non-square pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AspectHandling {
+    Anamorphic,   // Keep the raster, flag the SAR
+    SquarePixels, // Resample the width to square pixels
+}
+
+impl AspectHandling {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "keep" | "anamorphic" => Some(AspectHandling::Anamorphic),
+            "square" => Some(AspectHandling::SquarePixels),
+            _ => None,
+        }
+    }
+}
+
+/// Pixel aspect settings for SD sources
+#[derive(Debug, Clone, PartialEq)]
+pub struct AspectOptions {
+    pub display: Option<DisplayAspect>, // None = from the source's SAR/DAR flags, else 4:3
+    pub convention: ParConvention,
+    pub handling: AspectHandling,
+}
+
+impl Default for AspectOptions {
+    fn default() -> Self {
+        Self {
+            display: None,
+            convention: ParConvention::Itu,
+            handling: AspectHandling::Anamorphic,
+        }
+    }
+}
+
+/// Sample aspect ratio of a raster and how to apply it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelAspect {
+    pub sar: (u32, u32),
+    pub handling: AspectHandling,
+}
+
+impl PixelAspect {
+    /// setsar for anamorphic output, or a width resample followed by setsar=1
+    pub fn filters(&self) -> Vec<String> {
+        let (num, den) = self.sar;
+        match self.handling {
+            AspectHandling::Anamorphic => vec![format!("setsar={}/{}", num, den)],
+            AspectHandling::SquarePixels if num == den => vec!["setsar=1".to_string()],
+            AspectHandling::SquarePixels => vec![
+                format!("scale=trunc(iw*{}/{}/2+0.5)*2:ih:flags=lanczos", num, den),
+                "setsar=1".to_string(),
+            ],
+        }
+    }
+}
+
+/// Sample aspect ratio of an SD raster (720 or 704 wide, 480/486 or 576
+/// lines); None for other rasters, which are treated as square
+pub fn sd_sample_aspect(
+    width: u32,
+    height: u32,
+    display: DisplayAspect,
+    convention: ParConvention,
+) -> Option<(u32, u32)> {
+    let pal = match height {
+        480 | 486 => false,
+        576 => true,
+        _ => return None,
+    };
+    // 704 wide rasters are the active area, so both conventions agree
+    let convention = match width {
+        704 => ParConvention::Itu,
+        720 => convention,
+        _ => return None,
+    };
+
+    Some(match (convention, pal, display) {
+        (ParConvention::Itu, false, DisplayAspect::Standard) => (10, 11),
+        (ParConvention::Itu, true, DisplayAspect::Standard) => (12, 11),
+        (ParConvention::Itu, false, DisplayAspect::Widescreen) => (40, 33),
+        (ParConvention::Itu, true, DisplayAspect::Widescreen) => (16, 11),
+        (ParConvention::Mpeg, false, DisplayAspect::Standard) => (8, 9),
+        (ParConvention::Mpeg, true, DisplayAspect::Standard) => (16, 15),
+        (ParConvention::Mpeg, false, DisplayAspect::Widescreen) => (32, 27),
+        (ParConvention::Mpeg, true, DisplayAspect::Widescreen) => (64, 45),
+    })
+}
+
+/// Display aspect the source is flagged with: its DAR, or the DAR its SAR
+/// gives the raster. None when the file carries neither.
+pub fn source_display_aspect(stream: &VideoStreamInfo) -> Option<DisplayAspect> {
+    let ratio = match (stream.display_aspect, stream.sample_aspect) {
+        (Some((num, den)), _) => num as f64 / den as f64,
+        (None, Some((num, den))) if stream.height > 0 => {
+            stream.width as f64 * num as f64 / den as f64 / stream.height as f64
+        }
+        _ => return None,
+    };
+    // Halfway between 4:3 (1.33) and 16:9 (1.78)
+    Some(if ratio > 1.55 {
+        DisplayAspect::Widescreen
+    } else {
+        DisplayAspect::Standard
+    })
+}
+
+/// Plan the pixel aspect handling for a probed stream. Without a display
+/// aspect in the options, the one the source is flagged with is kept.
+pub fn plan_pixel_aspect(stream: &VideoStreamInfo, opts: &AspectOptions) -> Option<PixelAspect> {
+    let display = opts
+        .display
+        .or_else(|| source_display_aspect(stream))
+        .unwrap_or(DisplayAspect::Standard);
+    sd_sample_aspect(stream.width, stream.height, display, opts.convention).map(|sar| PixelAspect {
+        sar,
+        handling: opts.handling,
+    })
+}
+
+/// Width of a raster after resampling to square pixels, rounded to even
+pub fn square_width(width: u32, sar: (u32, u32)) -> u32 {
+    let w = width as f64 * sar.0 as f64 / sar.1 as f64;
+    ((w / 2.0).round() as u32) * 2
+}
@@ -0,0 +1,378 @@
+#![allow(dead_code)]
+
+use super::framerate::RateConversion;
+use crate::ffmpeg;
+use anyhow::Result;
+use std::path::Path;
+
+/// Frames per second sampled by the colour analysis pass
+pub const AUTOCOLOR_SAMPLE_FPS: u32 = 2;
+
+/// How corrections follow the content over time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AutoColorMode {
+    Smoothed { window_secs: f64 }, // Fixed windows, interpolated between windows
+    PerScene,                      // One correction per detected scene
+}
+
+impl AutoColorMode {
+    /// `smooth` (10 s windows) or `scene`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "smooth" | "smoothed" => Some(AutoColorMode::Smoothed { window_secs: 10.0 }),
+            "scene" | "per-scene" => Some(AutoColorMode::PerScene),
+            _ => None,
+        }
+    }
+}
+
+/// Auto white balance / levels settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct AutoColorOptions {
+    pub mode: AutoColorMode,
+    pub strength: f32,        // 0.0 = no change, 1.0 = full correction
+    pub max_stretch: f32,     // Upper limit for the levels gain
+    pub max_wb_gain: f32,     // Upper limit for a single channel gain (and 1/x lower limit)
+    pub scene_threshold: f32, // Mean luma jump (0-255) treated as a scene cut
+}
+
+impl Default for AutoColorOptions {
+    fn default() -> Self {
+        Self {
+            mode: AutoColorMode::Smoothed { window_secs: 10.0 },
+            strength: 0.7,
+            max_stretch: 1.5,
+            max_wb_gain: 1.25,
+            scene_threshold: 30.0,
+        }
+    }
+}
+
+/// signalstats values of one sampled frame (8-bit, limited range YUV)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameStats {
+    pub time: f64,
+    pub ymin: f32,
+    pub ymax: f32,
+    pub yavg: f32,
+    pub uavg: f32,
+    pub vavg: f32,
+}
+
+/// colorlevels input black/white points (0.0-1.0) for R, G and B from `start` seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelsCorrection {
+    pub start: f64,
+    pub imin: [f32; 3],
+    pub imax: [f32; 3],
+}
+
+/// Measured colour correction for a whole file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorCorrection {
+    pub segments: Vec<LevelsCorrection>,
+    pub interpolate: bool, // Ramp linearly from one segment start to the next instead of switching
+}
+
+impl ColorCorrection {
+    /// Filters applying the correction: a plain colorlevels for a single
+    /// segment, or sendcmd switching (or ramping) colorlevels values
+    /// between segment starts
+    pub fn filters(&self) -> Vec<String> {
+        let Some(first) = self.segments.first() else {
+            return Vec::new();
+        };
+
+        let levels = format!(
+            "colorlevels={}",
+            levels_params(first)
+                .iter()
+                .map(|(name, value)| format!("{}={:.4}", name, value))
+                .collect::<Vec<_>>()
+                .join(":")
+        );
+        if self.segments.len() == 1 {
+            return vec![levels];
+        }
+
+        let commands: Vec<String> = if self.interpolate {
+            // [expr] commands are sent on every frame of the interval, TI
+            // runs from 0 to 1 across it
+            self.segments
+                .windows(2)
+                .map(|pair| {
+                    let cmds: Vec<String> = levels_params(&pair[0])
+                        .iter()
+                        .zip(levels_params(&pair[1]))
+                        .map(|((name, from), (_, to))| {
+                            format!(
+                                "[expr] colorlevels {} {:.4}+({:.4})*TI",
+                                name,
+                                from,
+                                to - from
+                            )
+                        })
+                        .collect();
+                    format!(
+                        "{:.3}-{:.3} {}",
+                        pair[0].start,
+                        pair[1].start,
+                        cmds.join(",")
+                    )
+                })
+                .collect()
+        } else {
+            self.segments[1..]
+                .iter()
+                .map(|seg| {
+                    let cmds: Vec<String> = levels_params(seg)
+                        .iter()
+                        .map(|(name, value)| format!("colorlevels {} {:.4}", name, value))
+                        .collect();
+                    format!("{:.3} {}", seg.start, cmds.join(","))
+                })
+                .collect()
+        };
+
+        vec![format!("sendcmd=c='{};'", commands.join(";")), levels]
+    }
+
+    /// Correction in effect at `time`
+    fn at(&self, time: f64) -> Option<LevelsCorrection> {
+        let i = self.segments.iter().rposition(|seg| seg.start <= time)?;
+        let mut seg = self.segments[i].clone();
+        if let (true, Some(next)) = (self.interpolate, self.segments.get(i + 1)) {
+            let t = ((time - seg.start) / (next.start - seg.start)) as f32;
+            for c in 0..3 {
+                seg.imin[c] += (next.imin[c] - seg.imin[c]) * t;
+                seg.imax[c] += (next.imax[c] - seg.imax[c]) * t;
+            }
+        }
+        seg.start = 0.0;
+        Some(seg)
+    }
+
+    /// Re-time the segments for an input that is seeked to `start_secs`
+    /// (timestamps then start at 0): the correction in effect at the seek
+    /// point comes first, earlier segments are dropped
+    pub fn after_seek(self, start_secs: f64) -> Self {
+        if start_secs <= 0.0 {
+            return self;
+        }
+        let first = self.at(start_secs);
+        let later = self
+            .segments
+            .iter()
+            .filter(|seg| seg.start > start_secs)
+            .map(|seg| LevelsCorrection {
+                start: seg.start - start_secs,
+                ..seg.clone()
+            });
+        Self {
+            segments: first.into_iter().chain(later).collect(),
+            interpolate: self.interpolate,
+        }
+    }
+
+    /// Re-time the segments for a filter chain that applies them after
+    /// `rate`: a speed change stretches the timeline by source/target
+    pub fn after_rate_conversion(self, rate: Option<&RateConversion>) -> Self {
+        let stretch = match rate {
+            Some(RateConversion::SpeedChange { source, target }) => {
+                source.as_f64() / target.as_f64()
+            }
+            _ => return self,
+        };
+        Self {
+            segments: self
+                .segments
+                .into_iter()
+                .map(|seg| LevelsCorrection {
+                    start: seg.start * stretch,
+                    ..seg
+                })
+                .collect(),
+            interpolate: self.interpolate,
+        }
+    }
+}
+
+/// colorlevels option names and values of a segment
+fn levels_params(seg: &LevelsCorrection) -> Vec<(String, f32)> {
+    let mut params = Vec::new();
+    for (i, c) in ["r", "g", "b"].iter().enumerate() {
+        params.push((format!("{}imin", c), seg.imin[i]));
+        params.push((format!("{}imax", c), seg.imax[i]));
+    }
+    params
+}
+
+/// Parse `metadata=print` output of signalstats into per-frame stats
+pub fn parse_signalstats_output(log: &str) -> Vec<FrameStats> {
+    let mut frames = Vec::new();
+    let mut current: Option<FrameStats> = None;
+
+    for line in log.lines() {
+        if let Some(rest) = line.split("pts_time:").nth(1) {
+            if let Some(frame) = current.take() {
+                frames.push(frame);
+            }
+            current = Some(FrameStats {
+                time: rest
+                    .split_whitespace()
+                    .next()
+                    .and_then(|t| t.parse().ok())
+                    .unwrap_or(0.0),
+                ..Default::default()
+            });
+        } else if let (Some(frame), Some(rest)) =
+            (current.as_mut(), line.split("lavfi.signalstats.").nth(1))
+        {
+            let mut kv = rest.splitn(2, '=');
+            let (Some(key), Some(value)) = (kv.next(), kv.next()) else {
+                continue;
+            };
+            let Ok(value) = value.trim().parse::<f32>() else {
+                continue;
+            };
+            match key {
+                "YMIN" => frame.ymin = value,
+                "YMAX" => frame.ymax = value,
+                "YAVG" => frame.yavg = value,
+                "UAVG" => frame.uavg = value,
+                "VAVG" => frame.vavg = value,
+                _ => {}
+            }
+        }
+    }
+    if let Some(frame) = current {
+        frames.push(frame);
+    }
+
+    frames
+}
+
+/// Split the sampled frames into segments according to the mode
+fn segment_frames<'a>(frames: &'a [FrameStats], opts: &AutoColorOptions) -> Vec<&'a [FrameStats]> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+
+    for i in 1..frames.len() {
+        let split = match opts.mode {
+            AutoColorMode::Smoothed { window_secs } => {
+                frames[i].time - frames[start].time >= window_secs
+            }
+            AutoColorMode::PerScene => {
+                (frames[i].yavg - frames[i - 1].yavg).abs() > opts.scene_threshold
+            }
+        };
+        if split {
+            segments.push(&frames[start..i]);
+            start = i;
+        }
+    }
+    if start < frames.len() {
+        segments.push(&frames[start..]);
+    }
+
+    segments
+}
+
+fn median(mut values: Vec<f32>) -> f32 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    values[values.len() / 2]
+}
+
+/// Levels stretch and gray-world white balance for one segment, limited and
+/// blended with the identity by `strength`
+fn correct_segment(frames: &[FrameStats], opts: &AutoColorOptions) -> LevelsCorrection {
+    let ymin = median(frames.iter().map(|f| f.ymin).collect());
+    let ymax = median(frames.iter().map(|f| f.ymax).collect());
+    let n = frames.len().max(1) as f32;
+    let y = frames.iter().map(|f| f.yavg).sum::<f32>() / n;
+    let u = frames.iter().map(|f| f.uavg).sum::<f32>() / n - 128.0;
+    let v = frames.iter().map(|f| f.vavg).sum::<f32>() / n - 128.0;
+
+    // Limited range luma to 0.0-1.0
+    let mut black = ((ymin - 16.0) / 219.0).clamp(0.0, 0.5);
+    let mut white = ((ymax - 16.0) / 219.0).clamp(0.5, 1.0);
+    if white - black < 1.0 / opts.max_stretch {
+        let mid = (white + black) / 2.0;
+        black = mid - 0.5 / opts.max_stretch;
+        white = mid + 0.5 / opts.max_stretch;
+    }
+
+    // Gray world: average RGB (BT.601) should be neutral
+    let avg = [y + 1.402 * v, y - 0.344 * u - 0.714 * v, y + 1.772 * u];
+    let gray = (avg[0] + avg[1] + avg[2]) / 3.0;
+
+    let mut imin = [0.0; 3];
+    let mut imax = [1.0; 3];
+    for c in 0..3 {
+        let gain = if avg[c] > 1.0 {
+            (gray / avg[c]).clamp(1.0 / opts.max_wb_gain, opts.max_wb_gain)
+        } else {
+            1.0
+        };
+        let full_min = black;
+        let full_max = (black + (white - black) / gain).min(1.0);
+        imin[c] = opts.strength * full_min;
+        imax[c] = 1.0 + opts.strength * (full_max - 1.0);
+    }
+
+    LevelsCorrection {
+        start: frames.first().map(|f| f.time).unwrap_or(0.0),
+        imin,
+        imax,
+    }
+}
+
+/// Compute the correction from sampled frame statistics
+pub fn plan_correction(frames: &[FrameStats], opts: &AutoColorOptions) -> ColorCorrection {
+    let mut segments: Vec<LevelsCorrection> = segment_frames(frames, opts)
+        .into_iter()
+        .map(|seg| correct_segment(seg, opts))
+        .collect();
+
+    if let Some(first) = segments.first_mut() {
+        first.start = 0.0;
+    }
+    // Windows ramp into each other so corrections do not pump; scene cuts
+    // switch at once
+    ColorCorrection {
+        segments,
+        interpolate: matches!(opts.mode, AutoColorMode::Smoothed { .. }),
+    }
+}
+
+/// Measure the input with signalstats and plan levels/white balance correction
+pub fn analyze_color(input: &Path, opts: &AutoColorOptions) -> Result<ColorCorrection> {
+    ffmpeg::check_ffmpeg()?;
+
+    let vf = format!(
+        "fps={},signalstats,metadata=mode=print",
+        AUTOCOLOR_SAMPLE_FPS
+    );
+    let args = vec![
+        "-hide_banner",
+        "-i",
+        input.to_str().unwrap(),
+        "-vf",
+        &vf,
+        "-an",
+        "-f",
+        "null",
+        "-",
+    ];
+
+    let log = ffmpeg::execute_ffmpeg_with_log(&args)?;
+    let frames = parse_signalstats_output(&log);
+    if frames.is_empty() {
+        anyhow::bail!("signalstats produced no statistics for {}", input.display());
+    }
+
+    Ok(plan_correction(&frames, opts))
+}
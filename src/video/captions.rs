@@ -0,0 +1,477 @@
+#![allow(dead_code)]
+
+use super::framerate::RateConversion;
+use crate::ffmpeg;
+use crate::trim::{format_timestamp, Trim};
+use anyhow::Result;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// One caption on screen from `start` to `end` seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct Caption {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+/// Subtitle file formats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SubtitleFormat {
+    Srt,
+    WebVtt,
+}
+
+impl SubtitleFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "srt" => Some(SubtitleFormat::Srt),
+            "vtt" | "webvtt" => Some(SubtitleFormat::WebVtt),
+            _ => None,
+        }
+    }
+
+    /// Format from a file extension, SRT for anything but .vtt
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("vtt") => SubtitleFormat::WebVtt,
+            _ => SubtitleFormat::Srt,
+        }
+    }
+}
+
+/// Caption styles of CEA-608
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CaptionMode {
+    PopOn,      // Built off screen, shown whole at End Of Caption
+    RollUp(u8), // Rows scroll up at each carriage return
+    PaintOn,    // Written straight to the screen
+}
+
+/// Decoder for one CEA-608 data channel (CC1 or CC2 of field 1).
+/// Handles pop-on, roll-up and paint-on captions, the standard, special and
+/// extended character sets and doubled control codes; text mode and
+/// styling are ignored.
+#[derive(Debug, Clone)]
+pub struct Cea608Decoder {
+    channel: u8,
+    active: bool, // Last control code was for our channel
+    mode: CaptionMode,
+    displayed: Vec<String>,
+    hidden: Vec<String>,
+    last_control: Option<(u8, u8)>,
+    shown: Option<(f64, String)>, // Caption on screen since, and its text
+    captions: Vec<Caption>,
+}
+
+impl Cea608Decoder {
+    pub fn new(channel: u8) -> Self {
+        Self {
+            channel,
+            active: channel == 1,
+            mode: CaptionMode::PopOn,
+            displayed: Vec::new(),
+            hidden: Vec::new(),
+            last_control: None,
+            shown: None,
+            captions: Vec::new(),
+        }
+    }
+
+    /// Feed one byte pair (with parity bits) received at `time`
+    pub fn feed(&mut self, time: f64, b1: u8, b2: u8) {
+        let (b1, b2) = (b1 & 0x7f, b2 & 0x7f);
+        if b1 == 0 && b2 == 0 {
+            return; // Padding
+        }
+
+        if (0x10..=0x1f).contains(&b1) {
+            // Control codes are sent twice; act on the first only
+            if self.last_control == Some((b1, b2)) {
+                self.last_control = None;
+                return;
+            }
+            self.last_control = Some((b1, b2));
+            self.active = (b1 & 0x08 == 0) == (self.channel == 1);
+            if self.active {
+                self.control(time, b1 & 0x17, b2);
+            }
+            return;
+        }
+
+        self.last_control = None;
+        if self.active {
+            for b in [b1, b2] {
+                if b >= 0x20 {
+                    self.write_char(time, standard_char(b));
+                }
+            }
+        }
+    }
+
+    /// Close the caption still on screen and return all captions
+    pub fn finish(mut self, time: f64) -> Vec<Caption> {
+        self.close_shown(time);
+        self.captions
+    }
+
+    fn control(&mut self, time: f64, b1: u8, b2: u8) {
+        match (b1, b2) {
+            // Special characters (®, ½, ♪, ...)
+            (0x11, 0x30..=0x3f) => self.write_char(time, special_char(b2)),
+            // Mid-row style codes show as a space
+            (0x11, 0x20..=0x2f) => self.write_char(time, ' '),
+            // Extended characters replace the standard fallback sent before them
+            (0x12 | 0x13, 0x20..=0x3f) => {
+                self.backspace();
+                self.write_char(time, extended_char(b1, b2));
+            }
+            (0x14 | 0x15, 0x20..=0x2f) => self.misc_control(time, b2),
+            // Tab offsets
+            (0x17, 0x21..=0x23) => {}
+            // Preamble address codes: the text moves to another row
+            (_, 0x40..=0x7f) => self.new_row(),
+            _ => {}
+        }
+    }
+
+    fn misc_control(&mut self, time: f64, code: u8) {
+        match code {
+            0x20 => self.mode = CaptionMode::PopOn, // Resume caption loading
+            0x21 => self.backspace(),
+            0x24 => {} // Delete to end of row
+            0x25..=0x27 => {
+                // Roll-up with 2, 3 or 4 rows
+                if !matches!(self.mode, CaptionMode::RollUp(_)) {
+                    self.close_shown(time);
+                    self.displayed.clear();
+                }
+                self.mode = CaptionMode::RollUp(code - 0x23);
+            }
+            0x29 => self.mode = CaptionMode::PaintOn, // Resume direct captioning
+            0x2c => {
+                // Erase displayed memory
+                self.close_shown(time);
+                self.displayed.clear();
+            }
+            0x2d => self.carriage_return(time),
+            0x2e => self.hidden.clear(), // Erase non-displayed memory
+            0x2f => {
+                // End of caption: show the caption built off screen
+                self.close_shown(time);
+                self.displayed = std::mem::take(&mut self.hidden);
+                self.open_shown(time);
+            }
+            _ => {}
+        }
+    }
+
+    fn memory(&mut self) -> &mut Vec<String> {
+        match self.mode {
+            CaptionMode::PopOn => &mut self.hidden,
+            CaptionMode::RollUp(_) | CaptionMode::PaintOn => &mut self.displayed,
+        }
+    }
+
+    fn write_char(&mut self, time: f64, c: char) {
+        let memory = self.memory();
+        match memory.last_mut() {
+            Some(row) => row.push(c),
+            None => memory.push(c.to_string()),
+        }
+        if self.mode == CaptionMode::PaintOn && self.shown.is_none() {
+            self.open_shown(time);
+        }
+    }
+
+    fn backspace(&mut self) {
+        if let Some(row) = self.memory().last_mut() {
+            row.pop();
+        }
+    }
+
+    fn new_row(&mut self) {
+        let memory = self.memory();
+        if memory.last().is_some_and(|row| !row.trim().is_empty()) {
+            memory.push(String::new());
+        }
+    }
+
+    /// Roll-up: the finished row joins the rows on screen
+    fn carriage_return(&mut self, time: f64) {
+        let CaptionMode::RollUp(rows) = self.mode else {
+            self.new_row();
+            return;
+        };
+        self.close_shown(time);
+        self.displayed.retain(|row| !row.trim().is_empty());
+        // The base row is left free for the next line
+        while self.displayed.len() > (rows as usize).saturating_sub(1).max(1) {
+            self.displayed.remove(0);
+        }
+        self.open_shown(time);
+        self.displayed.push(String::new());
+    }
+
+    fn shown_text(&self) -> String {
+        self.displayed
+            .iter()
+            .map(|row| row.trim())
+            .filter(|row| !row.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn open_shown(&mut self, time: f64) {
+        self.shown = Some((time, self.shown_text()));
+    }
+
+    /// End the caption on screen. Paint-on captions grow while shown, so
+    /// they take the text on screen now rather than when they appeared.
+    fn close_shown(&mut self, time: f64) {
+        if let Some((start, text)) = self.shown.take() {
+            let text = match self.mode {
+                CaptionMode::PaintOn => self.shown_text(),
+                _ => text,
+            };
+            if !text.is_empty() && time > start {
+                self.captions.push(Caption {
+                    start,
+                    end: time,
+                    text,
+                });
+            }
+        }
+    }
+}
+
+/// Standard character set: ASCII with a few accented letters
+fn standard_char(b: u8) -> char {
+    match b {
+        0x2a => 'á',
+        0x5c => 'é',
+        0x5e => 'í',
+        0x5f => 'ó',
+        0x60 => 'ú',
+        0x7b => 'ç',
+        0x7c => '÷',
+        0x7d => 'Ñ',
+        0x7e => 'ñ',
+        0x7f => '█',
+        b => b as char,
+    }
+}
+
+fn special_char(b: u8) -> char {
+    const CHARS: [char; 16] = [
+        '®', '°', '½', '¿', '™', '¢', '£', '♪', 'à', ' ', 'è', 'â', 'ê', 'î', 'ô', 'û',
+    ];
+    CHARS[(b - 0x30) as usize]
+}
+
+/// Extended Spanish/French (0x12) and Portuguese/German (0x13) characters
+fn extended_char(b1: u8, b2: u8) -> char {
+    const SPANISH_FRENCH: [char; 32] = [
+        'Á', 'É', 'Ó', 'Ú', 'Ü', 'ü', '‘', '¡', '*', '\'', '—', '©', '℠', '•', '“', '”', 'À', 'Â',
+        'Ç', 'È', 'Ê', 'Ë', 'ë', 'Î', 'Ï', 'ï', 'Ô', 'Ù', 'ù', 'Û', '«', '»',
+    ];
+    const PORTUGUESE_GERMAN: [char; 32] = [
+        'Ã', 'ã', 'Í', 'Ì', 'ì', 'Ò', 'ò', 'Õ', 'õ', '{', '}', '\\', '^', '_', '|', '~', 'Ä', 'ä',
+        'Ö', 'ö', 'ß', '¥', '¤', '¦', 'Å', 'å', 'Ø', 'ø', '┌', '┐', '└', '┘',
+    ];
+    let table = if b1 == 0x12 {
+        &SPANISH_FRENCH
+    } else {
+        &PORTUGUESE_GERMAN
+    };
+    table[(b2 - 0x20) as usize]
+}
+
+/// Parse `readeia608,metadata=mode=print` output into (time, byte 1, byte 2),
+/// taking the first code found in each frame
+pub fn parse_readeia608_output(log: &str) -> Vec<(f64, u8, u8)> {
+    let mut codes = Vec::new();
+    let mut time: Option<f64> = None;
+
+    for line in log.lines() {
+        if let Some(rest) = line.split("pts_time:").nth(1) {
+            time = rest.split_whitespace().next().and_then(|t| t.parse().ok());
+        } else if let Some(rest) = line.split("lavfi.readeia608.0.cc=").nth(1) {
+            let (Some(t), Some(hex)) = (time.take(), rest.trim().strip_prefix("0x")) else {
+                continue;
+            };
+            if let Ok(code) = u16::from_str_radix(hex, 16) {
+                codes.push((t, (code >> 8) as u8, (code & 0xff) as u8));
+            }
+        }
+    }
+    codes
+}
+
+/// Decode the captions of one channel (1 = CC1, 2 = CC2)
+pub fn decode_captions(codes: &[(f64, u8, u8)], channel: u8, end: f64) -> Vec<Caption> {
+    let mut decoder = Cea608Decoder::new(channel);
+    for &(time, b1, b2) in codes {
+        decoder.feed(time, b1, b2);
+    }
+    decoder.finish(end)
+}
+
+/// Move captions onto the timeline of a trimmed and re-timed output:
+/// `start` is subtracted, captions past `end` dropped and a speed change
+/// applied
+pub fn retime_captions(
+    captions: &[Caption],
+    trim: &Trim,
+    rate: Option<&RateConversion>,
+) -> Vec<Caption> {
+    let offset = trim.start.unwrap_or(0.0);
+    let limit = trim.end.unwrap_or(f64::INFINITY);
+    let stretch = match rate {
+        Some(RateConversion::SpeedChange { source, target }) => source.as_f64() / target.as_f64(),
+        _ => 1.0,
+    };
+
+    captions
+        .iter()
+        .filter(|c| c.end > offset && c.start < limit)
+        .map(|c| Caption {
+            start: (c.start.max(offset) - offset) * stretch,
+            end: (c.end.min(limit) - offset) * stretch,
+            text: c.text.clone(),
+        })
+        .collect()
+}
+
+/// Captions as an SRT or WebVTT file
+pub fn subtitle_file(captions: &[Caption], format: SubtitleFormat) -> String {
+    let mut out = String::new();
+    if format == SubtitleFormat::WebVtt {
+        out.push_str("WEBVTT\n\n");
+    }
+    for (i, c) in captions.iter().enumerate() {
+        let (start, end) = match format {
+            SubtitleFormat::Srt => {
+                let _ = writeln!(out, "{}", i + 1);
+                (
+                    format_timestamp(c.start).replace('.', ","),
+                    format_timestamp(c.end).replace('.', ","),
+                )
+            }
+            SubtitleFormat::WebVtt => (format_timestamp(c.start), format_timestamp(c.end)),
+        };
+        let _ = writeln!(out, "{} --> {}\n{}\n", start, end, c.text);
+    }
+    out
+}
+
+/// Read the line-21 captions of an unscaled, uncropped capture
+pub fn extract_captions(input: &Path, channel: u8) -> Result<Vec<Caption>> {
+    ffmpeg::check_ffmpeg()?;
+
+    let stream = ffmpeg::probe_video_stream(input)?;
+    let args = vec![
+        "-hide_banner",
+        "-i",
+        input.to_str().unwrap(),
+        "-vf",
+        "readeia608=lp=1,metadata=mode=print",
+        "-an",
+        "-f",
+        "null",
+        "-",
+    ];
+
+    let log = ffmpeg::execute_ffmpeg_with_log(&args)?;
+    Ok(decode_captions(
+        &parse_readeia608_output(&log),
+        channel,
+        stream.duration,
+    ))
+}
+
+/// Subtitle codec for the output container
+fn subtitle_codec(output: &Path) -> &'static str {
+    match output
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase())
+        .as_deref()
+    {
+        Some("mp4") | Some("m4v") | Some("mov") => "mov_text",
+        Some("webm") => "webvtt",
+        _ => "srt",
+    }
+}
+
+/// Copy `video` to `output` with a subtitle file added as a caption track.
+/// Existing subtitle tracks are kept as they are.
+pub fn mux_subtitles(video: &Path, subtitles: &Path, output: &Path) -> Result<()> {
+    ffmpeg::check_ffmpeg()?;
+
+    // The new track comes after the subtitle tracks the video already has
+    let track = ffmpeg::count_streams(video, "subtitle")?;
+    let codec_arg = format!("-c:s:{}", track);
+    let metadata_arg = format!("-metadata:s:s:{}", track);
+
+    ffmpeg::execute_ffmpeg(&[
+        "-i",
+        video.to_str().unwrap(),
+        "-i",
+        subtitles.to_str().unwrap(),
+        "-map",
+        "0",
+        "-map",
+        "1",
+        "-c",
+        "copy",
+        &codec_arg,
+        subtitle_codec(output),
+        &metadata_arg,
+        "title=Closed captions",
+        "-y",
+        output.to_str().unwrap(),
+    ])
+}
+
+/// Add captions as a subtitle track to an already written file, in place.
+/// The remux is written next to the file and renamed over it, so a large
+/// master is never copied to the temp directory.
+pub fn add_caption_track(output: &Path, captions: &[Caption]) -> Result<()> {
+    let stem = output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let ext = output.extension().and_then(|e| e.to_str()).unwrap_or("mkv");
+    let subtitles = output.with_file_name(format!(".{}.captions.srt", stem));
+    let remuxed = output.with_file_name(format!(".{}.captions.{}", stem, ext));
+
+    let result = (|| {
+        std::fs::write(&subtitles, subtitle_file(captions, SubtitleFormat::Srt))?;
+        mux_subtitles(output, &subtitles, &remuxed)?;
+        std::fs::rename(&remuxed, output)?;
+        Ok(())
+    })();
+
+    let _ = std::fs::remove_file(&subtitles);
+    if result.is_err() {
+        let _ = std::fs::remove_file(&remuxed);
+    }
+    result
+}
+
+/// Read the CC1 captions of `source` and add them to an already written
+/// output, following its trim and speed change. Returns the number of
+/// captions.
+pub fn embed_captions(
+    source: &Path,
+    output: &Path,
+    trim: &Trim,
+    rate: Option<&RateConversion>,
+) -> Result<usize> {
+    let captions = retime_captions(&extract_captions(source, 1)?, trim, rate);
+    if !captions.is_empty() {
+        add_caption_track(output, &captions)?;
+    }
+    Ok(captions.len())
+}
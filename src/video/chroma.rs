@@ -0,0 +1,169 @@
+#![allow(dead_code)]
+
+use crate::ffmpeg;
+use crate::trim::Trim;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Number of frames sampled across the trimmed input when estimating chroma
+/// delay
+pub const CHROMA_SAMPLE_FRAMES: u32 = 10;
+
+/// Largest chroma offset searched, in chroma pixels
+pub const MAX_CHROMA_SHIFT: i32 = 8;
+
+/// Chroma delay and chroma noise correction
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChromaCorrection {
+    pub shift_x: i32, // Chroma pixels, negative moves colour left
+    pub shift_y: i32, // Chroma lines, negative moves colour up
+    #[serde(serialize_with = "crate::preset::serialize_f32")]
+    pub denoise_spatial: f32, // Chroma-only fftdnoiz sigma, 0 = off
+    #[serde(serialize_with = "crate::preset::serialize_f32")]
+    pub denoise_temporal: f32, // Chroma-only atadenoise strength, 0 = off
+}
+
+impl Default for ChromaCorrection {
+    fn default() -> Self {
+        Self {
+            shift_x: 0,
+            shift_y: 0,
+            denoise_spatial: 6.0, // Stronger than the luma denoise, VHS chroma is very noisy
+            denoise_temporal: 8.0,
+        }
+    }
+}
+
+/// Build the chroma shift and chroma-only denoise filters
+pub fn chroma_filters(chroma: &ChromaCorrection) -> Vec<String> {
+    let mut filters = Vec::new();
+
+    if chroma.shift_x != 0 || chroma.shift_y != 0 {
+        filters.push(format!(
+            "chromashift=cbh={x}:cbv={y}:crh={x}:crv={y}:edge=smear",
+            x = chroma.shift_x,
+            y = chroma.shift_y
+        ));
+    }
+
+    // Both filters take a plane mask; 6 = U and V, luma is never touched.
+    // (hqdn3d cannot do this: a luma strength of 0 means "default".)
+    if chroma.denoise_spatial > 0.0 {
+        filters.push(format!(
+            "fftdnoiz=sigma={}:planes=6",
+            chroma.denoise_spatial
+        ));
+    }
+    if chroma.denoise_temporal > 0.0 {
+        // Scaled so the default strength of 8 gives atadenoise's own
+        // thresholds (0.02/0.04)
+        let a = chroma.denoise_temporal * 0.0025;
+        filters.push(format!(
+            "atadenoise=1a={a:.3}:1b={b:.3}:2a={a:.3}:2b={b:.3}:p=6",
+            a = a,
+            b = 2.0 * a
+        ));
+    }
+
+    filters
+}
+
+/// Estimate the chroma offset from yuv420p frames stored back to back by
+/// aligning chroma edges with luma edges. Returns the (x, y) shift in
+/// chroma pixels that moves chroma back onto luma.
+pub fn estimate_shift_from_frames(frames: &[u8], width: usize, height: usize) -> (i32, i32) {
+    let (cw, ch) = (width / 2, height / 2);
+    let frame_size = width * height + 2 * cw * ch;
+    if cw < 4 * MAX_CHROMA_SHIFT as usize || ch < 4 * MAX_CHROMA_SHIFT as usize {
+        return (0, 0);
+    }
+
+    let mut score_x = vec![0.0f64; (2 * MAX_CHROMA_SHIFT + 1) as usize];
+    let mut score_y = vec![0.0f64; (2 * MAX_CHROMA_SHIFT + 1) as usize];
+
+    for frame in frames.chunks_exact(frame_size) {
+        let (y_plane, chroma) = frame.split_at(width * height);
+        let (u_plane, v_plane) = chroma.split_at(cw * ch);
+
+        // Luma downsampled 2x2 to chroma resolution, chroma as one signal
+        let luma: Vec<f64> = (0..cw * ch)
+            .map(|i| {
+                let (x, y) = (i % cw * 2, i / cw * 2);
+                let p = |dx: usize, dy: usize| y_plane[(y + dy) * width + x + dx] as f64;
+                (p(0, 0) + p(1, 0) + p(0, 1) + p(1, 1)) / 4.0
+            })
+            .collect();
+        let color: Vec<f64> = u_plane
+            .iter()
+            .zip(v_plane)
+            .map(|(&u, &v)| u as f64 + v as f64)
+            .collect();
+
+        let grad_x = |p: &[f64], x: usize, y: usize| (p[y * cw + x + 1] - p[y * cw + x]).abs();
+        let grad_y = |p: &[f64], x: usize, y: usize| (p[(y + 1) * cw + x] - p[y * cw + x]).abs();
+
+        let m = MAX_CHROMA_SHIFT as usize;
+        for (i, d) in (-MAX_CHROMA_SHIFT..=MAX_CHROMA_SHIFT).enumerate() {
+            for y in m..ch - m - 1 {
+                for x in m..cw - m - 1 {
+                    let sx = (x as i32 + d) as usize;
+                    let sy = (y as i32 + d) as usize;
+                    score_x[i] += grad_x(&luma, x, y) * grad_x(&color, sx, y);
+                    score_y[i] += grad_y(&luma, x, y) * grad_y(&color, x, sy);
+                }
+            }
+        }
+    }
+
+    // Chroma edge found at +d means chroma lags luma by d, so shift back by -d
+    let best = |scores: &[f64]| {
+        let (i, _) =
+            scores
+                .iter()
+                .enumerate()
+                .fold((MAX_CHROMA_SHIFT as usize, 0.0), |best, (i, &s)| {
+                    if s > best.1 {
+                        (i, s)
+                    } else {
+                        best
+                    }
+                });
+        -(i as i32 - MAX_CHROMA_SHIFT)
+    };
+    (best(&score_x), best(&score_y))
+}
+
+/// Sample frames across the trimmed part of the input and estimate the
+/// chroma delay
+pub fn detect_chroma_shift(input: &Path, trim: &Trim) -> Result<(i32, i32)> {
+    ffmpeg::check_ffmpeg()?;
+
+    let stream = ffmpeg::probe_video_stream(input)?;
+    let mut raw = Vec::new();
+    for time in trim.sample_times(stream.duration, CHROMA_SAMPLE_FRAMES as usize) {
+        let time = format!("{:.3}", time);
+        let args = vec![
+            "-hide_banner",
+            "-ss",
+            &time,
+            "-i",
+            input.to_str().unwrap(),
+            "-vf",
+            "format=yuv420p",
+            "-frames:v",
+            "1",
+            "-f",
+            "rawvideo",
+            "-",
+        ];
+        raw.extend(ffmpeg::execute_ffmpeg_raw(&args)?);
+    }
+
+    Ok(estimate_shift_from_frames(
+        &raw,
+        stream.width as usize,
+        stream.height as usize,
+    ))
+}
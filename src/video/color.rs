@@ -0,0 +1,305 @@
+use super::ProcessingDepth;
+use crate::ffmpeg::{self, VideoStreamInfo};
+use anyhow::Result;
+use std::path::Path;
+
+/// Colour standard (primaries, matrix and transfer) of a video
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorStandard {
+    Bt601Ntsc, // SMPTE 170M, 525-line SD
+    Bt601Pal,  // BT.470 BG, 625-line SD
+    Bt709,     // HD
+}
+
+impl ColorStandard {
+    /// Name used by the colorspace filter and for primaries/matrix tags
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorStandard::Bt601Ntsc => "smpte170m",
+            ColorStandard::Bt601Pal => "bt470bg",
+            ColorStandard::Bt709 => "bt709",
+        }
+    }
+
+    /// Name used for the transfer characteristics tag
+    pub fn transfer_name(&self) -> &'static str {
+        match self {
+            ColorStandard::Bt709 => "bt709",
+            // Both SD systems use the BT.601 curve
+            ColorStandard::Bt601Ntsc | ColorStandard::Bt601Pal => "smpte170m",
+        }
+    }
+
+    /// Interpret an ffprobe primaries or matrix tag
+    pub fn from_tag(tag: &str) -> Option<ColorStandard> {
+        match tag {
+            "smpte170m" | "smpte240m" | "bt470m" => Some(ColorStandard::Bt601Ntsc),
+            "bt470bg" => Some(ColorStandard::Bt601Pal),
+            "bt709" => Some(ColorStandard::Bt709),
+            _ => None,
+        }
+    }
+
+    /// Standard implied by the raster when nothing is tagged
+    pub fn for_height(height: u32) -> ColorStandard {
+        match height {
+            h if h >= 720 => ColorStandard::Bt709,
+            576 | 288 => ColorStandard::Bt601Pal,
+            _ => ColorStandard::Bt601Ntsc,
+        }
+    }
+}
+
+/// Transfer characteristics the colorspace filter knows, from an ffprobe
+/// color_transfer tag
+pub fn transfer_from_tag(tag: &str) -> Option<&'static str> {
+    Some(match tag {
+        "bt709" => "bt709",
+        "smpte170m" => "smpte170m",
+        "bt470m" => "bt470m",
+        "bt470bg" => "bt470bg",
+        "smpte240m" => "smpte240m",
+        "linear" => "linear",
+        "iec61966-2-1" => "iec61966-2-1",
+        "iec61966-2-4" => "iec61966-2-4",
+        "bt2020-10" => "bt2020-10",
+        "bt2020-12" => "bt2020-12",
+        _ => return None,
+    })
+}
+
+/// Quantisation range
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorRange {
+    Tv, // Limited, 16-235
+    Pc, // Full, 0-255
+}
+
+impl ColorRange {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorRange::Tv => "tv",
+            ColorRange::Pc => "pc",
+        }
+    }
+
+    pub fn from_tag(tag: &str) -> Option<ColorRange> {
+        match tag {
+            "tv" | "mpeg" | "limited" => Some(ColorRange::Tv),
+            "pc" | "jpeg" | "full" => Some(ColorRange::Pc),
+            _ => None,
+        }
+    }
+}
+
+/// Colour conversion and output tagging
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorConversion {
+    pub source: ColorStandard,
+    pub target: ColorStandard,
+    pub source_transfer: Option<&'static str>, // Probed transfer, None = the source standard's
+    pub source_range: ColorRange,
+    pub target_range: ColorRange,
+}
+
+impl ColorConversion {
+    /// Transfer of the output: the probed one while the standard is kept,
+    /// the target standard's after a conversion
+    pub fn target_transfer(&self) -> &'static str {
+        match self.source_transfer {
+            Some(transfer) if self.source == self.target => transfer,
+            _ => self.target.transfer_name(),
+        }
+    }
+
+    /// colorspace filter, or None if source and target already match. A
+    /// probed transfer is converted from as tagged instead of the one the
+    /// source standard implies.
+    pub fn filter(&self) -> Option<String> {
+        if self.source == self.target && self.source_range == self.target_range {
+            return None;
+        }
+        let mut filter = format!(
+            "colorspace=all={}:iall={}:range={}:irange={}",
+            self.target.name(),
+            self.source.name(),
+            self.target_range.name(),
+            self.source_range.name()
+        );
+        if let Some(transfer) = self.source_transfer {
+            filter += &format!(":trc={}:itrc={}", self.target_transfer(), transfer);
+        }
+        Some(filter)
+    }
+
+    /// zscale equivalent of `filter`
+    pub fn zscale_filter(&self) -> Option<String> {
+        if self.source == self.target && self.source_range == self.target_range {
+            return None;
+        }
+        // zscale takes the colorspace names for matrix and primaries but
+        // has its own for the BT.601 curve and the ranges
+        let transfer = |name: &str| match name {
+            "bt709" | "bt470m" | "bt470bg" | "linear" | "iec61966-2-1" => name.to_string(),
+            "bt2020-10" => "2020_10".to_string(),
+            "bt2020-12" => "2020_12".to_string(),
+            _ => "601".to_string(),
+        };
+        let range = |range: ColorRange| match range {
+            ColorRange::Tv => "limited",
+            ColorRange::Pc => "full",
+        };
+        Some(format!(
+            "zscale=matrixin={}:primariesin={}:transferin={}:rangein={}:matrix={}:primaries={}:transfer={}:range={}",
+            self.source.name(),
+            self.source.name(),
+            transfer(self.source_transfer.unwrap_or(self.source.transfer_name())),
+            range(self.source_range),
+            self.target.name(),
+            self.target.name(),
+            transfer(self.target_transfer()),
+            range(self.target_range)
+        ))
+    }
+
+    /// The conversion filter for a chain running at `depth`: the colorspace
+    /// filter takes 8 to 12-bit input only, so 16-bit chains use zscale
+    pub fn filter_at(&self, depth: Option<ProcessingDepth>) -> Option<String> {
+        match depth {
+            Some(ProcessingDepth::Bits16) => self.zscale_filter(),
+            _ => self.filter(),
+        }
+    }
+
+    /// Encoder options that tag the output with the target colour standard
+    pub fn output_args(&self) -> Vec<String> {
+        vec![
+            "-color_primaries".to_string(),
+            self.target.name().to_string(),
+            "-color_trc".to_string(),
+            self.target_transfer().to_string(),
+            "-colorspace".to_string(),
+            self.target.name().to_string(),
+            "-color_range".to_string(),
+            self.target_range.name().to_string(),
+        ]
+    }
+}
+
+/// Source colour standard from the matrix/primaries tags, falling back to the raster size
+pub fn source_standard(stream: &VideoStreamInfo) -> ColorStandard {
+    stream
+        .color_space
+        .as_deref()
+        .and_then(ColorStandard::from_tag)
+        .or_else(|| {
+            stream
+                .color_primaries
+                .as_deref()
+                .and_then(ColorStandard::from_tag)
+        })
+        .unwrap_or_else(|| ColorStandard::for_height(stream.height))
+}
+
+/// Plan the conversion for an output of `output_height` lines: SD sources
+/// going to HD are converted BT.601 -> BT.709, output is always limited range
+pub fn plan_conversion(stream: &VideoStreamInfo, output_height: u32) -> ColorConversion {
+    let source = source_standard(stream);
+    let target = if output_height >= 720 {
+        ColorStandard::Bt709
+    } else {
+        source
+    };
+
+    ColorConversion {
+        source,
+        target,
+        source_transfer: stream.color_transfer.as_deref().and_then(transfer_from_tag),
+        source_range: stream
+            .color_range
+            .as_deref()
+            .and_then(ColorRange::from_tag)
+            .unwrap_or(ColorRange::Tv),
+        target_range: ColorRange::Tv,
+    }
+}
+
+/// Describe missing or suspicious colour tags on the source
+pub fn color_tag_warnings(stream: &VideoStreamInfo) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let implied = ColorStandard::for_height(stream.height);
+
+    let matrix = stream
+        .color_space
+        .as_deref()
+        .and_then(ColorStandard::from_tag);
+    let primaries = stream
+        .color_primaries
+        .as_deref()
+        .and_then(ColorStandard::from_tag);
+
+    if matrix.is_none() && primaries.is_none() {
+        warnings.push(format!(
+            "No colour tags on source, assuming {} from {} lines",
+            implied.name(),
+            stream.height
+        ));
+    }
+    if let (Some(m), Some(p)) = (matrix, primaries) {
+        if m != p {
+            warnings.push(format!(
+                "Matrix ({}) and primaries ({}) tags disagree",
+                m.name(),
+                p.name()
+            ));
+        }
+    }
+    if let Some(tagged) = matrix.or(primaries) {
+        let tagged_hd = tagged == ColorStandard::Bt709;
+        let raster_hd = implied == ColorStandard::Bt709;
+        if tagged_hd != raster_hd {
+            warnings.push(format!(
+                "Source is tagged {} but its {}-line raster suggests {}",
+                tagged.name(),
+                stream.height,
+                implied.name()
+            ));
+        }
+    }
+    if let Some(ref tag) = stream.color_transfer {
+        let standard = matrix.or(primaries).unwrap_or(implied);
+        // Only the BT.601/BT.709 curves say which kind of source they belong to
+        let hd_curve = match transfer_from_tag(tag) {
+            None => {
+                warnings.push(format!(
+                    "Source transfer {} is not supported, assuming {}",
+                    tag,
+                    standard.transfer_name()
+                ));
+                None
+            }
+            Some("bt709") => Some(true),
+            Some("smpte170m" | "bt470m" | "bt470bg") => Some(false),
+            Some(_) => None,
+        };
+        if hd_curve.is_some_and(|hd| hd != (standard == ColorStandard::Bt709)) {
+            warnings.push(format!(
+                "Transfer ({}) and matrix ({}) tags disagree",
+                tag,
+                standard.name()
+            ));
+        }
+    }
+    if stream.color_range.as_deref() == Some("pc") {
+        warnings
+            .push("Source is tagged full range (pc), it will be converted to tv range".to_string());
+    }
+
+    warnings
+}
+
+/// Probe the input and return its colour tag warnings
+pub fn check_color_tags(input: &Path) -> Result<Vec<String>> {
+    let stream = ffmpeg::probe_video_stream(input)?;
+    Ok(color_tag_warnings(&stream))
+}
@@ -0,0 +1,205 @@
+#![allow(dead_code)]
+
+use super::aspect::{sd_sample_aspect, source_display_aspect, square_width};
+use super::{
+    build_video_filters, check_depth, DisplayAspect, FrameRate, ParConvention, VideoEnhanceOptions,
+};
+use crate::ffmpeg::{self, VideoStreamInfo};
+use crate::trim::Trim;
+use anyhow::Result;
+use std::path::Path;
+
+/// How the source and the enhanced picture are put together
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareLayout {
+    SideBySide, // Full frames next to each other
+    Stacked,    // Source above, enhanced below
+    Wipe,       // One frame: source on the left, enhanced on the right
+}
+
+impl CompareLayout {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "side" | "side-by-side" | "hstack" => Some(CompareLayout::SideBySide),
+            "stacked" | "stack" | "vstack" => Some(CompareLayout::Stacked),
+            "wipe" | "split" => Some(CompareLayout::Wipe),
+            _ => None,
+        }
+    }
+}
+
+/// Before/after render settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompareOptions {
+    pub layout: CompareLayout,
+    pub height: u32,   // Height of each pane
+    pub wipe: f32,     // Position of the wipe, 0.1-0.9 of the width from the left
+    pub labels: bool,  // "Source" / "Enhanced" captions
+    pub duration: f64, // Length of the rendered segment when the trim has no end
+}
+
+impl Default for CompareOptions {
+    fn default() -> Self {
+        Self {
+            layout: CompareLayout::SideBySide,
+            height: 480,
+            wipe: 0.5,
+            labels: true,
+            duration: 10.0,
+        }
+    }
+}
+
+/// Size of each pane: `height` lines at the source's display aspect
+/// (SD rasters follow the options' SAR, else the source's flags, else 4:3)
+pub fn pane_size(stream: &VideoStreamInfo, opts: &VideoEnhanceOptions, height: u32) -> (u32, u32) {
+    let sar = opts.pixel_aspect.map(|p| p.sar).or_else(|| {
+        sd_sample_aspect(
+            stream.width,
+            stream.height,
+            source_display_aspect(stream).unwrap_or(DisplayAspect::Standard),
+            ParConvention::Itu,
+        )
+    });
+    let display_width = match sar {
+        Some(sar) => square_width(stream.width, sar),
+        None => stream.width,
+    };
+    let width = height as f64 * display_width as f64 / stream.height.max(1) as f64;
+    (((width / 2.0).round() as u32).max(1) * 2, height)
+}
+
+/// Fit a picture into the pane, resample it to `rate` and caption it
+fn pane_filters(size: (u32, u32), rate: Option<FrameRate>, label: Option<&str>) -> Vec<String> {
+    let (w, h) = size;
+    let mut filters = Vec::new();
+    if let Some(rate) = rate {
+        filters.push(format!("fps={}", rate));
+    }
+    filters.push(format!(
+        "scale={w}:{h}:force_original_aspect_ratio=decrease:flags=bicubic"
+    ));
+    filters.push(format!("pad={w}:{h}:(ow-iw)/2:(oh-ih)/2:black"));
+    filters.push("setsar=1".to_string());
+    if let Some(label) = label {
+        filters.push(format!(
+            "drawtext=text='{}':x=10:y=10:fontsize={}:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=4",
+            label,
+            (h / 20).max(12)
+        ));
+    }
+    filters
+}
+
+/// filter_complex splitting input 0 into the untouched source and the
+/// `enhance_video` chain, then joining both panes into `[v]`. Both panes
+/// run at the source rate (or the resampled rate) so they stay in step;
+/// speed changes are left out for the same reason.
+pub fn compare_filtergraph(
+    opts: &VideoEnhanceOptions,
+    compare: &CompareOptions,
+    stream: &VideoStreamInfo,
+) -> String {
+    let mut opts = opts.clone();
+    if opts.frame_rate.is_some_and(|r| r.changes_speed()) {
+        opts.frame_rate = None;
+    }
+    let rate = opts.frame_rate.map(|r| r.target()).or(stream.frame_rate);
+    let size = pane_size(stream, &opts, compare.height);
+    let (w, h) = size;
+    let wipe_x = ((w as f32 * compare.wipe.clamp(0.1, 0.9)) as u32 / 2) * 2;
+
+    let (source_label, enhanced_label) = if compare.labels {
+        (Some("Source"), Some("Enhanced"))
+    } else {
+        (None, None)
+    };
+
+    let mut source = pane_filters(size, rate, source_label);
+    let mut enhanced = build_video_filters(&opts);
+    enhanced.extend(pane_filters(size, rate, None));
+    if let Some(label) = enhanced_label {
+        // In a wipe the enhanced caption sits at the right edge
+        let x = if compare.layout == CompareLayout::Wipe {
+            "w-tw-10"
+        } else {
+            "10"
+        };
+        enhanced.push(format!(
+            "drawtext=text='{}':x={}:y=10:fontsize={}:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=4",
+            label,
+            x,
+            (h / 20).max(12)
+        ));
+    }
+
+    let join = match compare.layout {
+        CompareLayout::SideBySide => "[src][enh]hstack=inputs=2[v]".to_string(),
+        CompareLayout::Stacked => "[src][enh]vstack=inputs=2[v]".to_string(),
+        CompareLayout::Wipe => {
+            source.push(format!("crop={}:{}:0:0", wipe_x, h));
+            enhanced.push(format!("crop={}:{}:{}:0", w - wipe_x, h, wipe_x));
+            format!(
+                "[src][enh]hstack=inputs=2,drawbox=x={}:y=0:w=2:h={}:color=white@0.8:t=fill[v]",
+                wipe_x.saturating_sub(1),
+                h
+            )
+        }
+    };
+
+    [
+        "[0:v]split=2[s0][e0]".to_string(),
+        format!("[s0]{}[src]", source.join(",")),
+        format!("[e0]{}[enh]", enhanced.join(",")),
+        join,
+    ]
+    .join(";")
+}
+
+/// Render a short segment of the source next to the result of `enhance_video`
+/// with the same options, with the source audio. No colour matrix conversion
+/// is done so both panes are shown in the source colours.
+pub fn render_comparison(
+    input: &Path,
+    output: &Path,
+    opts: &VideoEnhanceOptions,
+    compare: &CompareOptions,
+) -> Result<()> {
+    ffmpeg::check_ffmpeg()?;
+
+    check_depth(opts.depth, &build_video_filters(opts))?;
+    let stream = ffmpeg::probe_video_stream(input)?;
+    let graph = compare_filtergraph(opts, compare, &stream);
+
+    let start = opts.trim.start.unwrap_or(0.0);
+    let trim = Trim {
+        start: opts.trim.start,
+        end: Some(opts.trim.end.unwrap_or(start + compare.duration)),
+    };
+    let trim_args = trim.input_args();
+    let encoder_args = opts.encoder.args();
+
+    let mut args: Vec<&str> = trim_args.iter().map(|s| s.as_str()).collect();
+    args.extend([
+        "-i",
+        input.to_str().unwrap(),
+        "-filter_complex",
+        &graph,
+        "-map",
+        "[v]",
+        "-map",
+        "0:a?",
+    ]);
+    args.extend(encoder_args.iter().map(|s| s.as_str()));
+    args.extend([
+        "-c:a",
+        "aac",
+        "-b:a",
+        "192k",
+        "-y",
+        output.to_str().unwrap(),
+    ]);
+
+    ffmpeg::execute_ffmpeg(&args)?;
+    Ok(())
+}
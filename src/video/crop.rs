@@ -0,0 +1,166 @@
+#![allow(dead_code)]
+
+use crate::ffmpeg;
+use crate::trim::Trim;
+use anyhow::Result;
+use std::path::Path;
+
+/// Points across the trimmed part of the file sampled by cropdetect
+pub const CROP_SAMPLE_POINTS: usize = 5;
+
+/// Frames analysed at each sample point
+pub const CROP_SAMPLE_FRAMES: u32 = 50;
+
+/// Crop rectangle in source pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CropRect {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl CropRect {
+    /// FFmpeg crop filter for this rectangle
+    pub fn filter(&self) -> String {
+        format!("crop={}:{}:{}:{}", self.width, self.height, self.x, self.y)
+    }
+
+    /// Smallest rectangle containing both rectangles
+    pub fn union(&self, other: &CropRect) -> CropRect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        CropRect {
+            width: right - x,
+            height: bottom - y,
+            x,
+            y,
+        }
+    }
+}
+
+/// Parse the last `crop=w:h:x:y` suggestion cropdetect printed to the log.
+/// cropdetect grows its rectangle over the analysed frames, so the last line
+/// covers everything seen in the segment.
+pub fn parse_cropdetect_output(log: &str) -> Option<CropRect> {
+    log.lines()
+        .filter(|line| line.contains("cropdetect"))
+        .filter_map(|line| line.split("crop=").nth(1))
+        .filter_map(parse_crop)
+        .next_back()
+}
+
+/// Parse a `w:h:x:y` crop specification
+pub fn parse_crop(spec: &str) -> Option<CropRect> {
+    let values: Vec<u32> = spec
+        .trim()
+        .split(':')
+        .map(|v| v.parse().ok())
+        .collect::<Option<_>>()?;
+    match values.as_slice() {
+        [width, height, x, y] if *width > 0 && *height > 0 => Some(CropRect {
+            width: *width,
+            height: *height,
+            x: *x,
+            y: *y,
+        }),
+        _ => None,
+    }
+}
+
+/// Run cropdetect over sampled parts of the trimmed input and return a
+/// stable crop rectangle (the union of the per-sample rectangles, so no
+/// picture is lost in bright scenes). Returns None if the full frame is in use.
+pub fn detect_crop(input: &Path, trim: &Trim) -> Result<Option<CropRect>> {
+    ffmpeg::check_ffmpeg()?;
+
+    let stream = ffmpeg::probe_video_stream(input)?;
+    let frames = CROP_SAMPLE_FRAMES.to_string();
+    let mut crop: Option<CropRect> = None;
+
+    for time in trim.sample_times(stream.duration, CROP_SAMPLE_POINTS) {
+        let start = format!("{:.3}", time);
+        let args = vec![
+            "-hide_banner",
+            "-ss",
+            &start,
+            "-i",
+            input.to_str().unwrap(),
+            "-vf",
+            "cropdetect=limit=24:round=2:reset=0",
+            "-frames:v",
+            &frames,
+            "-an",
+            "-f",
+            "null",
+            "-",
+        ];
+
+        let log = ffmpeg::execute_ffmpeg_with_log(&args)?;
+        if let Some(rect) = parse_cropdetect_output(&log) {
+            crop = Some(match crop {
+                Some(c) => c.union(&rect),
+                None => rect,
+            });
+        }
+    }
+
+    Ok(crop.filter(|c| c.width < stream.width || c.height < stream.height))
+}
+
+/// A decoded RGBA preview frame
+#[derive(Debug, Clone)]
+pub struct PreviewFrame {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Render a frame from the middle of the input with the crop rectangle
+/// outlined, scaled to `preview_width`, for confirmation before applying
+pub fn render_crop_preview(
+    input: &Path,
+    crop: &CropRect,
+    preview_width: u32,
+) -> Result<PreviewFrame> {
+    ffmpeg::check_ffmpeg()?;
+
+    let stream = ffmpeg::probe_video_stream(input)?;
+    if stream.width == 0 || stream.height == 0 {
+        anyhow::bail!("Could not determine video size of {}", input.display());
+    }
+    let preview_height = (stream.height * preview_width / stream.width) & !1;
+
+    let start = format!("{:.2}", stream.duration * 0.5);
+    let vf = format!(
+        "drawbox=x={}:y={}:w={}:h={}:color=red:t=4,scale={}:{},format=rgba",
+        crop.x, crop.y, crop.width, crop.height, preview_width, preview_height
+    );
+    let args = vec![
+        "-hide_banner",
+        "-ss",
+        &start,
+        "-i",
+        input.to_str().unwrap(),
+        "-vf",
+        &vf,
+        "-frames:v",
+        "1",
+        "-f",
+        "rawvideo",
+        "-",
+    ];
+
+    let rgba = ffmpeg::execute_ffmpeg_raw(&args)?;
+    if rgba.len() != (preview_width * preview_height * 4) as usize {
+        anyhow::bail!("Unexpected preview frame size from {}", input.display());
+    }
+
+    Ok(PreviewFrame {
+        width: preview_width,
+        height: preview_height,
+        rgba,
+    })
+}
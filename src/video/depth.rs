@@ -0,0 +1,115 @@
+use super::encoder::{PixelFormat, VideoCodec, VideoEncoder};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Filters FFmpeg only runs on 8-bit frames, with what to use instead. In a
+/// high bit depth chain FFmpeg quietly converts the frames down for them.
+const EIGHT_BIT_FILTERS: &[(&str, &str)] = &[
+    ("deshake", "turn stabilization off"),
+    ("nlmeans", "denoise with hqdn3d"),
+    (
+        "minterpolate",
+        "use blend frame rate conversion and neighbour dropout repair",
+    ),
+    ("removegrain", "turn dust removal off"),
+    ("eq", "use the automatic colour correction"),
+];
+
+/// Names of the filters in a chain, without pad labels and options
+pub fn filter_names(filters: &[String]) -> Vec<&str> {
+    filters
+        .iter()
+        .flat_map(|chain| chain.split([',', ';']))
+        .map(|filter| {
+            let mut filter = filter.trim_start();
+            while let Some(rest) = filter.strip_prefix('[') {
+                filter = rest.split_once(']').map_or("", |(_, rest)| rest);
+            }
+            filter.split(['=', '[']).next().unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Bit depth the filters run at; frames are upconverted to 4:2:2 at this
+/// depth before the first filter
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingDepth {
+    Bits10,
+    Bits16,
+}
+
+impl ProcessingDepth {
+    pub fn from_bits(bits: u32) -> Option<Self> {
+        match bits {
+            10 => Some(ProcessingDepth::Bits10),
+            16 => Some(ProcessingDepth::Bits16),
+            _ => None,
+        }
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        match self {
+            ProcessingDepth::Bits10 => PixelFormat::Yuv422p10,
+            ProcessingDepth::Bits16 => PixelFormat::Yuv422p16,
+        }
+    }
+
+    /// Refuse a chain with a filter that would drop back to 8 bits
+    pub fn check_filters(&self, filters: &[String]) -> Result<()> {
+        for name in filter_names(filters) {
+            if let Some((_, instead)) = EIGHT_BIT_FILTERS.iter().find(|(f, _)| *f == name) {
+                anyhow::bail!(
+                    "The {} filter only runs at 8 bits and would undo {}-bit processing; \
+                     {} or leave out --bit-depth",
+                    name,
+                    self.format().bit_depth(),
+                    instead
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Upconversion at the start of the chain
+    pub fn input_filters(&self) -> Vec<String> {
+        vec![format!("format={}", self.format().name())]
+    }
+
+    /// Output pixel format for the encoder: an explicit pix_fmt wins, x265
+    /// and ProRes keep 10-bit 4:2:2, FFV1 keeps the processing depth, the
+    /// other codecs get their usual 8-bit format
+    pub fn output_format(&self, encoder: &VideoEncoder) -> PixelFormat {
+        if let Some(pix_fmt) = encoder.pix_fmt {
+            return pix_fmt;
+        }
+        match encoder.codec {
+            VideoCodec::X265 | VideoCodec::ProRes => PixelFormat::Yuv422p10,
+            VideoCodec::Ffv1 => self.format(),
+            VideoCodec::Dnxhr => PixelFormat::Yuv422p,
+            VideoCodec::X264 | VideoCodec::SvtAv1 | VideoCodec::Aom | VideoCodec::Vp9 => {
+                PixelFormat::Yuv420p
+            }
+        }
+    }
+
+    /// Conversion to the output format at the end of the chain, dithered
+    /// (error diffusion) when the output has fewer bits, so the smooth
+    /// gradients left by denoising do not band
+    pub fn output_filters(&self, output: PixelFormat) -> Vec<String> {
+        let mut filters = Vec::new();
+        if output.bit_depth() < self.format().bit_depth() {
+            filters.push("scale=sws_dither=ed".to_string());
+        }
+        filters.push(format!("format={}", output.name()));
+        filters
+    }
+
+    /// The encoder with its pixel format set to the output format
+    pub fn output_encoder(&self, encoder: &VideoEncoder) -> VideoEncoder {
+        VideoEncoder {
+            pix_fmt: Some(self.output_format(encoder)),
+            ..encoder.clone()
+        }
+    }
+}
@@ -0,0 +1,353 @@
+#![allow(dead_code)]
+
+use super::encoder::VideoEncoder;
+use super::framerate::FrameRate;
+use crate::ffmpeg;
+use crate::trim::{format_timestamp, Trim};
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Dropout detection thresholds (signalstats values are fractions of pixels/lines)
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropoutOptions {
+    pub tout_threshold: f32,   // Temporal outliers: white streaks, snow
+    pub vrep_threshold: f32,   // Vertically repeated lines: TBC line drops
+    pub brng_threshold: f32,   // Out of broadcast range: glitched frames
+    pub repair_threshold: f32, // TOUT above which a frame is replaced
+    pub merge_gap: u64,        // Suspect frames this close join one range
+    pub trim: Trim,            // Part of the input to scan; frames keep source numbers
+}
+
+impl Default for DropoutOptions {
+    fn default() -> Self {
+        Self {
+            tout_threshold: 0.005,
+            vrep_threshold: 0.1,
+            brng_threshold: 0.05,
+            repair_threshold: 0.02,
+            merge_gap: 2,
+            trim: Trim::default(),
+        }
+    }
+}
+
+/// signalstats dropout values of one frame
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DropoutFrame {
+    pub frame: u64,
+    pub time: f64,
+    pub tout: f32,
+    pub vrep: f32,
+    pub brng: f32,
+}
+
+/// Consecutive suspect frames
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropoutRange {
+    pub start_frame: u64,
+    pub end_frame: u64, // Inclusive
+    pub start: f64,
+    pub end: f64,
+    pub peak_tout: f32,
+    pub severe: bool, // At least one frame above the repair threshold
+}
+
+/// Result of the dropout analysis pass
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropoutReport {
+    pub total_frames: u64,
+    pub suspect: Vec<DropoutFrame>,
+    pub ranges: Vec<DropoutRange>,
+    pub severe_frames: Vec<u64>, // Frames to replace when repairing
+}
+
+impl DropoutReport {
+    /// Human readable report listing suspect time ranges and frames
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(
+            text,
+            "Dropout report: {} suspect of {} frames in {} ranges, {} to repair",
+            self.suspect.len(),
+            self.total_frames,
+            self.ranges.len(),
+            self.severe_frames.len()
+        );
+        for range in &self.ranges {
+            let _ = writeln!(
+                text,
+                "{} - {}  frames {}-{}  peak TOUT {:.4}{}",
+                format_timestamp(range.start),
+                format_timestamp(range.end),
+                range.start_frame,
+                range.end_frame,
+                range.peak_tout,
+                if range.severe { "  [severe]" } else { "" }
+            );
+        }
+        text
+    }
+}
+
+/// How badly damaged frames are replaced
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RepairMode {
+    Neighbour,   // Repeat the previous good frame
+    Interpolate, // Motion-interpolate from the surrounding good frames (see repair_filters)
+}
+
+impl RepairMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "neighbour" | "neighbor" | "repeat" => Some(RepairMode::Neighbour),
+            "interpolate" => Some(RepairMode::Interpolate),
+            _ => None,
+        }
+    }
+}
+
+/// Frames to replace and how
+#[derive(Debug, Clone, PartialEq)]
+pub struct DropoutRepair {
+    pub frames: Vec<u64>, // Source frame numbers, sorted
+    pub mode: RepairMode,
+    pub frame_rate: FrameRate, // Source rate
+}
+
+impl DropoutRepair {
+    /// Renumber the frames for an input that is seeked to `start_secs`,
+    /// dropping those before it
+    pub fn after_seek(mut self, start_secs: f64) -> Self {
+        let offset = (start_secs * self.frame_rate.as_f64()).round() as u64;
+        self.frames = self
+            .frames
+            .into_iter()
+            .filter(|&n| n >= offset)
+            .map(|n| n - offset)
+            .collect();
+        self
+    }
+}
+
+/// Move frames measured on an input seeked to `start_secs` back to their
+/// source frame numbers and times
+pub fn before_seek(frames: &mut [DropoutFrame], start_secs: f64, frame_rate: FrameRate) {
+    let offset = (start_secs * frame_rate.as_f64()).round() as u64;
+    for frame in frames {
+        frame.frame += offset;
+        frame.time += start_secs;
+    }
+}
+
+/// Parse `metadata=print` output of signalstats (stat=tout+vrep+brng)
+pub fn parse_dropout_stats(log: &str) -> Vec<DropoutFrame> {
+    let mut frames = Vec::new();
+    let mut current: Option<DropoutFrame> = None;
+
+    for line in log.lines() {
+        if let Some(rest) = line.split("pts_time:").nth(1) {
+            if let Some(frame) = current.take() {
+                frames.push(frame);
+            }
+            current = Some(DropoutFrame {
+                frame: line
+                    .split("frame:")
+                    .nth(1)
+                    .and_then(|f| f.split_whitespace().next())
+                    .and_then(|f| f.parse().ok())
+                    .unwrap_or(frames.len() as u64),
+                time: rest
+                    .split_whitespace()
+                    .next()
+                    .and_then(|t| t.parse().ok())
+                    .unwrap_or(0.0),
+                ..Default::default()
+            });
+        } else if let (Some(frame), Some(rest)) =
+            (current.as_mut(), line.split("lavfi.signalstats.").nth(1))
+        {
+            let Some((key, value)) = rest.split_once('=') else {
+                continue;
+            };
+            let Ok(value) = value.trim().parse::<f32>() else {
+                continue;
+            };
+            match key {
+                "TOUT" => frame.tout = value,
+                "VREP" => frame.vrep = value,
+                "BRNG" => frame.brng = value,
+                _ => {}
+            }
+        }
+    }
+    if let Some(frame) = current {
+        frames.push(frame);
+    }
+
+    frames
+}
+
+/// Flag suspect frames and group them into ranges
+pub fn build_report(frames: &[DropoutFrame], opts: &DropoutOptions) -> DropoutReport {
+    let suspect: Vec<DropoutFrame> = frames
+        .iter()
+        .filter(|f| {
+            f.tout > opts.tout_threshold
+                || f.vrep > opts.vrep_threshold
+                || f.brng > opts.brng_threshold
+        })
+        .cloned()
+        .collect();
+
+    let mut ranges: Vec<DropoutRange> = Vec::new();
+    for f in &suspect {
+        let severe = f.tout > opts.repair_threshold;
+        match ranges.last_mut() {
+            Some(range) if f.frame <= range.end_frame + opts.merge_gap + 1 => {
+                range.end_frame = f.frame;
+                range.end = f.time;
+                range.peak_tout = range.peak_tout.max(f.tout);
+                range.severe |= severe;
+            }
+            _ => ranges.push(DropoutRange {
+                start_frame: f.frame,
+                end_frame: f.frame,
+                start: f.time,
+                end: f.time,
+                peak_tout: f.tout,
+                severe,
+            }),
+        }
+    }
+
+    DropoutReport {
+        total_frames: frames.len() as u64,
+        severe_frames: suspect
+            .iter()
+            .filter(|f| f.tout > opts.repair_threshold)
+            .map(|f| f.frame)
+            .collect(),
+        suspect,
+        ranges,
+    }
+}
+
+/// Build the filters that drop the damaged frames and fill the gaps.
+/// Frame numbers refer to the source, so these must run first in the chain.
+/// That is before deinterlacing: on interlaced sources minterpolate works
+/// on whole woven frames, so a filled frame can comb where there is fast
+/// motion. Neighbour mode repeats a real frame and has no such artefacts.
+pub fn repair_filters(repair: &DropoutRepair) -> Vec<String> {
+    if repair.frames.is_empty() {
+        return Vec::new();
+    }
+
+    // Collapse runs of consecutive frames into between() terms
+    let mut runs: Vec<(u64, u64)> = Vec::new();
+    for &n in &repair.frames {
+        match runs.last_mut() {
+            Some(run) if n == run.1 + 1 => run.1 = n,
+            _ => runs.push((n, n)),
+        }
+    }
+    let terms: Vec<String> = runs
+        .iter()
+        .map(|&(a, b)| {
+            if a == b {
+                format!("eq(n\\,{})", a)
+            } else {
+                format!("between(n\\,{}\\,{})", a, b)
+            }
+        })
+        .collect();
+
+    let fill = match repair.mode {
+        RepairMode::Neighbour => format!("fps={}", repair.frame_rate),
+        RepairMode::Interpolate => format!(
+            "minterpolate=fps={}:mi_mode=mci:mc_mode=aobmc",
+            repair.frame_rate
+        ),
+    };
+
+    vec![format!("select='not({})'", terms.join("+")), fill]
+}
+
+/// Scan every frame of the input with signalstats and report dropouts
+pub fn analyze_dropouts(input: &Path, opts: &DropoutOptions) -> Result<DropoutReport> {
+    ffmpeg::check_ffmpeg()?;
+
+    let trim_args = opts.trim.input_args();
+    let mut args: Vec<&str> = vec!["-hide_banner"];
+    args.extend(trim_args.iter().map(|s| s.as_str()));
+    args.extend([
+        "-i",
+        input.to_str().unwrap(),
+        "-vf",
+        "signalstats=stat=tout+vrep+brng,metadata=mode=print",
+        "-an",
+        "-f",
+        "null",
+        "-",
+    ]);
+
+    let log = ffmpeg::execute_ffmpeg_with_log(&args)?;
+    let mut frames = parse_dropout_stats(&log);
+    if frames.is_empty() {
+        anyhow::bail!("signalstats produced no statistics for {}", input.display());
+    }
+    if let Some(start) = opts.trim.start {
+        let frame_rate = ffmpeg::probe_video_stream(input)?
+            .frame_rate
+            .with_context(|| format!("Unknown frame rate for {}", input.display()))?;
+        before_seek(&mut frames, start, frame_rate);
+    }
+
+    Ok(build_report(&frames, opts))
+}
+
+/// Plan the repair of the report's severe frames at the input's frame rate
+pub fn plan_repair(
+    input: &Path,
+    report: &DropoutReport,
+    mode: RepairMode,
+) -> Result<DropoutRepair> {
+    let stream = ffmpeg::probe_video_stream(input)?;
+    let frame_rate = stream
+        .frame_rate
+        .with_context(|| format!("Unknown frame rate for {}", input.display()))?;
+
+    Ok(DropoutRepair {
+        frames: report.severe_frames.clone(),
+        mode,
+        frame_rate,
+    })
+}
+
+/// Replace the damaged frames only, leaving the rest of the picture
+/// untouched, and encode the `trim` range with `encoder` (e.g.
+/// `VideoEncoder::archival()`)
+pub fn repair_dropouts(
+    input: &Path,
+    output: &Path,
+    repair: &DropoutRepair,
+    encoder: &VideoEncoder,
+    trim: &Trim,
+) -> Result<()> {
+    ffmpeg::check_ffmpeg()?;
+
+    let repair = repair.clone().after_seek(trim.start.unwrap_or(0.0));
+    let vf = repair_filters(&repair).join(",");
+    let encoder_args = encoder.args();
+    let trim_args = trim.input_args();
+    let mut args: Vec<&str> = trim_args.iter().map(|s| s.as_str()).collect();
+    args.extend(["-i", input.to_str().unwrap()]);
+    if !vf.is_empty() {
+        args.extend(["-vf", vf.as_str()]);
+    }
+    args.extend(encoder_args.iter().map(|s| s.as_str()));
+    args.extend(["-c:a", "copy", "-y", output.to_str().unwrap()]);
+
+    ffmpeg::execute_ffmpeg(&args)?;
+    Ok(())
+}
@@ -0,0 +1,277 @@
+#![allow(dead_code)]
+
+use serde::{Deserialize, Serialize};
+
+/// Video codec / encoder family
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    X264,
+    X265,
+    SvtAv1,
+    Aom, // libaom AV1, slow but efficient
+    Vp9,
+    Ffv1,   // Lossless archival
+    ProRes, // Intra editing codec
+    Dnxhr,  // Intra editing codec
+}
+
+impl VideoCodec {
+    /// FFmpeg encoder name
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            VideoCodec::X264 => "libx264",
+            VideoCodec::X265 => "libx265",
+            VideoCodec::SvtAv1 => "libsvtav1",
+            VideoCodec::Aom => "libaom-av1",
+            VideoCodec::Vp9 => "libvpx-vp9",
+            VideoCodec::Ffv1 => "ffv1",
+            VideoCodec::ProRes => "prores_ks",
+            VideoCodec::Dnxhr => "dnxhd",
+        }
+    }
+
+    /// Parse a codec name as given on the command line
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "x264" | "h264" | "libx264" => Some(VideoCodec::X264),
+            "x265" | "h265" | "hevc" | "libx265" => Some(VideoCodec::X265),
+            "svt-av1" | "svtav1" | "libsvtav1" | "av1" => Some(VideoCodec::SvtAv1),
+            "aom" | "libaom" | "libaom-av1" => Some(VideoCodec::Aom),
+            "vp9" | "libvpx-vp9" => Some(VideoCodec::Vp9),
+            "ffv1" => Some(VideoCodec::Ffv1),
+            "prores" | "prores_ks" => Some(VideoCodec::ProRes),
+            "dnxhr" | "dnxhd" => Some(VideoCodec::Dnxhr),
+            _ => None,
+        }
+    }
+
+    /// Intra-only codecs that ignore CRF/bitrate and GOP settings
+    pub fn is_intra(&self) -> bool {
+        matches!(
+            self,
+            VideoCodec::Ffv1 | VideoCodec::ProRes | VideoCodec::Dnxhr
+        )
+    }
+}
+
+/// Rate control for inter-frame codecs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateControl {
+    Crf(u32),        // Constant quality
+    Bitrate(String), // Average bitrate, e.g. "8M"
+}
+
+/// Output pixel format: bit depth and chroma subsampling
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PixelFormat {
+    Yuv420p,
+    Yuv420p10,
+    Yuv422p,
+    Yuv422p10,
+    Yuv422p16, // FFV1 only
+}
+
+impl PixelFormat {
+    /// FFmpeg pix_fmt name
+    pub fn name(&self) -> &'static str {
+        match self {
+            PixelFormat::Yuv420p => "yuv420p",
+            PixelFormat::Yuv420p10 => "yuv420p10le",
+            PixelFormat::Yuv422p => "yuv422p",
+            PixelFormat::Yuv422p10 => "yuv422p10le",
+            PixelFormat::Yuv422p16 => "yuv422p16le",
+        }
+    }
+
+    pub fn bit_depth(&self) -> u32 {
+        match self {
+            PixelFormat::Yuv420p | PixelFormat::Yuv422p => 8,
+            PixelFormat::Yuv420p10 | PixelFormat::Yuv422p10 => 10,
+            PixelFormat::Yuv422p16 => 16,
+        }
+    }
+
+    /// Parse an FFmpeg pix_fmt name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "yuv420p" => Some(PixelFormat::Yuv420p),
+            "yuv420p10" | "yuv420p10le" => Some(PixelFormat::Yuv420p10),
+            "yuv422p" => Some(PixelFormat::Yuv422p),
+            "yuv422p10" | "yuv422p10le" => Some(PixelFormat::Yuv422p10),
+            "yuv422p16" | "yuv422p16le" => Some(PixelFormat::Yuv422p16),
+            _ => None,
+        }
+    }
+}
+
+/// Encoder settings for the enhancement outputs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "EncoderSpec")]
+pub struct VideoEncoder {
+    pub codec: VideoCodec,
+    pub rate: RateControl,
+    pub preset: Option<String>, // x264/x265 name, SVT-AV1/libaom speed, VP9 deadline
+    pub tune: Option<String>,
+    pub pix_fmt: Option<PixelFormat>, // None = encoder default
+    pub gop: Option<u32>,             // Keyframe interval in frames
+    pub closed_gop: bool,
+    pub profile: Option<String>, // e.g. high10, main10, hq, dnxhr_hq
+    pub level: Option<String>,   // e.g. 4.1; FFV1 version (1 or 3)
+}
+
+/// Encoder settings as read from a preset; missing fields take the codec's
+/// defaults, not x264's
+#[derive(Deserialize)]
+struct EncoderSpec {
+    codec: Option<VideoCodec>,
+    rate: Option<RateControl>,
+    preset: Option<String>,
+    tune: Option<String>,
+    pix_fmt: Option<PixelFormat>,
+    gop: Option<u32>,
+    closed_gop: Option<bool>,
+    profile: Option<String>,
+    level: Option<String>,
+}
+
+impl From<EncoderSpec> for VideoEncoder {
+    fn from(spec: EncoderSpec) -> Self {
+        let base = VideoEncoder::new(spec.codec.unwrap_or(VideoCodec::X264));
+        VideoEncoder {
+            codec: base.codec,
+            rate: spec.rate.unwrap_or(base.rate),
+            preset: spec.preset.or(base.preset),
+            tune: spec.tune.or(base.tune),
+            pix_fmt: spec.pix_fmt.or(base.pix_fmt),
+            gop: spec.gop.or(base.gop),
+            closed_gop: spec.closed_gop.unwrap_or(base.closed_gop),
+            profile: spec.profile.or(base.profile),
+            level: spec.level.or(base.level),
+        }
+    }
+}
+
+impl Default for VideoEncoder {
+    fn default() -> Self {
+        Self::new(VideoCodec::X264)
+    }
+}
+
+impl VideoEncoder {
+    /// Encoder with sensible defaults for the codec
+    pub fn new(codec: VideoCodec) -> Self {
+        let (crf, preset, pix_fmt, profile, level) = match codec {
+            VideoCodec::X264 => (18, Some("medium"), None, None, None),
+            VideoCodec::X265 => (20, Some("medium"), None, None, None),
+            VideoCodec::SvtAv1 => (30, Some("6"), None, None, None),
+            VideoCodec::Aom => (30, Some("4"), None, None, None),
+            VideoCodec::Vp9 => (31, Some("good"), None, None, None),
+            VideoCodec::Ffv1 => (0, None, None, None, Some("3")),
+            VideoCodec::ProRes => (0, None, Some(PixelFormat::Yuv422p10), Some("hq"), None),
+            VideoCodec::Dnxhr => (0, None, Some(PixelFormat::Yuv422p), Some("dnxhr_hq"), None),
+        };
+
+        Self {
+            codec,
+            rate: RateControl::Crf(crf),
+            preset: preset.map(String::from),
+            tune: None,
+            pix_fmt,
+            gop: None,
+            closed_gop: false,
+            profile: profile.map(String::from),
+            level: level.map(String::from),
+        }
+    }
+
+    /// libx264 CRF 18 with the slow preset, for archival rescues
+    pub fn archival() -> Self {
+        Self {
+            preset: Some("slow".to_string()),
+            ..Self::new(VideoCodec::X264)
+        }
+    }
+
+    /// FFmpeg output arguments selecting and configuring the encoder
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec!["-c:v".to_string(), self.codec.encoder().to_string()];
+        let mut push = |k: &str, v: &str| {
+            args.push(k.to_string());
+            args.push(v.to_string());
+        };
+
+        if !self.codec.is_intra() {
+            match self.rate {
+                RateControl::Crf(crf) => {
+                    push("-crf", &crf.to_string());
+                    // libaom and libvpx only run constant quality without a bitrate cap
+                    if matches!(self.codec, VideoCodec::Aom | VideoCodec::Vp9) {
+                        push("-b:v", "0");
+                    }
+                }
+                RateControl::Bitrate(ref bitrate) => push("-b:v", bitrate),
+            }
+        }
+
+        if let Some(ref preset) = self.preset {
+            match self.codec {
+                VideoCodec::X264 | VideoCodec::X265 | VideoCodec::SvtAv1 => push("-preset", preset),
+                VideoCodec::Aom => push("-cpu-used", preset),
+                VideoCodec::Vp9 => push("-deadline", preset),
+                _ => {}
+            }
+        }
+
+        if let Some(ref tune) = self.tune {
+            match self.codec {
+                VideoCodec::X264 | VideoCodec::X265 | VideoCodec::Aom => push("-tune", tune),
+                VideoCodec::Vp9 => push("-tune-content", tune),
+                _ => {}
+            }
+        }
+
+        if let Some(pix_fmt) = self.pix_fmt {
+            push("-pix_fmt", pix_fmt.name());
+        }
+
+        // FFV1 archival: intra-only with per-slice CRCs
+        if self.codec == VideoCodec::Ffv1 {
+            push("-g", "1");
+            push("-slices", "16");
+            push("-slicecrc", "1");
+        } else if let Some(gop) = self.gop.filter(|_| !self.codec.is_intra()) {
+            push("-g", &gop.to_string());
+        }
+
+        let mut x265_params = Vec::new();
+        if self.closed_gop && !self.codec.is_intra() {
+            match self.codec {
+                VideoCodec::X265 => x265_params.push("open-gop=0".to_string()),
+                VideoCodec::SvtAv1 => push("-svtav1-params", "irefresh-type=2"),
+                _ => push("-flags", "+cgop"),
+            }
+        }
+
+        if let Some(ref profile) = self.profile {
+            push("-profile:v", profile);
+        }
+
+        if let Some(ref level) = self.level {
+            match self.codec {
+                VideoCodec::X265 => x265_params.push(format!("level-idc={}", level)),
+                VideoCodec::Ffv1 => push("-level", level),
+                VideoCodec::ProRes | VideoCodec::Dnxhr => {}
+                _ => push("-level:v", level),
+            }
+        }
+
+        if !x265_params.is_empty() {
+            push("-x265-params", &x265_params.join(":"));
+        }
+
+        args
+    }
+}
@@ -0,0 +1,129 @@
+#![allow(dead_code)]
+
+use super::framerate::FrameRate;
+
+/// How the film got onto the file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilmSource {
+    Telecine, // Projected/transferred to interlaced video with pulldown
+    Scan,     // Frame-by-frame scan, one file frame per film frame
+}
+
+impl FilmSource {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "telecine" | "transfer" | "video" => Some(FilmSource::Telecine),
+            "scan" | "scanned" => Some(FilmSource::Scan),
+            _ => None,
+        }
+    }
+}
+
+/// Dust and scratch reduction strength
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DustRemoval {
+    Off,
+    Light,  // Spatial: removes isolated specks
+    Strong, // Spatial plus a temporal median: dust on single frames, softens fast motion
+}
+
+impl DustRemoval {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "off" | "none" => Some(DustRemoval::Off),
+            "light" => Some(DustRemoval::Light),
+            "strong" => Some(DustRemoval::Strong),
+            _ => None,
+        }
+    }
+
+    pub fn filters(&self) -> Vec<String> {
+        match self {
+            DustRemoval::Off => Vec::new(),
+            DustRemoval::Light => vec!["removegrain=m0=2:m1=2:m2=2".to_string()],
+            DustRemoval::Strong => vec![
+                "removegrain=m0=17:m1=17:m2=17".to_string(),
+                "tmedian=radius=1".to_string(),
+            ],
+        }
+    }
+}
+
+/// Film frame rate as it runs in a transfer: NTSC video runs 1000/1001 slow,
+/// so 18 fps film comes out at 17.982 fps
+pub fn film_rate(speed: FrameRate, source: Option<FrameRate>) -> FrameRate {
+    match source {
+        Some(rate) if rate.den == 1001 && speed.den == 1 => {
+            FrameRate::new(speed.num * 1000, 1001).reduced()
+        }
+        _ => speed,
+    }
+}
+
+/// Whether film at `rate` was transferred one frame per video frame by
+/// running it at `stream` (the same rate, or PAL's 4% speed-up of 24 fps)
+fn runs_fast_on(rate: FrameRate, stream: FrameRate) -> bool {
+    let film = rate.num as u64 * stream.den as u64;
+    let video = stream.num as u64 * rate.den as u64;
+    film == video || film * 25 == video * 24
+}
+
+/// Recover one output frame per film frame at `speed` (16, 18 or 24 fps)
+pub fn cadence_filters(
+    source: FilmSource,
+    speed: FrameRate,
+    stream_rate: Option<FrameRate>,
+) -> Vec<String> {
+    match source {
+        FilmSource::Telecine => {
+            let rate = film_rate(speed, stream_rate);
+            let mut filters = vec![
+                "fieldmatch=order=auto:combmatch=full".to_string(),
+                // Frames fieldmatch could not match are still combed
+                "yadif=deint=interlaced".to_string(),
+            ];
+            if rate == FrameRate::FILM_NTSC && stream_rate == Some(FrameRate::NTSC) {
+                // 3:2 pulldown: one repeated frame in every five
+                filters.push("decimate".to_string());
+            } else if stream_rate.is_some_and(|stream| runs_fast_on(rate, stream)) {
+                // 2:2 pulldown: the film ran fast (24 fps at 25) and every
+                // video frame is a film frame; keep them all at the video
+                // rate, so the audio stays in sync
+            } else {
+                // Irregular pulldown (16/18 fps): drop the repeats, then pick
+                // frames on the film's time grid
+                filters.push("mpdecimate".to_string());
+                filters.push(format!("fps={}", rate));
+            }
+            filters
+        }
+        FilmSource::Scan => vec![
+            // Scans are often flagged at a video rate; play them at film speed
+            format!("setpts=N*{}/{}/TB", speed.den, speed.num),
+            format!("fps={}", speed),
+        ],
+    }
+}
+
+/// Cleanup after cadence recovery: dust, flicker, gate weave, then a light
+/// denoise that removes chroma noise but keeps the luma grain
+pub fn cleanup_filters(
+    dust: DustRemoval,
+    deflicker: bool,
+    stabilize: bool,
+    denoise: bool,
+) -> Vec<String> {
+    let mut filters = dust.filters();
+    if deflicker {
+        // Exposure varies frame to frame with hand-cranked and old projector shutters
+        filters.push("deflicker=size=5:mode=pm".to_string());
+    }
+    if stabilize {
+        // Gate weave is a small, smooth drift; search a small range
+        filters.push("deshake=rx=16:ry=16:edge=mirror".to_string());
+    }
+    if denoise {
+        filters.push("hqdn3d=1:3:2:4.5".to_string());
+    }
+    filters
+}
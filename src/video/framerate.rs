@@ -0,0 +1,191 @@
+#![allow(dead_code)]
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Exact (rational) frame rate, e.g. 30000/1001 for NTSC
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct FrameRate {
+    pub num: u32,
+    pub den: u32,
+}
+
+impl FrameRate {
+    pub const FILM: FrameRate = FrameRate::new(24, 1);
+    pub const FILM_NTSC: FrameRate = FrameRate::new(24000, 1001);
+    pub const PAL: FrameRate = FrameRate::new(25, 1);
+    pub const NTSC: FrameRate = FrameRate::new(30000, 1001);
+    pub const PAL_FIELDS: FrameRate = FrameRate::new(50, 1);
+    pub const NTSC_FIELDS: FrameRate = FrameRate::new(60000, 1001);
+
+    pub const fn new(num: u32, den: u32) -> Self {
+        Self { num, den }
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    /// Rate from a decimal value, snapping to the NTSC x/1001 rates
+    /// (29.97 is 30000/1001, not 2997/100)
+    pub fn from_f64(fps: f64) -> Option<Self> {
+        if !fps.is_finite() || fps <= 0.0 || fps >= 1000.0 {
+            return None;
+        }
+        for rate in [24, 30, 48, 60, 120] {
+            let ntsc = rate as f64 * 1000.0 / 1001.0;
+            if (fps - ntsc).abs() < 0.01 {
+                return Some(Self::new(rate * 1000, 1001));
+            }
+        }
+        if (fps - fps.round()).abs() < 0.001 {
+            return Some(Self::new(fps.round() as u32, 1));
+        }
+        Some(Self::new((fps * 1000.0).round() as u32, 1000).reduced())
+    }
+
+    /// Parse "30000/1001", "29.97", "25" or a standard name (ntsc, pal, film)
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim().to_lowercase();
+        match s.as_str() {
+            "ntsc" => return Some(Self::NTSC),
+            "pal" => return Some(Self::PAL),
+            "film" => return Some(Self::FILM),
+            "ntsc-film" => return Some(Self::FILM_NTSC),
+            _ => {}
+        }
+        if let Some((num, den)) = s.split_once(['/', ':']) {
+            let (num, den) = (num.trim().parse().ok()?, den.trim().parse().ok()?);
+            return (num > 0 && den > 0).then(|| Self::new(num, den).reduced());
+        }
+        Self::from_f64(s.parse().ok()?)
+    }
+
+    pub fn reduced(self) -> Self {
+        let (mut a, mut b) = (self.num, self.den);
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+        Self::new(self.num / a, self.den / a)
+    }
+}
+
+impl fmt::Display for FrameRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.den == 1 {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
+
+impl From<FrameRate> for String {
+    fn from(rate: FrameRate) -> Self {
+        rate.to_string()
+    }
+}
+
+impl TryFrom<String> for FrameRate {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        FrameRate::parse(&s).ok_or_else(|| format!("invalid frame rate '{}'", s))
+    }
+}
+
+/// How frames are created or removed when the duration is kept
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameRateMode {
+    DropDuplicate, // Nearest frame, fast, can judder
+    Blend,         // Cross-fade neighbouring frames
+    Interpolate,   // Motion-compensated (minterpolate), slow
+}
+
+impl FrameRateMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "drop" | "duplicate" => Some(FrameRateMode::DropDuplicate),
+            "blend" => Some(FrameRateMode::Blend),
+            "interpolate" | "mci" => Some(FrameRateMode::Interpolate),
+            _ => None,
+        }
+    }
+}
+
+/// Frame rate conversion stage
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateConversion {
+    /// Resample to the target rate, duration and audio are unchanged
+    Resample {
+        target: FrameRate,
+        mode: FrameRateMode,
+    },
+    /// Keep every frame and play them at the target rate; audio tempo follows
+    /// with its pitch preserved (PAL 4% speedup / NTSC slowdown)
+    SpeedChange {
+        source: FrameRate,
+        target: FrameRate,
+    },
+}
+
+impl RateConversion {
+    /// Film-speed standards conversion: PAL 25 fps to NTSC film 23.976 fps
+    pub const PAL_TO_NTSC: RateConversion = RateConversion::SpeedChange {
+        source: FrameRate::PAL,
+        target: FrameRate::FILM_NTSC,
+    };
+    /// Film-speed standards conversion: NTSC film 23.976 fps to PAL 25 fps
+    pub const NTSC_TO_PAL: RateConversion = RateConversion::SpeedChange {
+        source: FrameRate::FILM_NTSC,
+        target: FrameRate::PAL,
+    };
+
+    /// Output frame rate
+    pub fn target(&self) -> FrameRate {
+        match self {
+            RateConversion::Resample { target, .. } => *target,
+            RateConversion::SpeedChange { target, .. } => *target,
+        }
+    }
+
+    /// True if the audio has to be re-timed as well
+    pub fn changes_speed(&self) -> bool {
+        matches!(self, RateConversion::SpeedChange { .. })
+    }
+
+    pub fn video_filters(&self) -> Vec<String> {
+        match self {
+            RateConversion::Resample { target, mode } => vec![match mode {
+                FrameRateMode::DropDuplicate => format!("fps={}", target),
+                FrameRateMode::Blend => format!("framerate=fps={}", target),
+                FrameRateMode::Interpolate => format!(
+                    "minterpolate=fps={}:mi_mode=mci:mc_mode=aobmc:me_mode=bidir:vsbmc=1",
+                    target
+                ),
+            }],
+            RateConversion::SpeedChange { source, target } => vec![
+                // New timestamps = old * source / target
+                format!(
+                    "setpts=PTS*{}/{}",
+                    source.num as u64 * target.den as u64,
+                    source.den as u64 * target.num as u64
+                ),
+                format!("fps={}", target),
+            ],
+        }
+    }
+
+    /// atempo keeps the pitch while matching the new playback speed
+    pub fn audio_filters(&self) -> Vec<String> {
+        match self {
+            RateConversion::Resample { .. } => Vec::new(),
+            RateConversion::SpeedChange { source, target } => {
+                vec![format!("atempo={:.6}", target.as_f64() / source.as_f64())]
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use crate::ffmpeg;
+use crate::trim::Trim;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Number of frames sampled by the idet analysis pass
+pub const IDET_SAMPLE_FRAMES: u32 = 1000;
+
+/// Points of the input the sampled frames are spread over
+pub const IDET_SAMPLE_POINTS: usize = 5;
+
+/// Scan type of a video source, as classified by idet
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScanType {
+    Progressive,
+    Tff,      // Interlaced, top field first
+    Bff,      // Interlaced, bottom field first
+    Telecine, // 3:2 pulldown film, needs inverse telecine
+}
+
+impl ScanType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "progressive" => Some(ScanType::Progressive),
+            "tff" => Some(ScanType::Tff),
+            "bff" => Some(ScanType::Bff),
+            "telecine" => Some(ScanType::Telecine),
+            _ => None,
+        }
+    }
+}
+
+/// Deinterlacing algorithm
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeinterlaceMethod {
+    Bwdif,                      // Bob Weaver, good default
+    Yadif,                      // Fast, classic
+    W3fdif,                     // Weston 3-field
+    Estdif,                     // Edge slope tracing
+    Nnedi { weights: PathBuf }, // Neural network, needs nnedi3_weights.bin
+}
+
+/// Output rate of the deinterlacer
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldRate {
+    Field, // One frame per field (double rate, smoothest motion)
+    Frame, // One frame per frame (keeps the original frame rate)
+}
+
+impl FieldRate {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "field" => Some(FieldRate::Field),
+            "frame" => Some(FieldRate::Frame),
+            _ => None,
+        }
+    }
+}
+
+/// Build the deinterlace filter for a method, output rate and parity
+/// (`auto`, `tff` or `bff`)
+pub fn deinterlace_filter(method: &DeinterlaceMethod, rate: FieldRate, parity: &str) -> String {
+    let mode = match rate {
+        FieldRate::Field => "send_field",
+        FieldRate::Frame => "send_frame",
+    };
+
+    match method {
+        DeinterlaceMethod::Bwdif => format!("bwdif=mode={}:parity={}:deint=all", mode, parity),
+        DeinterlaceMethod::Yadif => format!("yadif=mode={}:parity={}:deint=all", mode, parity),
+        DeinterlaceMethod::W3fdif => {
+            let mode = match rate {
+                FieldRate::Field => "field",
+                FieldRate::Frame => "frame",
+            };
+            format!(
+                "w3fdif=filter=complex:mode={}:parity={}:deint=all",
+                mode, parity
+            )
+        }
+        DeinterlaceMethod::Estdif => {
+            let mode = match rate {
+                FieldRate::Field => "field",
+                FieldRate::Frame => "frame",
+            };
+            format!("estdif=mode={}:parity={}:deint=all", mode, parity)
+        }
+        DeinterlaceMethod::Nnedi { weights } => {
+            // nnedi encodes parity and rate in one option: a/t/b, suffixed with f for double rate
+            let field = match (parity, rate) {
+                ("tff", FieldRate::Frame) => "t",
+                ("tff", FieldRate::Field) => "tf",
+                ("bff", FieldRate::Frame) => "b",
+                ("bff", FieldRate::Field) => "bf",
+                (_, FieldRate::Frame) => "a",
+                (_, FieldRate::Field) => "af",
+            };
+            format!(
+                "nnedi=weights='{}':field={}:deint=all",
+                weights.to_string_lossy(),
+                field
+            )
+        }
+    }
+}
+
+/// Frame counters reported by the idet filter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IdetStats {
+    pub tff: u64,
+    pub bff: u64,
+    pub progressive: u64,
+    pub undetermined: u64,
+    pub repeated_top: u64,
+    pub repeated_bottom: u64,
+    pub repeated_neither: u64,
+}
+
+impl IdetStats {
+    /// Add the counters of another run, e.g. another part of the input
+    pub fn add(&mut self, other: &IdetStats) {
+        self.tff += other.tff;
+        self.bff += other.bff;
+        self.progressive += other.progressive;
+        self.undetermined += other.undetermined;
+        self.repeated_top += other.repeated_top;
+        self.repeated_bottom += other.repeated_bottom;
+        self.repeated_neither += other.repeated_neither;
+    }
+
+    /// Classify the source from the multi-frame detection counters
+    pub fn scan_type(&self) -> ScanType {
+        let repeated = self.repeated_top + self.repeated_bottom;
+        let total_repeat = repeated + self.repeated_neither;
+
+        // 3:2 pulldown repeats one field in two of every five frames
+        if total_repeat > 0 && repeated as f64 / total_repeat as f64 > 0.2 {
+            return ScanType::Telecine;
+        }
+
+        let interlaced = self.tff + self.bff;
+        if interlaced <= self.progressive {
+            ScanType::Progressive
+        } else if self.tff >= self.bff {
+            ScanType::Tff
+        } else {
+            ScanType::Bff
+        }
+    }
+}
+
+/// Parse the summary idet prints to stderr at the end of a run
+pub fn parse_idet_output(log: &str) -> Option<IdetStats> {
+    let mut stats = IdetStats::default();
+    let mut found = false;
+
+    for line in log.lines() {
+        if let Some(rest) = line.split("Multi frame detection:").nth(1) {
+            stats.tff = field_value(rest, "TFF:").unwrap_or(0);
+            stats.bff = field_value(rest, "BFF:").unwrap_or(0);
+            stats.progressive = field_value(rest, "Progressive:").unwrap_or(0);
+            stats.undetermined = field_value(rest, "Undetermined:").unwrap_or(0);
+            found = true;
+        } else if let Some(rest) = line.split("Repeated Fields:").nth(1) {
+            stats.repeated_neither = field_value(rest, "Neither:").unwrap_or(0);
+            stats.repeated_top = field_value(rest, "Top:").unwrap_or(0);
+            stats.repeated_bottom = field_value(rest, "Bottom:").unwrap_or(0);
+        }
+    }
+
+    if found {
+        Some(stats)
+    } else {
+        None
+    }
+}
+
+/// Read the number following `key` in an idet summary line
+fn field_value(line: &str, key: &str) -> Option<u64> {
+    line.split(key)
+        .nth(1)?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Run idet over frames sampled across the kept part of the input and
+/// return the summed counters
+pub fn analyze_interlacing(input: &Path, trim: &Trim) -> Result<IdetStats> {
+    ffmpeg::check_ffmpeg()?;
+
+    let stream = ffmpeg::probe_video_stream(input)?;
+    let times = trim.sample_times(stream.duration, IDET_SAMPLE_POINTS);
+    let frames = (IDET_SAMPLE_FRAMES / times.len() as u32).to_string();
+    let mut total: Option<IdetStats> = None;
+    for time in times {
+        let time = format!("{:.3}", time);
+        let args = vec![
+            "-hide_banner",
+            "-ss",
+            &time,
+            "-i",
+            input.to_str().unwrap(),
+            "-vf",
+            "idet",
+            "-frames:v",
+            &frames,
+            "-an",
+            "-f",
+            "null",
+            "-",
+        ];
+
+        let log = ffmpeg::execute_ffmpeg_with_log(&args)?;
+        // A point past the end of a file with a wrong duration gives no summary
+        if let Some(stats) = parse_idet_output(&log) {
+            total.get_or_insert_with(IdetStats::default).add(&stats);
+        }
+    }
+    total.ok_or_else(|| anyhow::anyhow!("idet produced no statistics for {}", input.display()))
+}
+
+/// Detect whether the kept part of the input is progressive, interlaced
+/// (with parity) or telecined
+pub fn detect_scan_type(input: &Path, trim: &Trim) -> Result<ScanType> {
+    Ok(analyze_interlacing(input, trim)?.scan_type())
+}
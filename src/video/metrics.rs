@@ -0,0 +1,332 @@
+#![allow(dead_code)]
+
+use crate::ffmpeg::{self, VideoStreamInfo};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Identical frames have infinite PSNR; they are counted as this many dB
+pub const PSNR_CAP: f64 = 100.0;
+
+/// Objective quality metric
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    Psnr,
+    Ssim,
+    Vmaf, // Needs an ffmpeg build with libvmaf
+}
+
+impl Metric {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Psnr => "psnr",
+            Metric::Ssim => "ssim",
+            Metric::Vmaf => "vmaf",
+        }
+    }
+
+    /// Filter comparing [distorted][reference], writing per-frame values to `log`
+    pub fn filter(&self, log: &Path) -> String {
+        match self {
+            Metric::Psnr => format!("psnr=stats_file={}", ffmpeg::filter_path(log)),
+            Metric::Ssim => format!("ssim=stats_file={}", ffmpeg::filter_path(log)),
+            Metric::Vmaf => format!("libvmaf=log_fmt=json:log_path={}", ffmpeg::filter_path(log)),
+        }
+    }
+}
+
+/// Which metrics to compute
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsOptions {
+    pub psnr: bool,
+    pub ssim: bool,
+    pub vmaf: bool, // Skipped when ffmpeg has no libvmaf
+}
+
+impl Default for MetricsOptions {
+    fn default() -> Self {
+        Self {
+            psnr: true,
+            ssim: true,
+            vmaf: true,
+        }
+    }
+}
+
+/// Metric values of one frame (numbered from 0)
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FrameMetrics {
+    pub frame: u64,
+    pub psnr: Option<f64>,
+    pub ssim: Option<f64>,
+    pub vmaf: Option<f64>,
+}
+
+/// Aggregate of one metric over all frames
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct MetricSummary {
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl MetricSummary {
+    pub fn from_values(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        Some(Self {
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        })
+    }
+}
+
+/// Per-frame and aggregate metrics of a distorted file against its reference
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricsReport {
+    pub frames: Vec<FrameMetrics>,
+    pub psnr: Option<MetricSummary>,
+    pub ssim: Option<MetricSummary>,
+    pub vmaf: Option<MetricSummary>,
+}
+
+impl MetricsReport {
+    /// Merge per-frame values (0-based frame, value) of each metric
+    pub fn from_frames(psnr: &[(u64, f64)], ssim: &[(u64, f64)], vmaf: &[(u64, f64)]) -> Self {
+        let count = [psnr, ssim, vmaf]
+            .iter()
+            .flat_map(|values| values.iter().map(|&(n, _)| n + 1))
+            .max()
+            .unwrap_or(0);
+        let mut frames: Vec<FrameMetrics> = (0..count)
+            .map(|frame| FrameMetrics {
+                frame,
+                ..Default::default()
+            })
+            .collect();
+        for &(n, v) in psnr {
+            frames[n as usize].psnr = Some(v);
+        }
+        for &(n, v) in ssim {
+            frames[n as usize].ssim = Some(v);
+        }
+        for &(n, v) in vmaf {
+            frames[n as usize].vmaf = Some(v);
+        }
+
+        let summary = |values: &[(u64, f64)]| {
+            MetricSummary::from_values(&values.iter().map(|&(_, v)| v).collect::<Vec<_>>())
+        };
+        Self {
+            frames,
+            psnr: summary(psnr),
+            ssim: summary(ssim),
+            vmaf: summary(vmaf),
+        }
+    }
+
+    /// Summary with mean/min/max of each metric
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        let _ = writeln!(text, "Quality metrics over {} frames", self.frames.len());
+        for (name, summary, unit) in [
+            ("PSNR", self.psnr, " dB"),
+            ("SSIM", self.ssim, ""),
+            ("VMAF", self.vmaf, ""),
+        ] {
+            if let Some(s) = summary {
+                let _ = writeln!(
+                    text,
+                    "{:<5} mean {:.4}{unit}  min {:.4}{unit}  max {:.4}{unit}",
+                    name, s.mean, s.min, s.max
+                );
+            }
+        }
+        text
+    }
+
+    /// One row per frame; metrics that were not computed are left empty
+    pub fn to_csv(&self) -> String {
+        let cell = |v: Option<f64>| v.map(|v| format!("{:.6}", v)).unwrap_or_default();
+        let mut csv = String::from("frame,psnr,ssim,vmaf\n");
+        for f in &self.frames {
+            let _ = writeln!(
+                csv,
+                "{},{},{},{}",
+                f.frame,
+                cell(f.psnr),
+                cell(f.ssim),
+                cell(f.vmaf)
+            );
+        }
+        csv
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Value of `key:` in a stats file line ("n:1 mse_avg:0.5 psnr_avg:51.2 ...")
+fn stats_value(line: &str, key: &str) -> Option<f64> {
+    let value = line
+        .split_whitespace()
+        .find_map(|field| field.strip_prefix(key)?.strip_prefix(':'))?;
+    value.parse().ok()
+}
+
+/// Parse a psnr stats_file; frames are numbered from 0, infinite values capped
+pub fn parse_psnr_stats(text: &str) -> Vec<(u64, f64)> {
+    text.lines()
+        .filter_map(|line| {
+            let n = stats_value(line, "n")? as u64;
+            let psnr = stats_value(line, "psnr_avg")?;
+            Some((n.saturating_sub(1), psnr.min(PSNR_CAP)))
+        })
+        .collect()
+}
+
+/// Parse an ssim stats_file ("n:1 Y:0.99 U:0.98 V:0.98 All:0.987 (18.9)")
+pub fn parse_ssim_stats(text: &str) -> Vec<(u64, f64)> {
+    text.lines()
+        .filter_map(|line| {
+            let n = stats_value(line, "n")? as u64;
+            Some((n.saturating_sub(1), stats_value(line, "All")?))
+        })
+        .collect()
+}
+
+/// Parse a libvmaf JSON log
+pub fn parse_vmaf_log(text: &str) -> Result<Vec<(u64, f64)>> {
+    let log: serde_json::Value = serde_json::from_str(text).context("Invalid libvmaf log")?;
+    Ok(log
+        .get("frames")
+        .and_then(|f| f.as_array())
+        .map(|frames| {
+            frames
+                .iter()
+                .filter_map(|f| {
+                    let n = f.get("frameNum")?.as_u64()?;
+                    let vmaf = f.get("metrics")?.get("vmaf")?.as_f64()?;
+                    Some((n, vmaf))
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// filter_complex aligning the distorted input 0 with the reference input 1
+/// (same frame rate, size and pixel format, timestamps from zero) and
+/// running each metric on its own copy of the pair
+pub fn metrics_filtergraph(reference: &VideoStreamInfo, metrics: &[(Metric, &Path)]) -> String {
+    let rate = reference
+        .frame_rate
+        .map(|r| format!("fps={},", r))
+        .unwrap_or_default();
+    let n = metrics.len();
+
+    let mut graph = vec![
+        format!(
+            "[0:v]setpts=PTS-STARTPTS,{}scale={}:{}:flags=bicubic,format=yuv420p10le,setsar=1[dist]",
+            rate, reference.width, reference.height
+        ),
+        format!("[1:v]setpts=PTS-STARTPTS,{}format=yuv420p10le,setsar=1[ref]", rate),
+    ];
+    if n > 1 {
+        let outs = |p: &str| (0..n).map(|i| format!("[{}{}]", p, i)).collect::<String>();
+        graph.push(format!("[dist]split={}{}", n, outs("d")));
+        graph.push(format!("[ref]split={}{}", n, outs("r")));
+    }
+    for (i, (metric, log)) in metrics.iter().enumerate() {
+        let (d, r) = if n > 1 {
+            (format!("d{}", i), format!("r{}", i))
+        } else {
+            ("dist".to_string(), "ref".to_string())
+        };
+        graph.push(format!("[{}][{}]{}[m{}]", d, r, metric.filter(log), i));
+    }
+    graph.join(";")
+}
+
+/// Compare a distorted file (e.g. a re-encode) with its reference
+pub fn compare_metrics(
+    distorted: &Path,
+    reference: &Path,
+    opts: &MetricsOptions,
+) -> Result<MetricsReport> {
+    ffmpeg::check_ffmpeg()?;
+
+    let stream = ffmpeg::probe_video_stream(reference)?;
+    let mut metrics = Vec::new();
+    if opts.psnr {
+        metrics.push(Metric::Psnr);
+    }
+    if opts.ssim {
+        metrics.push(Metric::Ssim);
+    }
+    if opts.vmaf && ffmpeg::has_filter("libvmaf")? {
+        metrics.push(Metric::Vmaf);
+    }
+    if metrics.is_empty() {
+        anyhow::bail!("No metrics to compute");
+    }
+
+    let dir = std::env::temp_dir().join(format!("fluxara-metrics-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+
+    let result = (|| {
+        let logs: Vec<_> = metrics
+            .iter()
+            .map(|m| dir.join(format!("{}.log", m.name())))
+            .collect();
+        let pairs: Vec<(Metric, &Path)> = metrics
+            .iter()
+            .zip(&logs)
+            .map(|(&m, log)| (m, log.as_path()))
+            .collect();
+        let graph = metrics_filtergraph(&stream, &pairs);
+
+        let mut args = vec![
+            "-hide_banner",
+            "-i",
+            distorted.to_str().unwrap(),
+            "-i",
+            reference.to_str().unwrap(),
+            "-filter_complex",
+            &graph,
+        ];
+        let labels: Vec<String> = (0..metrics.len()).map(|i| format!("[m{}]", i)).collect();
+        for label in &labels {
+            args.extend(["-map", label.as_str(), "-f", "null", "-"]);
+        }
+        ffmpeg::execute_ffmpeg(&args)?;
+
+        let read = |m: Metric| -> Result<String> {
+            let log = dir.join(format!("{}.log", m.name()));
+            std::fs::read_to_string(&log)
+                .with_context(|| format!("Missing {} log {}", m.name(), log.display()))
+        };
+        let psnr = if opts.psnr {
+            parse_psnr_stats(&read(Metric::Psnr)?)
+        } else {
+            Vec::new()
+        };
+        let ssim = if opts.ssim {
+            parse_ssim_stats(&read(Metric::Ssim)?)
+        } else {
+            Vec::new()
+        };
+        let vmaf = if metrics.contains(&Metric::Vmaf) {
+            parse_vmaf_log(&read(Metric::Vmaf)?)?
+        } else {
+            Vec::new()
+        };
+        Ok(MetricsReport::from_frames(&psnr, &ssim, &vmaf))
+    })();
+
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
@@ -1,21 +1,95 @@
+pub mod aspect;
+pub mod autocolor;
+pub mod captions;
+pub mod chroma;
+pub mod color;
+pub mod compare;
+pub mod crop;
+pub mod depth;
+pub mod dropout;
+pub mod encoder;
+pub mod film;
+pub mod framerate;
+pub mod interlace;
+pub mod metrics;
+pub mod overscan;
+pub mod restore;
+pub mod scale;
+pub mod scenes;
+pub mod sharpen;
+pub mod thumbnails;
+pub mod timecode;
+
+use crate::audio::AudioEnhanceOptions;
 use crate::ffmpeg;
+use crate::trim::Trim;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
-/// Video enhancement options
-#[derive(Debug, Clone)]
+pub use aspect::{AspectHandling, AspectOptions, DisplayAspect, ParConvention, PixelAspect};
+pub use autocolor::{AutoColorMode, AutoColorOptions, ColorCorrection};
+pub use captions::SubtitleFormat;
+pub use chroma::ChromaCorrection;
+pub use color::ColorConversion;
+pub use compare::{CompareLayout, CompareOptions};
+pub use crop::CropRect;
+pub use depth::ProcessingDepth;
+pub use dropout::{DropoutOptions, DropoutRepair, RepairMode};
+pub use encoder::{PixelFormat, RateControl, VideoCodec, VideoEncoder};
+pub use film::{DustRemoval, FilmSource};
+pub use framerate::{FrameRate, FrameRateMode, RateConversion};
+pub use interlace::{DeinterlaceMethod, FieldRate, ScanType};
+pub use metrics::MetricsOptions;
+pub use overscan::{OverscanMask, OverscanMode};
+pub use restore::RestoreOptions;
+pub use scale::{ScaleFit, ScaleOptions, ScaleTarget, Scaler};
+pub use scenes::{ChapterFormat, SceneOptions};
+pub use sharpen::{SharpenMethod, SharpenOptions};
+pub use thumbnails::{ImageFormat, ThumbnailOptions};
+pub use timecode::Timecode;
+
+/// Video enhancement options; the skipped fields are measured or planned per
+/// file and are not saved in presets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct VideoEnhanceOptions {
+    #[serde(skip)]
+    pub dropout_repair: Option<DropoutRepair>, // Damaged source frames to replace
     pub deinterlace: bool,
+    pub scan_type: Option<ScanType>, // None = unknown, deinterlace with parity=auto
+    pub deinterlace_method: DeinterlaceMethod,
+    pub field_rate: FieldRate,
+    #[serde(skip)]
+    pub crop: Option<CropRect>, // Black borders / letterbox to remove
+    pub overscan: Option<OverscanMask>, // Head-switching noise / ragged edges
+    pub chroma: Option<ChromaCorrection>, // Chroma delay and chroma-only denoise
     pub stabilize: bool,
     pub denoise: DenoiseType,
-    pub sharpen: bool,
+    #[serde(with = "crate::preset::or_off")]
+    pub sharpen: Option<SharpenOptions>, // None = no sharpening
     pub color_adjust: bool,
+    pub frame_rate: Option<RateConversion>, // Resample or speed change to a new rate
+    #[serde(skip)]
+    pub auto_color: Option<ColorCorrection>, // Measured levels/white balance, replaces eq
+    #[serde(skip)]
+    pub color_conversion: Option<ColorConversion>, // Matrix/range conversion and output tags
+    #[serde(skip)]
+    pub pixel_aspect: Option<PixelAspect>, // SD anamorphic SAR: flag it or resample to square
+    pub scale: Option<ScaleOptions>,        // Aspect-preserving scale to a target raster
     pub scale_width: Option<u32>,
     pub scale_height: Option<u32>,
     pub aspect_ratio: Option<String>,
+    pub encoder: VideoEncoder,
+    pub depth: Option<ProcessingDepth>, // Filter at 10/16-bit 4:2:2 instead of the decoded format
+    #[serde(skip)]
+    pub trim: Trim,   // Part of the input to process
+    #[serde(skip)]
+    pub timecode: Option<Timecode>, // Start timecode of the output (e.g. from VITC)
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DenoiseType {
     None,
     Hqdn3d,  // Fast, conservative
@@ -25,14 +99,30 @@ pub enum DenoiseType {
 impl Default for VideoEnhanceOptions {
     fn default() -> Self {
         Self {
+            dropout_repair: None,
             deinterlace: true,
+            scan_type: None,
+            deinterlace_method: DeinterlaceMethod::Bwdif,
+            field_rate: FieldRate::Field,
+            crop: None,
+            overscan: None,
+            chroma: None,
             stabilize: false, // Can be slow, off by default
             denoise: DenoiseType::Hqdn3d,
-            sharpen: true,
+            sharpen: Some(SharpenOptions::default()),
             color_adjust: true,
+            frame_rate: None,
+            auto_color: None,
+            color_conversion: None,
+            pixel_aspect: None,
+            scale: None,
             scale_width: None,
             scale_height: None,
             aspect_ratio: None,
+            encoder: VideoEncoder::default(),
+            depth: None,
+            trim: Trim::default(),
+            timecode: None,
         }
     }
 }
@@ -41,9 +131,52 @@ impl Default for VideoEnhanceOptions {
 pub fn build_video_filters(opts: &VideoEnhanceOptions) -> Vec<String> {
     let mut filters = Vec::new();
 
-    // Deinterlace using bwdif (Bob Weaver Deinterlacing Filter)
+    // Upconvert before any filter touches the picture
+    if let Some(depth) = opts.depth {
+        filters.extend(depth.input_filters());
+    }
+
+    // Replace tape dropout frames first, while frame numbers still match the source
+    if let Some(ref repair) = opts.dropout_repair {
+        filters.extend(dropout::repair_filters(repair));
+    }
+
+    // Deinterlace (bwdif by default), or inverse telecine for 3:2 pulldown
+    // film when the scan type is known
     if opts.deinterlace {
-        filters.push("bwdif=mode=send_field:parity=auto:deint=all".to_string());
+        let parity = match opts.scan_type {
+            Some(ScanType::Tff) => Some("tff"),
+            Some(ScanType::Bff) => Some("bff"),
+            Some(ScanType::Telecine) | Some(ScanType::Progressive) => None,
+            None => Some("auto"),
+        };
+
+        if let Some(parity) = parity {
+            filters.push(interlace::deinterlace_filter(
+                &opts.deinterlace_method,
+                opts.field_rate,
+                parity,
+            ));
+        } else if opts.scan_type == Some(ScanType::Telecine) {
+            filters.push("fieldmatch=order=auto:combmatch=full".to_string());
+            filters.push("decimate".to_string());
+        }
+    }
+
+    // Remove black borders / letterboxing
+    if let Some(ref crop) = opts.crop {
+        filters.push(crop.filter());
+    }
+
+    // Mask head-switching noise and overscan before stabilization and
+    // denoising so they do not track or smear the noise band
+    if let Some(ref mask) = opts.overscan {
+        filters.extend(overscan::overscan_filters(mask));
+    }
+
+    // Move chroma back onto luma and denoise chroma separately
+    if let Some(ref chroma) = opts.chroma {
+        filters.extend(chroma::chroma_filters(chroma));
     }
 
     // Video stabilization
@@ -54,32 +187,68 @@ pub fn build_video_filters(opts: &VideoEnhanceOptions) -> Vec<String> {
     }
 
     // Denoise
-    match opts.denoise {
-        DenoiseType::Hqdn3d => {
-            // Conservative denoising: luma, chroma, luma_temporal, chroma_temporal
-            filters.push("hqdn3d=4:3:6:4.5".to_string());
+    let denoise = match opts.denoise {
+        // Conservative denoising: luma, chroma, luma_temporal, chroma_temporal
+        DenoiseType::Hqdn3d => Some("hqdn3d=4:3:6:4.5"),
+        // High-quality but slower
+        DenoiseType::Nlmeans => Some("nlmeans=s=3.0"),
+        DenoiseType::None => None,
+    };
+    // Noise-protected sharpening needs the frames before and after the
+    // denoise, so it runs here instead of after the rate conversion
+    match opts.sharpen {
+        Some(ref sharpen) if sharpen.protect_noise => {
+            filters.push(sharpen::noise_protected_filters(denoise, sharpen));
+        }
+        _ => filters.extend(denoise.map(String::from)),
+    }
+
+    // Frame rate conversion on the cleaned frames (motion estimation copes
+    // badly with noise)
+    if let Some(ref conversion) = opts.frame_rate {
+        filters.extend(conversion.video_filters());
+    }
+
+    // Sharpen (unsharp or contrast-adaptive)
+    if let Some(ref sharpen) = opts.sharpen {
+        if !sharpen.protect_noise {
+            filters.extend(sharpen.filters());
         }
-        DenoiseType::Nlmeans => {
-            // High-quality but slower
-            filters.push("nlmeans=s=3.0".to_string());
+    }
+
+    // Color adjustments: measured levels/white balance, or a fixed EQ
+    if let Some(ref correction) = opts.auto_color {
+        // Measured on the source timeline, applied after the rate conversion
+        let correction = correction
+            .clone()
+            .after_rate_conversion(opts.frame_rate.as_ref());
+        filters.extend(correction.filters());
+    } else if opts.color_adjust {
+        // Slight brightness and saturation boost typical for VHS; eq only
+        // runs at 8 bits, so high bit depth chains lift luma and saturate
+        // with filters that keep the depth
+        if opts.depth.is_some() {
+            filters.push("lutyuv=y='clip(val+0.02*maxval\\,minval\\,maxval)'".to_string());
+            filters.push("colorcorrect=saturation=1.1".to_string());
+        } else {
+            filters.push("eq=brightness=0.02:saturation=1.1".to_string());
         }
-        DenoiseType::None => {}
     }
 
-    // Sharpen using unsharp
-    if opts.sharpen {
-        // Conservative sharpening: luma_amount, chroma_amount
-        filters.push("unsharp=5:5:0.8:3:3:0.4".to_string());
+    // Convert colour matrix/primaries/range (e.g. BT.601 -> BT.709 for HD)
+    if let Some(filter) = opts.color_conversion.and_then(|c| c.filter_at(opts.depth)) {
+        filters.push(filter);
     }
 
-    // Color adjustments (EQ filter)
-    if opts.color_adjust {
-        // Slight brightness and saturation boost typical for VHS
-        filters.push("eq=brightness=0.02:saturation=1.1".to_string());
+    // Flag anamorphic pixels, or resample them to square pixels
+    if let Some(ref pixel_aspect) = opts.pixel_aspect {
+        filters.extend(pixel_aspect.filters());
     }
 
-    // Scale if specified
-    if let (Some(w), Some(h)) = (opts.scale_width, opts.scale_height) {
+    // Scale to a target raster keeping the aspect ratio, or to an exact size
+    if let Some(ref scale) = opts.scale {
+        filters.extend(scale.filters());
+    } else if let (Some(w), Some(h)) = (opts.scale_width, opts.scale_height) {
         filters.push(format!("scale={}:{}:flags=lanczos", w, h));
     }
 
@@ -88,88 +257,367 @@ pub fn build_video_filters(opts: &VideoEnhanceOptions) -> Vec<String> {
         filters.push(format!("setdar={}", dar));
     }
 
+    // Back down to what the encoder takes, dithered if it has fewer bits
+    if let Some(depth) = opts.depth {
+        filters.extend(depth.output_filters(depth.output_format(&opts.encoder)));
+    }
+
     filters
 }
 
+/// Plan the colour conversion from the probed source when the options do not set one
+fn with_color_conversion(input: &Path, opts: &VideoEnhanceOptions) -> Result<VideoEnhanceOptions> {
+    let mut opts = opts.clone();
+    if opts.color_conversion.is_none() {
+        let stream = ffmpeg::probe_video_stream(input)?;
+        let output_height = opts
+            .scale
+            .map(|s| s.target.height)
+            .or(opts.scale_height)
+            .unwrap_or(stream.height);
+        opts.color_conversion = Some(color::plan_conversion(&stream, output_height));
+    }
+    Ok(opts)
+}
+
+/// Refuse a high bit depth chain that FFmpeg would partly run at 8 bits
+fn check_depth(depth: Option<ProcessingDepth>, filters: &[String]) -> Result<()> {
+    match depth {
+        Some(depth) => depth.check_filters(filters),
+        None => Ok(()),
+    }
+}
+
+/// Encoder with the pixel format the filter chain ends in
+fn output_encoder(opts: &VideoEnhanceOptions) -> VideoEncoder {
+    match opts.depth {
+        Some(depth) => depth.output_encoder(&opts.encoder),
+        None => opts.encoder.clone(),
+    }
+}
+
 /// Enhance video in a file
 pub fn enhance_video(input: &Path, output: &Path, opts: &VideoEnhanceOptions) -> Result<()> {
     ffmpeg::check_ffmpeg()?;
 
-    let filters = build_video_filters(opts);
+    let opts = with_color_conversion(input, opts)?;
+    let filters = build_video_filters(&opts);
+    check_depth(opts.depth, &filters)?;
     let filter_str = filters.join(",");
+    let color_args = opts
+        .color_conversion
+        .map(|c| c.output_args())
+        .unwrap_or_default();
 
-    let args = vec![
-        "-i",
-        input.to_str().unwrap(),
-        "-vf",
-        &filter_str,
-        "-c:v",
-        "libx264",
-        "-preset",
-        "medium",
-        "-crf",
-        "18",
-        "-c:a",
-        "copy", // Copy audio stream
-        "-y",
-        output.to_str().unwrap(),
-    ];
+    let encoder_args = output_encoder(&opts).args();
+    let af = opts
+        .frame_rate
+        .map(|c| c.audio_filters().join(","))
+        .unwrap_or_default();
+
+    let trim_args = opts.trim.input_args();
+
+    let mut args: Vec<&str> = trim_args.iter().map(|s| s.as_str()).collect();
+    args.extend(["-i", input.to_str().unwrap(), "-vf", &filter_str]);
+    args.extend(encoder_args.iter().map(|s| s.as_str()));
+    if af.is_empty() {
+        args.extend(["-c:a", "copy"]); // Copy audio stream
+    } else {
+        // Speed change: re-time the audio to stay in sync
+        args.extend(["-af", &af, "-c:a", "aac", "-b:a", "192k"]);
+    }
+    args.extend(color_args.iter().map(|s| s.as_str()));
+    let timecode = opts.timecode.map(|tc| tc.to_string());
+    if let Some(ref timecode) = timecode {
+        args.extend(["-timecode", timecode.as_str()]);
+    }
+    args.push("-y");
+    args.push(output.to_str().unwrap());
 
     ffmpeg::execute_ffmpeg(&args)?;
     Ok(())
 }
 
+/// VHS rescue options
+#[derive(Debug, Clone)]
+pub struct VhsRescueOptions {
+    pub video: VideoEnhanceOptions, // Base filters and encoder, e.g. from a preset
+    pub audio: Option<AudioEnhanceOptions>, // None = copy the audio
+    pub notch_freq: Option<u32>,
+    pub scan_type: Option<ScanType>, // None = video.scan_type, else detect with idet
+    pub overscan: Option<OverscanMask>, // None = video.overscan, else detect from frame statistics
+    pub overscan_mode: OverscanMode, // How a detected mask is handled
+    pub repair_dropouts: Option<RepairMode>, // None = leave dropouts untouched
+    pub encoder: Option<VideoEncoder>, // None = video.encoder
+    pub aspect: AspectOptions,       // Applied to SD rasters from the probed size
+    pub frame_rate: Option<RateConversion>, // e.g. PAL/NTSC standards conversion
+    pub scale: Option<ScaleOptions>, // e.g. upscale to 1080p
+    pub depth: Option<ProcessingDepth>, // e.g. 10-bit processing for FFV1/ProRes masters
+    pub trim: Trim,                  // Part of the input to process
+    pub timecode: Option<Timecode>,  // Start timecode of the output (e.g. from VITC)
+}
+
+impl Default for VhsRescueOptions {
+    fn default() -> Self {
+        Self {
+            video: Self::default_video(),
+            audio: Some(Self::default_audio()),
+            notch_freq: None,
+            scan_type: None,
+            overscan: None,
+            overscan_mode: OverscanMode::Pad,
+            repair_dropouts: None,
+            encoder: None,
+            aspect: AspectOptions::default(),
+            frame_rate: None,
+            scale: None,
+            depth: None,
+            trim: Trim::default(),
+            timecode: None,
+        }
+    }
+}
+
+impl VhsRescueOptions {
+    /// Video filters for VHS: stabilized, denoised, sharpened around the
+    /// noise, libx264 CRF 18 preset slow
+    pub fn default_video() -> VideoEnhanceOptions {
+        VideoEnhanceOptions {
+            stabilize: true,
+            // Noisy tape: keep the sharpening out of the noise
+            sharpen: Some(SharpenOptions {
+                protect_noise: true,
+                ..Default::default()
+            }),
+            encoder: VideoEncoder::archival(),
+            ..Default::default()
+        }
+    }
+
+    /// Audio filters for VHS linear tracks
+    pub fn default_audio() -> AudioEnhanceOptions {
+        AudioEnhanceOptions {
+            lowpass_freq: Some(15000), // Remove high-freq noise
+            ..Default::default()
+        }
+    }
+}
+
+/// Audio filters of a VHS rescue: the enhancement chain plus the re-timing a
+/// speed change needs, even when the audio is otherwise copied; empty when
+/// the audio can be copied
+pub fn rescue_audio_filters(
+    rescue: &VhsRescueOptions,
+    frame_rate: Option<&RateConversion>,
+) -> Vec<String> {
+    let mut filters = rescue
+        .audio
+        .as_ref()
+        .map(|audio| {
+            crate::audio::build_audio_filters(&AudioEnhanceOptions {
+                notch_freq: rescue.notch_freq.or(audio.notch_freq),
+                trim: rescue.trim,
+                ..audio.clone()
+            })
+        })
+        .unwrap_or_default();
+    if let Some(conversion) = frame_rate {
+        filters.extend(conversion.audio_filters());
+    }
+    filters
+}
+
 /// VHS rescue preset - combines video and audio enhancement
-pub fn vhs_rescue(input: &Path, output: &Path, notch_freq: Option<u32>) -> Result<()> {
+pub fn vhs_rescue(input: &Path, output: &Path, rescue: &VhsRescueOptions) -> Result<()> {
     ffmpeg::check_ffmpeg()?;
 
-    // Video filters for VHS
-    let video_opts = VideoEnhanceOptions {
-        deinterlace: true,
-        stabilize: true,
-        denoise: DenoiseType::Hqdn3d,
-        sharpen: true,
-        color_adjust: true,
-        scale_width: None,
-        scale_height: None,
-        aspect_ratio: Some("4:3".to_string()), // Typical VHS
+    let scan_type = match rescue.scan_type.or(rescue.video.scan_type) {
+        Some(scan_type) => scan_type,
+        None => interlace::detect_scan_type(input, &rescue.trim)?,
+    };
+    let overscan = match rescue.overscan.as_ref().or(rescue.video.overscan.as_ref()) {
+        Some(mask) => mask.clone(),
+        None => overscan::detect_overscan(input, &rescue.trim, rescue.overscan_mode)?,
     };
+    let dropout_repair = match rescue.repair_dropouts {
+        Some(mode) => {
+            let opts = DropoutOptions {
+                trim: rescue.trim,
+                ..Default::default()
+            };
+            let report = dropout::analyze_dropouts(input, &opts)?;
+            let repair = dropout::plan_repair(input, &report, mode)?;
+            Some(repair.after_seek(rescue.trim.start.unwrap_or(0.0)))
+        }
+        None => None,
+    };
+
+    let stream = ffmpeg::probe_video_stream(input)?;
+    let pixel_aspect = aspect::plan_pixel_aspect(&stream, &rescue.aspect);
 
-    // Audio filters for VHS
-    let audio_opts = crate::audio::AudioEnhanceOptions {
-        denoise: true,
-        normalize: true,
-        highpass_freq: Some(80),
-        lowpass_freq: Some(15000), // Remove high-freq noise
-        notch_freq,
-        compressor: true,
-        gate: true,
-        gate_threshold: -50.0,
+    // Video filters for VHS, with the measured and per-run settings
+    let base = &rescue.video;
+    let video_opts = VideoEnhanceOptions {
+        dropout_repair,
+        scan_type: Some(scan_type),
+        overscan: Some(overscan),
+        frame_rate: rescue.frame_rate.or(base.frame_rate),
+        pixel_aspect,
+        scale: rescue.scale.or(base.scale),
+        encoder: rescue
+            .encoder
+            .clone()
+            .unwrap_or_else(|| base.encoder.clone()),
+        depth: rescue.depth.or(base.depth),
+        trim: rescue.trim,
+        timecode: rescue.timecode,
+        ..base.clone()
     };
+    let video_opts = with_color_conversion(input, &video_opts)?;
+    let color_args = video_opts
+        .color_conversion
+        .map(|c| c.output_args())
+        .unwrap_or_default();
+
+    let filters = build_video_filters(&video_opts);
+    check_depth(video_opts.depth, &filters)?;
+    let vf = filters.join(",");
+    let audio_filters = rescue_audio_filters(rescue, video_opts.frame_rate.as_ref());
+    let af = (!audio_filters.is_empty()).then(|| audio_filters.join(","));
+    let encoder_args = output_encoder(&video_opts).args();
+
+    let trim_args = rescue.trim.input_args();
 
-    let vf = build_video_filters(&video_opts).join(",");
-    let af = crate::audio::build_audio_filters(&audio_opts).join(",");
+    let mut args: Vec<&str> = trim_args.iter().map(|s| s.as_str()).collect();
+    args.extend(["-i", input.to_str().unwrap(), "-vf", &vf]);
+    args.extend(encoder_args.iter().map(|s| s.as_str()));
+    match af {
+        Some(ref af) => args.extend(["-af", af, "-c:a", "aac", "-b:a", "192k"]),
+        None => args.extend(["-c:a", "copy"]),
+    }
+    args.extend(color_args.iter().map(|s| s.as_str()));
+    let timecode = rescue.timecode.map(|tc| tc.to_string());
+    if let Some(ref timecode) = timecode {
+        args.extend(["-timecode", timecode.as_str()]);
+    }
+    args.push("-y");
+    args.push(output.to_str().unwrap());
+
+    ffmpeg::execute_ffmpeg(&args)?;
+    Ok(())
+}
 
-    let args = vec![
+/// Film rescue options
+#[derive(Debug, Clone)]
+pub struct FilmRescueOptions {
+    pub source: FilmSource,
+    pub speed: FrameRate, // Film frame rate: 16, 18 or 24
+    pub dust: DustRemoval,
+    pub deflicker: bool,
+    pub stabilize: bool,                    // Gate weave
+    pub denoise: bool,                      // Grain-preserving
+    pub audio: Option<AudioEnhanceOptions>, // None = copy; most home film is silent
+    pub encoder: Option<VideoEncoder>,      // None = libx264 CRF 18, preset slow, tune grain
+    pub depth: Option<ProcessingDepth>,     // Filter at 10/16-bit 4:2:2
+    pub trim: Trim,                         // Part of the input to process
+}
+
+impl Default for FilmRescueOptions {
+    fn default() -> Self {
+        Self {
+            source: FilmSource::Telecine,
+            speed: FrameRate::new(18, 1),
+            dust: DustRemoval::Light,
+            deflicker: true,
+            stabilize: true,
+            denoise: true,
+            audio: None,
+            encoder: None,
+            depth: None,
+            trim: Trim::default(),
+        }
+    }
+}
+
+impl FilmRescueOptions {
+    /// Projector soundtracks: rumble, hiss and a narrow optical/magnetic band
+    pub fn soundtrack_audio() -> AudioEnhanceOptions {
+        AudioEnhanceOptions {
+            highpass_freq: Some(60),
+            lowpass_freq: Some(10000),
+            compressor: false,
+            gate: false,
+            ..Default::default()
+        }
+    }
+}
+
+/// Film rescue preset for 8mm/Super 8/16mm transfers and scans
+pub fn film_rescue(input: &Path, output: &Path, rescue: &FilmRescueOptions) -> Result<()> {
+    ffmpeg::check_ffmpeg()?;
+
+    let stream = ffmpeg::probe_video_stream(input)?;
+    let conversion = color::plan_conversion(&stream, stream.height);
+
+    let mut encoder = rescue.encoder.clone().unwrap_or_else(|| VideoEncoder {
+        tune: Some("grain".to_string()),
+        ..VideoEncoder::archival()
+    });
+
+    let mut filters = Vec::new();
+    if let Some(depth) = rescue.depth {
+        filters.extend(depth.input_filters());
+    }
+    filters.extend(film::cadence_filters(
+        rescue.source,
+        rescue.speed,
+        stream.frame_rate,
+    ));
+    filters.extend(film::cleanup_filters(
+        rescue.dust,
+        rescue.deflicker,
+        rescue.stabilize,
+        rescue.denoise,
+    ));
+    filters.extend(conversion.filter_at(rescue.depth));
+    if let Some(depth) = rescue.depth {
+        filters.extend(depth.output_filters(depth.output_format(&encoder)));
+        encoder = depth.output_encoder(&encoder);
+    }
+    check_depth(rescue.depth, &filters)?;
+    let vf = filters.join(",");
+    let color_args = conversion.output_args();
+    let encoder_args = encoder.args();
+
+    let af = rescue.audio.as_ref().map(|audio| {
+        let audio_opts = AudioEnhanceOptions {
+            trim: rescue.trim,
+            ..audio.clone()
+        };
+        crate::audio::build_audio_filters(&audio_opts).join(",")
+    });
+
+    let trim_args = rescue.trim.input_args();
+
+    let mut args: Vec<&str> = trim_args.iter().map(|s| s.as_str()).collect();
+    args.extend([
         "-i",
         input.to_str().unwrap(),
+        "-map",
+        "0:v:0",
+        "-map",
+        "0:a?",
         "-vf",
         &vf,
-        "-af",
-        &af,
-        "-c:v",
-        "libx264",
-        "-preset",
-        "slow", // Better quality for archival
-        "-crf",
-        "18",
-        "-c:a",
-        "aac",
-        "-b:a",
-        "192k",
-        "-y",
-        output.to_str().unwrap(),
-    ];
+    ]);
+    args.extend(encoder_args.iter().map(|s| s.as_str()));
+    match af {
+        Some(ref af) => args.extend(["-af", af, "-c:a", "aac", "-b:a", "192k"]),
+        None => args.extend(["-c:a", "copy"]),
+    }
+    args.extend(color_args.iter().map(|s| s.as_str()));
+    args.push("-y");
+    args.push(output.to_str().unwrap());
 
     ffmpeg::execute_ffmpeg(&args)?;
     Ok(())
@@ -0,0 +1,227 @@
+use crate::ffmpeg;
+use crate::trim::Trim;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Number of frames sampled when auto-detecting the overscan mask
+pub const OVERSCAN_SAMPLE_FRAMES: u32 = 20;
+
+/// Points of the input the sampled frames are spread over
+pub const OVERSCAN_SAMPLE_POINTS: usize = 5;
+
+/// How the masked overscan area is handled
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverscanMode {
+    Crop, // Remove the lines, output raster shrinks
+    Pad,  // Replace the lines with black, original raster is kept
+}
+
+impl OverscanMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "crop" => Some(OverscanMode::Crop),
+            "pad" => Some(OverscanMode::Pad),
+            _ => None,
+        }
+    }
+}
+
+/// Lines/columns of head-switching noise and ragged overscan to mask
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OverscanMask {
+    pub top: u32,
+    pub bottom: u32, // Head-switching noise lives here
+    pub left: u32,
+    pub right: u32,
+    pub mode: OverscanMode,
+}
+
+impl Default for OverscanMask {
+    fn default() -> Self {
+        Self {
+            top: 0,
+            bottom: 8, // Typical head-switching band on SD captures
+            left: 8,
+            right: 8,
+            mode: OverscanMode::Pad,
+        }
+    }
+}
+
+impl OverscanMask {
+    /// True if nothing would be masked
+    pub fn is_empty(&self) -> bool {
+        self.top == 0 && self.bottom == 0 && self.left == 0 && self.right == 0
+    }
+}
+
+/// Build the crop (and pad) filters for an overscan mask
+pub fn overscan_filters(mask: &OverscanMask) -> Vec<String> {
+    if mask.is_empty() {
+        return Vec::new();
+    }
+
+    let mut filters = vec![format!(
+        "crop=iw-{}:ih-{}:{}:{}",
+        mask.left + mask.right,
+        mask.top + mask.bottom,
+        mask.left,
+        mask.top
+    )];
+
+    if mask.mode == OverscanMode::Pad {
+        filters.push(format!(
+            "pad=iw+{}:ih+{}:{}:{}:black",
+            mask.left + mask.right,
+            mask.top + mask.bottom,
+            mask.left,
+            mask.top
+        ));
+    }
+
+    filters
+}
+
+/// Estimate the overscan mask from 8-bit grayscale frames stored back to back.
+///
+/// Bottom lines are flagged when their difference to the line above is far
+/// above the picture average (head-switching tears the line sideways); edges
+/// are flagged while they stay much darker than the picture just inside the
+/// search band (blanking and ragged borders), so dark footage is not
+/// mistaken for a border. Counts are rounded up to even values so that chroma
+/// planes and field order stay aligned.
+pub fn detect_from_frames(frames: &[u8], width: usize, height: usize) -> OverscanMask {
+    let frame_size = width * height;
+    let mut mask = OverscanMask {
+        top: 0,
+        bottom: 0,
+        left: 0,
+        right: 0,
+        mode: OverscanMode::Pad,
+    };
+    if width < 16 || height < 16 || frames.len() < frame_size {
+        return mask;
+    }
+
+    let frame_count = frames.len() / frame_size;
+    let mut row_diff = vec![0.0f64; height];
+    let mut row_mean = vec![0.0f64; height];
+    let mut col_mean = vec![0.0f64; width];
+
+    for frame in frames.chunks_exact(frame_size).take(frame_count) {
+        for y in 0..height {
+            let row = &frame[y * width..(y + 1) * width];
+            let sum: u64 = row.iter().map(|&p| p as u64).sum();
+            row_mean[y] += sum as f64 / width as f64;
+            for (x, &p) in row.iter().enumerate() {
+                col_mean[x] += p as f64 / height as f64;
+            }
+            if y > 0 {
+                let above = &frame[(y - 1) * width..y * width];
+                let diff: u64 = row
+                    .iter()
+                    .zip(above)
+                    .map(|(&a, &b)| (a as i32 - b as i32).unsigned_abs() as u64)
+                    .sum();
+                row_diff[y] += diff as f64 / width as f64;
+            }
+        }
+    }
+    for v in row_diff
+        .iter_mut()
+        .chain(row_mean.iter_mut())
+        .chain(col_mean.iter_mut())
+    {
+        *v /= frame_count as f64;
+    }
+
+    let body_diff = median(&row_diff[height / 4..height * 3 / 4]);
+
+    // Search at most 1/16 of the raster from each edge; each edge is
+    // compared with the picture in the band just inside it
+    let max_lines = height / 16;
+    let max_cols = width / 16;
+    let top_ref = median(&row_mean[max_lines..2 * max_lines]);
+    let bottom_ref = median(&row_mean[height - 2 * max_lines..height - max_lines]);
+    let left_ref = median(&col_mean[max_cols..2 * max_cols]);
+    let right_ref = median(&col_mean[width - 2 * max_cols..width - max_cols]);
+
+    let diff_threshold = body_diff * 3.0 + 4.0;
+    mask.bottom = (height - max_lines..height)
+        .find(|&y| row_diff[y] > diff_threshold || is_dark(row_mean[y], bottom_ref))
+        .map(|y| (height - y) as u32)
+        .unwrap_or(0);
+    mask.top = (0..max_lines)
+        .take_while(|&y| is_dark(row_mean[y], top_ref))
+        .count() as u32;
+    mask.left = (0..max_cols)
+        .take_while(|&x| is_dark(col_mean[x], left_ref))
+        .count() as u32;
+    mask.right = (0..max_cols)
+        .take_while(|&x| is_dark(col_mean[width - 1 - x], right_ref))
+        .count() as u32;
+
+    mask.top = round_up_even(mask.top);
+    mask.bottom = round_up_even(mask.bottom);
+    mask.left = round_up_even(mask.left);
+    mask.right = round_up_even(mask.right);
+    mask
+}
+
+/// Much darker than the neighbouring picture: a relative drop, so a dark
+/// scene next to its own edge rows is not a border
+fn is_dark(value: f64, reference: f64) -> bool {
+    reference - value > 16.0_f64.max(reference * 0.3)
+}
+
+fn round_up_even(v: u32) -> u32 {
+    v + (v % 2)
+}
+
+fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    sorted[sorted.len() / 2]
+}
+
+/// Sample frames across the trimmed part of the input and detect
+/// head-switching/overscan lines; the mask is handled with `mode`
+pub fn detect_overscan(input: &Path, trim: &Trim, mode: OverscanMode) -> Result<OverscanMask> {
+    ffmpeg::check_ffmpeg()?;
+
+    let stream = ffmpeg::probe_video_stream(input)?;
+    let times = trim.sample_times(stream.duration, OVERSCAN_SAMPLE_POINTS);
+    let frames = (OVERSCAN_SAMPLE_FRAMES / times.len() as u32).to_string();
+    let mut raw = Vec::new();
+    for time in times {
+        let time = format!("{:.3}", time);
+        let args = vec![
+            "-hide_banner",
+            "-ss",
+            &time,
+            "-i",
+            input.to_str().unwrap(),
+            "-vf",
+            "select='not(mod(n\\,25))',format=gray",
+            "-vsync",
+            "0",
+            "-frames:v",
+            &frames,
+            "-f",
+            "rawvideo",
+            "-",
+        ];
+        raw.extend(ffmpeg::execute_ffmpeg_raw(&args)?);
+    }
+
+    Ok(OverscanMask {
+        mode,
+        ..detect_from_frames(&raw, stream.width as usize, stream.height as usize)
+    })
+}
@@ -0,0 +1,155 @@
+#![allow(dead_code)]
+
+use super::{
+    build_video_filters, check_depth, output_encoder, with_color_conversion, VideoEnhanceOptions,
+};
+use crate::audio::{build_audio_filters, AudioEnhanceOptions};
+use crate::ffmpeg;
+use crate::trim::Trim;
+use anyhow::{bail, Result};
+use std::path::Path;
+
+/// Video, audio and encoder settings for a single-pass restore
+#[derive(Debug, Clone)]
+pub struct RestoreOptions {
+    pub video: Option<VideoEnhanceOptions>, // None = copy the video stream
+    pub audio: Option<AudioEnhanceOptions>, // None = copy the audio stream
+    pub video_stream: usize,                // Input video stream to restore (0:v:N)
+    pub audio_stream: Option<usize>,        // Input audio stream (0:a:N); None = no audio
+    pub audio_bitrate: String,              // AAC bitrate of the restored audio
+    pub trim: Trim,                         // Part of the input to process
+}
+
+impl Default for RestoreOptions {
+    fn default() -> Self {
+        Self {
+            video: Some(VideoEnhanceOptions::default()),
+            audio: Some(AudioEnhanceOptions::default()),
+            video_stream: 0,
+            audio_stream: Some(0),
+            audio_bitrate: "192k".to_string(),
+            trim: Trim::default(),
+        }
+    }
+}
+
+/// Audio filters: the enhancement chain plus the re-timing a speed change
+/// needs; empty when the audio can be copied
+fn audio_filters(opts: &RestoreOptions) -> Vec<String> {
+    let mut filters = opts
+        .audio
+        .as_ref()
+        .map(build_audio_filters)
+        .unwrap_or_default();
+    if let Some(conversion) = opts.video.as_ref().and_then(|v| v.frame_rate) {
+        filters.extend(conversion.audio_filters());
+    }
+    filters
+}
+
+/// filter_complex running the video chain on the chosen video stream into
+/// `[v]` and the audio chain on the chosen audio stream into `[a]`; None
+/// when both streams are copied
+pub fn restore_filtergraph(opts: &RestoreOptions) -> Option<String> {
+    let mut chains = Vec::new();
+    if let Some(ref video) = opts.video {
+        let filters = build_video_filters(video);
+        let vf = if filters.is_empty() {
+            "null".to_string()
+        } else {
+            filters.join(",")
+        };
+        chains.push(format!("[0:v:{}]{}[v]", opts.video_stream, vf));
+    }
+    let af = audio_filters(opts);
+    if let (Some(stream), false) = (opts.audio_stream, af.is_empty()) {
+        chains.push(format!("[0:a:{}]{}[a]", stream, af.join(",")));
+    }
+    (!chains.is_empty()).then(|| chains.join(";"))
+}
+
+/// FFmpeg arguments for the restore; every output stream is mapped
+/// explicitly, other input streams (subtitles, data) are left out
+pub fn restore_args(input: &Path, output: &Path, opts: &RestoreOptions) -> Vec<String> {
+    let mut args = opts.trim.input_args();
+    args.extend(["-i".to_string(), input.display().to_string()]);
+    if let Some(graph) = restore_filtergraph(opts) {
+        args.extend(["-filter_complex".to_string(), graph]);
+    }
+
+    args.push("-map".to_string());
+    match opts.video {
+        Some(ref video) => {
+            args.push("[v]".to_string());
+            args.extend(output_encoder(video).args());
+            args.extend(
+                video
+                    .color_conversion
+                    .map(|c| c.output_args())
+                    .unwrap_or_default(),
+            );
+            if let Some(timecode) = video.timecode {
+                args.extend(["-timecode".to_string(), timecode.to_string()]);
+            }
+        }
+        None => {
+            args.push(format!("0:v:{}", opts.video_stream));
+            args.extend(["-c:v".to_string(), "copy".to_string()]);
+        }
+    }
+
+    match opts.audio_stream {
+        Some(_) if !audio_filters(opts).is_empty() => args.extend([
+            "-map".to_string(),
+            "[a]".to_string(),
+            "-c:a".to_string(),
+            "aac".to_string(),
+            "-b:a".to_string(),
+            opts.audio_bitrate.clone(),
+        ]),
+        Some(stream) => args.extend([
+            "-map".to_string(),
+            format!("0:a:{}?", stream),
+            "-c:a".to_string(),
+            "copy".to_string(),
+        ]),
+        None => args.push("-an".to_string()),
+    }
+
+    args.extend(["-y".to_string(), output.display().to_string()]);
+    args
+}
+
+/// The audio stream to restore from an input with `audio_streams` audio
+/// streams: none for a silent input, so no audio chain is built for it
+pub fn resolve_audio_stream(opts: &RestoreOptions, audio_streams: usize) -> Result<Option<usize>> {
+    match opts.audio_stream {
+        Some(_) if audio_streams == 0 => Ok(None),
+        Some(stream) if stream >= audio_streams => bail!(
+            "Audio stream {} not found, the input has {} audio stream(s)",
+            stream,
+            audio_streams
+        ),
+        stream => Ok(stream),
+    }
+}
+
+/// Restore video and audio in one decode/encode generation
+pub fn restore(input: &Path, output: &Path, opts: &RestoreOptions) -> Result<()> {
+    ffmpeg::check_ffmpeg()?;
+
+    let audio_stream = resolve_audio_stream(opts, ffmpeg::count_streams(input, "audio")?)?;
+    let opts = RestoreOptions {
+        video: match opts.video {
+            Some(ref video) => Some(with_color_conversion(input, video)?),
+            None => None,
+        },
+        audio_stream,
+        ..opts.clone()
+    };
+    if let Some(ref video) = opts.video {
+        check_depth(video.depth, &build_video_filters(video))?;
+    }
+    let args = restore_args(input, output, &opts);
+    ffmpeg::execute_ffmpeg(&args.iter().map(|s| s.as_str()).collect::<Vec<_>>())
+}
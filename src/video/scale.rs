@@ -0,0 +1,114 @@
+#![allow(dead_code)]
+
+use serde::{Deserialize, Serialize};
+
+/// Resampling kernel used when scaling
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scaler {
+    Lanczos,  // Sharpest, slight ringing
+    Spline36, // zscale spline36, sharp with less ringing
+    Bicubic,  // Softest, no ringing
+}
+
+impl Scaler {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "lanczos" => Some(Scaler::Lanczos),
+            "spline36" | "spline" => Some(Scaler::Spline36),
+            "bicubic" => Some(Scaler::Bicubic),
+            _ => None,
+        }
+    }
+}
+
+/// How the picture is fitted into the target raster
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScaleFit {
+    Fit, // Largest size inside the target that keeps the aspect ratio
+    Pad, // As Fit, then pillarbox/letterbox to exactly the target raster
+}
+
+impl ScaleFit {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "fit" => Some(ScaleFit::Fit),
+            "pad" => Some(ScaleFit::Pad),
+            _ => None,
+        }
+    }
+}
+
+/// Target raster, e.g. 1920x1080 for 1080p
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScaleTarget {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ScaleTarget {
+    /// Parse a named target (480p, 576p, 720p, 1080p, 1440p, 2160p/4k) or WxH
+    pub fn parse(s: &str) -> Option<Self> {
+        let (width, height) = match s.trim().to_lowercase().as_str() {
+            "480p" => (854, 480),
+            "576p" => (1024, 576),
+            "720p" => (1280, 720),
+            "1080p" => (1920, 1080),
+            "1440p" => (2560, 1440),
+            "2160p" | "4k" | "uhd" => (3840, 2160),
+            other => {
+                let (w, h) = other.split_once('x')?;
+                (w.trim().parse().ok()?, h.trim().parse().ok()?)
+            }
+        };
+        // Subsampled chroma needs even dimensions
+        (width > 0 && height > 0 && width % 2 == 0 && height % 2 == 0)
+            .then_some(Self { width, height })
+    }
+}
+
+/// Target-based, aspect-preserving scaling
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScaleOptions {
+    pub target: ScaleTarget,
+    pub fit: ScaleFit,
+    pub scaler: Scaler,
+}
+
+impl ScaleOptions {
+    /// Scale (and pad) filters. The display aspect (`dar`, which includes the
+    /// sample aspect ratio) decides whether width or height is limiting, so
+    /// anamorphic sources come out with square pixels at the right shape.
+    /// Sizes are kept even for 4:2:0/4:2:2 chroma; swscale interpolates chroma
+    /// at full resolution with accurate rounding, zscale uses MPEG-2 (left)
+    /// chroma siting.
+    pub fn filters(&self) -> Vec<String> {
+        let (w, h) = (self.target.width, self.target.height);
+        let width = format!("if(gt(dar\\,{w}/{h})\\,{w}\\,trunc({h}*dar/2)*2)");
+        let height = format!("if(gt(dar\\,{w}/{h})\\,trunc({w}/dar/2)*2\\,{h})");
+
+        let scale = match self.scaler {
+            Scaler::Spline36 => format!(
+                "zscale=w='{}':h='{}':filter=spline36:chromal=left",
+                width, height
+            ),
+            Scaler::Lanczos | Scaler::Bicubic => format!(
+                "scale=w='{}':h='{}':flags={}+accurate_rnd+full_chroma_int",
+                width,
+                height,
+                if self.scaler == Scaler::Lanczos {
+                    "lanczos"
+                } else {
+                    "bicubic"
+                }
+            ),
+        };
+
+        let mut filters = vec![scale, "setsar=1".to_string()];
+        if self.fit == ScaleFit::Pad {
+            filters.push(format!("pad={w}:{h}:(ow-iw)/2:(oh-ih)/2:black"));
+        }
+        filters
+    }
+}
@@ -0,0 +1,268 @@
+#![allow(dead_code)]
+
+use super::encoder::VideoEncoder;
+use crate::ffmpeg;
+use crate::trim::{format_timestamp, Trim};
+use anyhow::{Context, Result};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Scene cut detection settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct SceneOptions {
+    pub threshold: f32,  // Scene change score (0-1) above which a frame starts a scene
+    pub min_length: f64, // Shortest scene kept, seconds; earlier cuts are ignored
+    pub trim: Trim,      // Part of the input to scan; scenes keep source times
+}
+
+impl Default for SceneOptions {
+    fn default() -> Self {
+        Self {
+            threshold: 0.3,
+            min_length: 5.0,
+            trim: Trim::default(),
+        }
+    }
+}
+
+/// One scene, in seconds from the start of the file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scene {
+    pub start: f64,
+    pub end: f64,
+}
+
+impl Scene {
+    pub fn duration(&self) -> f64 {
+        self.end - self.start
+    }
+}
+
+/// Chapter list formats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChapterFormat {
+    Text,       // "HH:MM:SS.mmm Scene N", one per line
+    Ogm,        // CHAPTER01=... / CHAPTER01NAME=... (mkvmerge simple chapters)
+    FfMetadata, // FFmpeg metadata file, usable with -map_chapters
+}
+
+impl ChapterFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "text" | "txt" => Some(ChapterFormat::Text),
+            "ogm" | "simple" => Some(ChapterFormat::Ogm),
+            "ffmetadata" | "ffmpeg" => Some(ChapterFormat::FfMetadata),
+            _ => None,
+        }
+    }
+}
+
+/// Parse the timestamps of frames passed by `select='gt(scene,T)'` followed
+/// by `metadata=mode=print`
+pub fn parse_scene_cuts(log: &str) -> Vec<f64> {
+    let mut cuts: Vec<f64> = log
+        .lines()
+        .filter_map(|line| line.split("pts_time:").nth(1))
+        .filter_map(|rest| rest.split_whitespace().next())
+        .filter_map(|t| t.parse().ok())
+        .collect();
+    cuts.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    cuts
+}
+
+/// Turn cut timestamps into scenes covering [0, duration]. A cut closer than
+/// `min_length` to the previous kept cut is ignored, and a too-short last
+/// scene is merged into the one before it.
+pub fn plan_scenes(cuts: &[f64], duration: f64, min_length: f64) -> Result<Vec<Scene>> {
+    if duration <= 0.0 {
+        anyhow::bail!("The duration is unknown, so the last scene has no end");
+    }
+    let mut starts = vec![0.0];
+    for &cut in cuts {
+        if cut > 0.0 && cut < duration && cut - starts[starts.len() - 1] >= min_length {
+            starts.push(cut);
+        }
+    }
+    if starts.len() > 1 && duration - starts[starts.len() - 1] < min_length {
+        starts.pop();
+    }
+
+    let mut scenes: Vec<Scene> = starts
+        .windows(2)
+        .map(|w| Scene {
+            start: w[0],
+            end: w[1],
+        })
+        .collect();
+    scenes.push(Scene {
+        start: starts[starts.len() - 1],
+        end: duration,
+    });
+    Ok(scenes)
+}
+
+/// Find the scenes of the input
+pub fn detect_scenes(input: &Path, opts: &SceneOptions) -> Result<Vec<Scene>> {
+    ffmpeg::check_ffmpeg()?;
+
+    let stream = ffmpeg::probe_video_stream(input)?;
+    let vf = format!(
+        "select='gt(scene\\,{:.3})',metadata=mode=print",
+        opts.threshold
+    );
+    let trim_args = opts.trim.input_args();
+    let mut args: Vec<&str> = vec!["-hide_banner"];
+    args.extend(trim_args.iter().map(|s| s.as_str()));
+    args.extend([
+        "-i",
+        input.to_str().unwrap(),
+        "-vf",
+        &vf,
+        "-an",
+        "-f",
+        "null",
+        "-",
+    ]);
+
+    let log = ffmpeg::execute_ffmpeg_with_log(&args)?;
+    // Raw and unfinished captures may have no duration in the container
+    let duration = match stream.duration {
+        d if d > 0.0 => d,
+        _ => ffmpeg::probe_packet_duration(input, "v:0")?,
+    };
+    // The cuts are timed from the seek point; plan on the trimmed range and
+    // move the scenes back onto the source timeline
+    let start = opts.trim.start.unwrap_or(0.0);
+    let end = opts.trim.end.map_or(duration, |end| end.min(duration));
+    let scenes = plan_scenes(&parse_scene_cuts(&log), end - start, opts.min_length)
+        .with_context(|| format!("Cannot split {} into scenes", input.display()))?;
+    Ok(scenes
+        .into_iter()
+        .map(|scene| Scene {
+            start: scene.start + start,
+            end: scene.end + start,
+        })
+        .collect())
+}
+
+/// Chapter list with one chapter per scene, titled "Scene N"
+pub fn chapter_list(scenes: &[Scene], format: ChapterFormat) -> String {
+    let mut out = String::new();
+    if format == ChapterFormat::FfMetadata {
+        out.push_str(";FFMETADATA1\n");
+    }
+
+    for (i, scene) in scenes.iter().enumerate() {
+        let n = i + 1;
+        let _ = match format {
+            ChapterFormat::Text => {
+                writeln!(out, "{} Scene {}", format_timestamp(scene.start), n)
+            }
+            ChapterFormat::Ogm => writeln!(
+                out,
+                "CHAPTER{:02}={}\nCHAPTER{:02}NAME=Scene {}",
+                n,
+                format_timestamp(scene.start),
+                n,
+                n
+            ),
+            ChapterFormat::FfMetadata => writeln!(
+                out,
+                "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle=Scene {}",
+                (scene.start * 1000.0).round() as u64,
+                (scene.end * 1000.0).round() as u64,
+                n
+            ),
+        };
+    }
+    out
+}
+
+/// Copy the input into an MKV/MP4 output with the scenes as chapters
+pub fn write_chapters(input: &Path, output: &Path, scenes: &[Scene]) -> Result<()> {
+    ffmpeg::check_ffmpeg()?;
+
+    let ext = output
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    if !matches!(ext.as_str(), "mkv" | "mp4" | "m4v" | "mov") {
+        anyhow::bail!("Chapters can only be written to MKV or MP4 outputs");
+    }
+
+    let meta = std::env::temp_dir().join(format!("fluxara-chapters-{}.txt", std::process::id()));
+    std::fs::write(&meta, chapter_list(scenes, ChapterFormat::FfMetadata))?;
+
+    let result = ffmpeg::execute_ffmpeg(&[
+        "-i",
+        input.to_str().unwrap(),
+        "-i",
+        meta.to_str().unwrap(),
+        "-map",
+        "0",
+        "-map_metadata",
+        "0",
+        "-map_chapters",
+        "1",
+        "-c",
+        "copy",
+        "-y",
+        output.to_str().unwrap(),
+    ]);
+
+    let _ = std::fs::remove_file(&meta);
+    result
+}
+
+/// Clip file name for scene `index` (0-based): "<stem>_scene_001.<ext>"
+pub fn scene_file_name(input: &Path, index: usize) -> String {
+    let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("clip");
+    let ext = input.extension().and_then(|e| e.to_str()).unwrap_or("mkv");
+    format!("{}_scene_{:03}.{}", stem, index + 1, ext)
+}
+
+/// Write each scene to its own file in `out_dir`, named after `named_after`
+/// (the original file when `input` is a cut intermediate) but keeping the
+/// container of `input`. With no encoder the streams are copied, so clips
+/// start on the nearest keyframe before each cut.
+pub fn split_scenes(
+    input: &Path,
+    named_after: &Path,
+    out_dir: &Path,
+    scenes: &[Scene],
+    encoder: Option<&VideoEncoder>,
+) -> Result<Vec<PathBuf>> {
+    ffmpeg::check_ffmpeg()?;
+    std::fs::create_dir_all(out_dir)?;
+
+    let mut clips = Vec::new();
+    for (i, scene) in scenes.iter().enumerate() {
+        let ext = input.extension().and_then(|e| e.to_str()).unwrap_or("mkv");
+        let clip = out_dir
+            .join(scene_file_name(named_after, i))
+            .with_extension(ext);
+        let trim = Trim {
+            start: Some(scene.start),
+            end: Some(scene.end),
+        };
+
+        let mut args = trim.input_args();
+        args.extend(["-i", input.to_str().unwrap(), "-map", "0"].map(String::from));
+        match encoder {
+            Some(encoder) => {
+                args.extend(encoder.args());
+                args.extend(["-c:a", "aac", "-b:a", "192k"].map(String::from));
+            }
+            None => {
+                args.extend(["-c", "copy", "-avoid_negative_ts", "make_zero"].map(String::from))
+            }
+        }
+        args.extend(["-y".to_string(), clip.to_str().unwrap().to_string()]);
+
+        ffmpeg::execute_ffmpeg(&args.iter().map(|s| s.as_str()).collect::<Vec<_>>())?;
+        clips.push(clip);
+    }
+
+    Ok(clips)
+}
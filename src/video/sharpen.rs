@@ -0,0 +1,124 @@
+#![allow(dead_code)]
+
+use serde::{Deserialize, Serialize};
+
+/// Sharpening algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SharpenMethod {
+    Unsharp, // Unsharp mask with a configurable radius
+    Cas,     // Contrast-adaptive: sharpens flat detail more than strong edges
+}
+
+impl SharpenMethod {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "unsharp" => Some(SharpenMethod::Unsharp),
+            "cas" => Some(SharpenMethod::Cas),
+            _ => None,
+        }
+    }
+}
+
+/// Sharpening strength and radius, set separately for luma and chroma
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SharpenOptions {
+    pub method: SharpenMethod,
+    #[serde(serialize_with = "crate::preset::serialize_f32")]
+    pub luma_amount: f32, // unsharp 0.0-2.0, cas 0.0-1.0; 0 leaves luma alone
+    pub luma_radius: u32, // Pixels, 1-11 (unsharp only)
+    #[serde(serialize_with = "crate::preset::serialize_f32")]
+    pub chroma_amount: f32, // As luma_amount
+    pub chroma_radius: u32, // Pixels, 1-11 (unsharp only)
+    pub protect_noise: bool, // Leave regions the denoiser found noisy unsharpened
+}
+
+impl Default for SharpenOptions {
+    fn default() -> Self {
+        Self::new(SharpenMethod::Unsharp)
+    }
+}
+
+impl SharpenOptions {
+    /// Defaults for the method; unsharp matches the previous fixed 5x5/3x3 mask
+    pub fn new(method: SharpenMethod) -> Self {
+        let (luma_amount, chroma_amount) = match method {
+            SharpenMethod::Unsharp => (0.8, 0.4),
+            SharpenMethod::Cas => (0.5, 0.0),
+        };
+        Self {
+            method,
+            luma_amount,
+            luma_radius: 2,
+            chroma_amount,
+            chroma_radius: 1,
+            protect_noise: false,
+        }
+    }
+
+    pub fn filters(&self) -> Vec<String> {
+        match self.method {
+            SharpenMethod::Unsharp => {
+                let luma = 2 * self.luma_radius.clamp(1, 11) + 1;
+                let chroma = 2 * self.chroma_radius.clamp(1, 11) + 1;
+                vec![format!(
+                    "unsharp={luma}:{luma}:{}:{chroma}:{chroma}:{}",
+                    self.luma_amount.clamp(0.0, 2.0),
+                    self.chroma_amount.clamp(0.0, 2.0)
+                )]
+            }
+            SharpenMethod::Cas => {
+                let mut filters = Vec::new();
+                if self.luma_amount > 0.0 {
+                    filters.push(format!(
+                        "cas=strength={}:planes=1",
+                        self.luma_amount.min(1.0)
+                    ));
+                }
+                if self.chroma_amount > 0.0 {
+                    filters.push(format!(
+                        "cas=strength={}:planes=6",
+                        self.chroma_amount.min(1.0)
+                    ));
+                }
+                filters
+            }
+        }
+    }
+}
+
+/// Denoise used only to find noisy regions when the options have no denoise
+const NOISE_PROBE: &str = "hqdn3d=4:3:6:4.5";
+
+/// Where the denoiser changed a pixel by more than 2% the region counts as
+/// noisy (fully from 6%); blurred so whole regions are left out, not pixels
+const NOISE_MASK: &str = "blend=all_mode=difference,\
+lut=c0='clip((val/maxval-0.02)*25,0,1)*maxval':c1='clip((val/maxval-0.02)*25,0,1)*maxval':\
+c2='clip((val/maxval-0.02)*25,0,1)*maxval',gblur=sigma=4";
+
+/// Denoise and sharpen in one filtergraph fragment, using the denoised
+/// picture unsharpened where the denoiser removed a lot. Runs where the
+/// denoise would, so the mask lines up with the frames it was made from.
+pub fn noise_protected_filters(denoise: Option<&str>, opts: &SharpenOptions) -> String {
+    let sharpen = opts.filters();
+    if sharpen.is_empty() {
+        return denoise.unwrap_or("null").to_string();
+    }
+    let head = match denoise {
+        Some(denoise) => format!(
+            "split=2[shp_raw][shp_in];[shp_in]{},split=3[shp_dn][shp_plain][shp_ref]",
+            denoise
+        ),
+        None => format!(
+            "split=4[shp_raw][shp_probe][shp_dn][shp_plain];[shp_probe]{}[shp_ref]",
+            NOISE_PROBE
+        ),
+    };
+    format!(
+        "{};[shp_raw][shp_ref]{}[shp_mask];[shp_dn]{}[shp_sharp];[shp_sharp][shp_plain][shp_mask]maskedmerge",
+        head,
+        NOISE_MASK,
+        sharpen.join(",")
+    )
+}
@@ -0,0 +1,174 @@
+#![allow(dead_code)]
+
+use super::scenes::Scene;
+use crate::ffmpeg;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+/// Still image format of contact sheets and thumbnails
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl ImageFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+            "png" => Some(ImageFormat::Png),
+            "webp" => Some(ImageFormat::Webp),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Png => "png",
+            ImageFormat::Webp => "webp",
+        }
+    }
+
+    /// Encoder quality options (PNG is lossless)
+    pub fn args(&self) -> Vec<&'static str> {
+        match self {
+            ImageFormat::Jpeg => vec!["-q:v", "2"],
+            ImageFormat::Png => Vec::new(),
+            ImageFormat::Webp => vec!["-quality", "90"],
+        }
+    }
+}
+
+/// Contact sheet and thumbnail settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThumbnailOptions {
+    pub columns: u32,
+    pub rows: u32,
+    pub width: u32,       // Width of each thumbnail; height follows the display aspect
+    pub timestamps: bool, // Burn the source time into each tile
+    pub format: ImageFormat,
+}
+
+impl Default for ThumbnailOptions {
+    fn default() -> Self {
+        Self {
+            columns: 4,
+            rows: 4,
+            width: 320,
+            timestamps: true,
+            format: ImageFormat::Jpeg,
+        }
+    }
+}
+
+/// Parse a grid size "COLSxROWS", e.g. "4x5"
+pub fn parse_grid(s: &str) -> Option<(u32, u32)> {
+    let s = s.trim().to_lowercase();
+    let (cols, rows) = s.split_once('x')?;
+    let (cols, rows) = (cols.trim().parse().ok()?, rows.trim().parse().ok()?);
+    (cols > 0 && rows > 0).then_some((cols, rows))
+}
+
+/// Scale one thumbnail to the requested width with square pixels
+fn thumbnail_scale(width: u32) -> String {
+    format!("scale={}:trunc(ow/dar/2)*2:flags=lanczos,setsar=1", width)
+}
+
+/// Shortest time between contact sheet tiles, about one frame; a file too
+/// short for the grid gets one tile per frame and the rest stay empty
+const MIN_TILE_INTERVAL: f64 = 0.04;
+
+/// Filters sampling `columns * rows` frames evenly over `duration` seconds
+/// and tiling them into one image. Each tile is taken from the middle of its
+/// interval so the first and last tiles miss leader and run-out.
+pub fn contact_sheet_filters(opts: &ThumbnailOptions, duration: f64) -> Vec<String> {
+    let count = (opts.columns * opts.rows).max(1);
+    let interval = (duration / count as f64).max(MIN_TILE_INTERVAL);
+
+    let mut filters = vec![
+        format!("fps=1/{:.3}:start_time={:.3}", interval, interval / 2.0),
+        thumbnail_scale(opts.width),
+    ];
+    if opts.timestamps {
+        filters.push(format!(
+            "drawtext=text='%{{pts\\:hms}}':x=6:y=h-th-6:fontsize={}:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=3",
+            (opts.width / 16).max(10)
+        ));
+    }
+    filters.push(format!(
+        "tile={}x{}:padding=4:margin=4",
+        opts.columns, opts.rows
+    ));
+    filters
+}
+
+/// Write a contact sheet of the input
+pub fn contact_sheet(input: &Path, output: &Path, opts: &ThumbnailOptions) -> Result<()> {
+    ffmpeg::check_ffmpeg()?;
+
+    let stream = ffmpeg::probe_video_stream(input)?;
+    // Raw and unfinished captures may have no duration in the container
+    let duration = match stream.duration {
+        d if d > 0.0 => d,
+        _ => ffmpeg::probe_packet_duration(input, "v:0")?,
+    };
+    if duration <= 0.0 {
+        anyhow::bail!("Unknown duration for {}", input.display());
+    }
+
+    let vf = contact_sheet_filters(opts, duration).join(",");
+    let mut args = vec!["-i", input.to_str().unwrap(), "-vf", vf.as_str(), "-an"];
+    args.extend(["-frames:v", "1"]);
+    args.extend(opts.format.args());
+    args.extend(["-y", output.to_str().unwrap()]);
+
+    ffmpeg::execute_ffmpeg(&args)?;
+    Ok(())
+}
+
+/// Image file name for scene `index` (0-based): "<stem>_scene_001.jpg"
+pub fn thumbnail_file_name(input: &Path, index: usize, format: ImageFormat) -> String {
+    let stem = input
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("thumb");
+    format!("{}_scene_{:03}.{}", stem, index + 1, format.extension())
+}
+
+/// One thumbnail per scene, taken from the middle of the scene
+pub fn scene_thumbnails(
+    input: &Path,
+    out_dir: &Path,
+    scenes: &[Scene],
+    opts: &ThumbnailOptions,
+) -> Result<Vec<PathBuf>> {
+    ffmpeg::check_ffmpeg()?;
+    std::fs::create_dir_all(out_dir)?;
+
+    let vf = thumbnail_scale(opts.width);
+    let mut images = Vec::new();
+    for (i, scene) in scenes.iter().enumerate() {
+        let image = out_dir.join(thumbnail_file_name(input, i, opts.format));
+        let seek = format!("{:.3}", scene.start + scene.duration() / 2.0);
+
+        let mut args = vec![
+            "-ss",
+            seek.as_str(),
+            "-i",
+            input.to_str().unwrap(),
+            "-vf",
+            vf.as_str(),
+            "-frames:v",
+            "1",
+        ];
+        args.extend(opts.format.args());
+        args.extend(["-y", image.to_str().unwrap()]);
+
+        ffmpeg::execute_ffmpeg(&args)?;
+        images.push(image);
+    }
+
+    Ok(images)
+}
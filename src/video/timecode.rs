@@ -0,0 +1,350 @@
+#![allow(dead_code)]
+
+use super::framerate::FrameRate;
+use crate::ffmpeg;
+use crate::trim::format_timestamp;
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// SMPTE timecode; drop-frame timecodes (29.97/59.94) use ';' before the frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timecode {
+    pub hours: u32,
+    pub minutes: u32,
+    pub seconds: u32,
+    pub frames: u32,
+    pub drop_frame: bool,
+}
+
+impl Timecode {
+    /// Parse "HH:MM:SS:FF" or drop-frame "HH:MM:SS;FF"
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let drop_frame = s.contains(';') || s.contains('.');
+        let parts: Vec<u32> = s
+            .split([':', ';', '.'])
+            .map(|p| p.parse().ok())
+            .collect::<Option<_>>()?;
+        let [hours, minutes, seconds, frames] = parts[..] else {
+            return None;
+        };
+        (minutes < 60 && seconds < 60).then_some(Self {
+            hours,
+            minutes,
+            seconds,
+            frames,
+            drop_frame,
+        })
+    }
+
+    /// Frame numbers dropped each minute (except every tenth) in drop-frame
+    fn dropped(fps: u32) -> u64 {
+        (fps as u64 / 15).max(1)
+    }
+
+    /// Frames since 00:00:00:00 at the nominal rate (30 for 29.97)
+    pub fn to_frames(self, fps: u32) -> u64 {
+        let fps = fps as u64;
+        let minutes = self.hours as u64 * 60 + self.minutes as u64;
+        let mut frames = (minutes * 60 + self.seconds as u64) * fps + self.frames as u64;
+        if self.drop_frame {
+            frames -= Self::dropped(fps as u32) * (minutes - minutes / 10);
+        }
+        frames
+    }
+
+    /// Timecode of a frame count, skipping the dropped frame numbers
+    pub fn from_frames(frames: u64, fps: u32, drop_frame: bool) -> Self {
+        let fps64 = fps as u64;
+        let mut n = frames;
+        if drop_frame {
+            let drop = Self::dropped(fps);
+            let per_10_min = fps64 * 600 - drop * 9;
+            let per_min = fps64 * 60 - drop;
+            let (tens, rest) = (n / per_10_min, n % per_10_min);
+            n += drop * 9 * tens;
+            if rest > drop {
+                n += drop * ((rest - drop) / per_min);
+            }
+        }
+        Self {
+            hours: (n / (fps64 * 3600)) as u32 % 24,
+            minutes: (n / (fps64 * 60) % 60) as u32,
+            seconds: (n / fps64 % 60) as u32,
+            frames: (n % fps64) as u32,
+            drop_frame,
+        }
+    }
+
+    /// This timecode moved by a number of frames (negative goes back)
+    pub fn offset(&self, frames: i64, fps: u32) -> Self {
+        let day = Timecode {
+            hours: 24,
+            minutes: 0,
+            seconds: 0,
+            frames: 0,
+            drop_frame: self.drop_frame,
+        }
+        .to_frames(fps);
+        let n = (self.to_frames(fps) as i64 + frames).rem_euclid(day as i64);
+        Self::from_frames(n as u64, fps, self.drop_frame)
+    }
+
+    /// The same point counted at another timecode rate (after a rate
+    /// conversion); drop-frame is kept only for 30/60 fps targets
+    pub fn at_rate(&self, from: u32, to: u32) -> Self {
+        if from == to {
+            return *self;
+        }
+        let frames = self.to_frames(from) * to as u64 / from.max(1) as u64;
+        Self::from_frames(frames, to, self.drop_frame && to.is_multiple_of(30))
+    }
+}
+
+impl fmt::Display for Timecode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}:{:02}{}{:02}",
+            self.hours,
+            self.minutes,
+            self.seconds,
+            if self.drop_frame { ';' } else { ':' },
+            self.frames
+        )
+    }
+}
+
+/// Nominal timecode rate of a frame rate (30 for 30000/1001)
+pub fn nominal_fps(rate: FrameRate) -> u32 {
+    (rate.as_f64().round() as u32).max(1)
+}
+
+/// VITC read from one frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VitcFrame {
+    pub frame: u64,
+    pub time: f64,
+    pub timecode: Timecode,
+}
+
+/// A frame whose timecode does not follow on from the previous one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimecodeJump {
+    pub frame: u64,
+    pub time: f64,
+    pub expected: Timecode,
+    pub found: Timecode,
+}
+
+/// Result of reading VITC over a file
+#[derive(Debug, Clone, PartialEq)]
+pub struct VitcReport {
+    pub fps: u32,                 // Nominal timecode rate
+    pub frames_read: usize,       // Frames with a readable VITC
+    pub frames_missing: u64,      // Frames between the first and last without one
+    pub start: Option<Timecode>,  // Timecode of the first frame of the file
+    pub jumps: Vec<TimecodeJump>, // Discontinuities (edits, rewinds, bad reads)
+}
+
+impl VitcReport {
+    /// Timecode at `secs` into the file, counted on from the start timecode
+    pub fn timecode_at(&self, secs: f64, rate: FrameRate) -> Option<Timecode> {
+        let frames = (secs * rate.as_f64()).round() as i64;
+        self.start.map(|tc| tc.offset(frames, self.fps))
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        match self.start {
+            Some(start) => {
+                let _ = writeln!(
+                    text,
+                    "VITC: start {}, {} frames read, {} missing, {} discontinuities",
+                    start,
+                    self.frames_read,
+                    self.frames_missing,
+                    self.jumps.len()
+                );
+            }
+            None => text.push_str("VITC: no timecode found\n"),
+        }
+        for jump in &self.jumps {
+            let _ = writeln!(
+                text,
+                "{}  frame {}  expected {}  found {}",
+                format_timestamp(jump.time),
+                jump.frame,
+                jump.expected,
+                jump.found
+            );
+        }
+        text
+    }
+}
+
+/// Parse `readvitc,metadata=mode=print` output (on the log or written to a
+/// file with the metadata filter's `file` option)
+pub fn parse_readvitc_output(log: &str) -> Vec<VitcFrame> {
+    let mut frames = Vec::new();
+    let mut current: Option<(u64, f64)> = None;
+
+    for line in log.lines() {
+        if let Some(rest) = line.split("pts_time:").nth(1) {
+            let frame = line
+                .split("frame:")
+                .nth(1)
+                .and_then(|f| f.split_whitespace().next())
+                .and_then(|f| f.parse().ok());
+            let time = rest.split_whitespace().next().and_then(|t| t.parse().ok());
+            current = frame.zip(time);
+        } else if let Some(rest) = line.split("lavfi.readvitc.tc_str=").nth(1) {
+            let Some((frame, time)) = current else {
+                continue;
+            };
+            if let Some(timecode) = Timecode::parse(rest) {
+                frames.push(VitcFrame {
+                    frame,
+                    time,
+                    timecode,
+                });
+            }
+        }
+    }
+    frames
+}
+
+/// Check that the timecode counts on frame by frame and find the start
+pub fn build_vitc_report(frames: &[VitcFrame], rate: FrameRate) -> VitcReport {
+    let fps = nominal_fps(rate);
+    let mut jumps = Vec::new();
+    let mut missing = 0;
+
+    for pair in frames.windows(2) {
+        let (prev, cur) = (pair[0], pair[1]);
+        let gap = cur.frame.saturating_sub(prev.frame);
+        missing += gap.saturating_sub(1);
+        let expected = prev.timecode.offset(gap as i64, fps);
+        if expected != cur.timecode {
+            jumps.push(TimecodeJump {
+                frame: cur.frame,
+                time: cur.time,
+                expected,
+                found: cur.timecode,
+            });
+        }
+    }
+
+    VitcReport {
+        fps,
+        frames_read: frames.len(),
+        frames_missing: missing,
+        start: frames
+            .first()
+            .map(|f| f.timecode.offset(-(f.frame as i64), fps)),
+        jumps,
+    }
+}
+
+/// Frame rate the VITC was read at, from the frame numbers and times of the
+/// first and last timecode. A capture log counts the raw frames, which is
+/// not the rate of the output when deinterlacing doubled it.
+pub fn log_frame_rate(frames: &[VitcFrame]) -> Option<FrameRate> {
+    let (first, last) = (frames.first()?, frames.last()?);
+    let span = last.time - first.time;
+    if last.frame <= first.frame || span <= 0.0 {
+        return None;
+    }
+    FrameRate::from_f64((last.frame - first.frame) as f64 / span)
+}
+
+/// Read a VITC log written with `readvitc_filters(Some(file))`, at the rate
+/// measured from the log or else `fallback`
+pub fn read_vitc_log(log: &Path, fallback: Option<FrameRate>) -> Result<VitcReport> {
+    let text = std::fs::read_to_string(log)
+        .with_context(|| format!("Failed to read {}", log.display()))?;
+    let frames = parse_readvitc_output(&text);
+    let rate = match log_frame_rate(&frames).or(fallback) {
+        Some(rate) => rate,
+        // No timecode was read, so the rate does not matter
+        None if frames.is_empty() => FrameRate::PAL,
+        None => anyhow::bail!("Cannot tell the frame rate of {}", log.display()),
+    };
+    Ok(build_vitc_report(&frames, rate))
+}
+
+/// Filters reading VITC from the raw picture; they have to run before any
+/// deinterlacing, cropping or scaling. With `file` the timecodes are written
+/// there instead of the log.
+pub fn readvitc_filters(file: Option<&Path>) -> Vec<String> {
+    let mut print = "metadata=mode=print:key=lavfi.readvitc.tc_str".to_string();
+    if let Some(file) = file {
+        print.push_str(&format!(":file={}", ffmpeg::filter_path(file)));
+    }
+    vec!["readvitc".to_string(), print]
+}
+
+/// Read the VITC of a file
+pub fn read_vitc(input: &Path) -> Result<VitcReport> {
+    ffmpeg::check_ffmpeg()?;
+
+    let stream = ffmpeg::probe_video_stream(input)?;
+    let rate = stream
+        .frame_rate
+        .with_context(|| format!("Unknown frame rate for {}", input.display()))?;
+    let vf = readvitc_filters(None).join(",");
+    let args = vec![
+        "-hide_banner",
+        "-i",
+        input.to_str().unwrap(),
+        "-vf",
+        &vf,
+        "-an",
+        "-f",
+        "null",
+        "-",
+    ];
+
+    let log = ffmpeg::execute_ffmpeg_with_log(&args)?;
+    Ok(build_vitc_report(&parse_readvitc_output(&log), rate))
+}
+
+/// Copy a file with its start timecode set (a tmcd track in MP4/MOV, a
+/// timecode tag in Matroska)
+pub fn write_timecode(input: &Path, output: &Path, start: Timecode) -> Result<()> {
+    ffmpeg::check_ffmpeg()?;
+
+    let timecode = start.to_string();
+    ffmpeg::execute_ffmpeg(&[
+        "-i",
+        input.to_str().unwrap(),
+        "-map",
+        "0",
+        "-c",
+        "copy",
+        "-timecode",
+        &timecode,
+        "-y",
+        output.to_str().unwrap(),
+    ])
+}
+
+/// Set the start timecode of an already written file, remuxing it next to
+/// itself and replacing it
+pub fn set_timecode(output: &Path, start: Timecode) -> Result<()> {
+    let stem = output
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("output");
+    let ext = output.extension().and_then(|e| e.to_str()).unwrap_or("mkv");
+    let remuxed = output.with_file_name(format!(".{}.timecode.{}", stem, ext));
+
+    let result = write_timecode(output, &remuxed, start)
+        .and_then(|_| std::fs::rename(&remuxed, output).map_err(Into::into));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&remuxed);
+    }
+    result
+}
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use fluxara_avc::audio::{build_audio_filters, AudioEnhanceOptions, enhance_audio, enhance_audio_only};
+use fluxara_avc::trim::Trim;
 
 fn temp_file(name: &str) -> PathBuf {
     std::env::temp_dir().join(name)
@@ -30,6 +31,7 @@ fn should_build_filters_respecting_all_options() {
         compressor: false,
         gate: true,
         gate_threshold: -42.5,
+        trim: Trim::default(),
     };
 
     let filters = build_audio_filters(&opts);
@@ -61,6 +63,7 @@ fn should_build_filters_without_high_or_lowpass_when_none() {
         compressor: true,
         gate: false,
         gate_threshold: -50.0,
+        trim: Trim::default(),
     };
 
     let filters = build_audio_filters(&opts);
@@ -119,6 +122,7 @@ fn should_enhance_audio_only_to_lossless_flac() {
         compressor: true,
         gate: false,
         gate_threshold: -40.0,
+        trim: Trim::default(),
     };
 
     let res = enhance_audio_only(&input, &output, &opts);
@@ -0,0 +1,79 @@
+use fluxara_avc::preset::{builtin_preset, Preset, PresetFormat, BUILTIN_PRESETS};
+use fluxara_avc::video::{
+    build_video_filters, DenoiseType, FrameRate, RateConversion, ScanType, VideoCodec, VideoEncoder,
+};
+
+#[test]
+fn should_round_trip_builtin_presets() {
+    for name in BUILTIN_PRESETS {
+        let preset = builtin_preset(name).unwrap();
+        assert!(preset.video.is_some() || preset.audio.is_some());
+        for format in [PresetFormat::Toml, PresetFormat::Json] {
+            let text = preset.to_text(format).unwrap();
+            let parsed = Preset::parse(&text, format).unwrap();
+            assert_eq!(
+                parsed.to_text(format).unwrap(),
+                text,
+                "{} {:?}",
+                name,
+                format
+            );
+            assert_eq!(
+                parsed.video.as_ref().map(build_video_filters),
+                preset.video.as_ref().map(build_video_filters)
+            );
+        }
+    }
+    assert!(builtin_preset("cassette").unwrap().video.is_none());
+    assert!(builtin_preset("umatic").is_none());
+}
+
+#[test]
+fn should_fill_partial_presets_with_defaults() {
+    let preset = Preset::parse(
+        r#"
+description = "Deck 2"
+
+[video]
+scan_type = "tff"
+denoise = "nlmeans"
+frame_rate = { resample = { target = "25", mode = "blend" } }
+
+[video.overscan]
+bottom = 12
+
+[video.encoder]
+codec = "ffv1"
+
+[audio]
+notch_freq = 50
+"#,
+        PresetFormat::Toml,
+    )
+    .unwrap();
+
+    let video = preset.video.unwrap();
+    assert_eq!(video.scan_type, Some(ScanType::Tff));
+    assert_eq!(video.denoise, DenoiseType::Nlmeans);
+    assert!(video.deinterlace);
+    assert_eq!(
+        video.overscan.as_ref().map(|m| (m.bottom, m.left)),
+        Some((12, 8))
+    );
+    assert!(matches!(
+        video.frame_rate,
+        Some(RateConversion::Resample { target, .. }) if target == FrameRate::PAL
+    ));
+    assert_eq!(video.encoder, VideoEncoder::new(VideoCodec::Ffv1));
+    assert!(video.sharpen.is_some());
+    let audio = preset.audio.unwrap();
+    assert_eq!(audio.notch_freq, Some(50));
+    assert_eq!(audio.highpass_freq, Some(80));
+
+    assert!(Preset::parse("[video]\ndenoise = \"median\"\n", PresetFormat::Toml).is_err());
+    assert!(Preset::parse(
+        r#"{"video": {"frame_rate": {"resample": {"target": "fast", "mode": "blend"}}}}"#,
+        PresetFormat::Json
+    )
+    .is_err());
+}
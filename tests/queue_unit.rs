@@ -0,0 +1,106 @@
+use fluxara_avc::queue::{kill_job, JobQueue, JobState};
+use std::path::PathBuf;
+
+fn args(command: &str) -> Vec<String> {
+    command.split_whitespace().map(String::from).collect()
+}
+
+#[test]
+fn should_run_jobs_by_priority_and_retry_failures() {
+    let mut queue = JobQueue::default();
+    let low = queue.add(args("formats"), PathBuf::from("/"), 0, 0);
+    let high = queue.add(args("info -i a.mkv"), PathBuf::from("/"), 5, 1);
+    let later = queue.add(args("info -i b.mkv"), PathBuf::from("/"), 0, 0);
+    assert_eq!(queue.next_job(), Some(high));
+
+    // One retry: the first failure re-queues, the second is final
+    queue.start(high, 100, None).unwrap();
+    assert_eq!(queue.next_job(), Some(low));
+    queue
+        .finish(high, Err("Exited with 1".to_string()))
+        .unwrap();
+    assert_eq!(queue.get(high).unwrap().state, JobState::Queued);
+    queue.start(high, 101, None).unwrap();
+    queue
+        .finish(high, Err("Exited with 1".to_string()))
+        .unwrap();
+    let job = queue.get(high).unwrap();
+    assert_eq!((job.state, job.attempts), (JobState::Failed, 2));
+    assert_eq!(queue.next_job(), Some(low));
+
+    queue.retry(high).unwrap();
+    assert_eq!(queue.next_job(), Some(high));
+    assert!(queue.retry(high).is_err());
+
+    // A job cancelled while running stays cancelled when its process exits
+    queue.start(low, 102, None).unwrap();
+    assert_eq!(queue.cancel(low).unwrap(), Some(102));
+    queue.finish(low, Err("Killed".to_string())).unwrap();
+    assert_eq!(queue.get(low).unwrap().state, JobState::Cancelled);
+    assert!(queue.cancel(low).is_err());
+
+    assert_eq!(queue.cancel(later).unwrap(), None);
+    queue.start(high, 103, None).unwrap();
+    queue.finish(high, Ok(())).unwrap();
+    assert_eq!(queue.get(high).unwrap().state, JobState::Done);
+    assert_eq!(queue.next_job(), None);
+    assert!(queue.cancel(99).is_err());
+}
+
+#[test]
+fn should_persist_and_recover_interrupted_jobs() {
+    let dir = std::env::temp_dir().join(format!("fluxara-queue-{}", std::process::id()));
+    let path = dir.join("queue.json");
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(JobQueue::load(&path).unwrap().jobs.is_empty());
+
+    let first = JobQueue::update(&path, |q| Ok(q.add(args("formats"), dir.clone(), 0, 0))).unwrap();
+    let second =
+        JobQueue::update(&path, |q| Ok(q.add(args("formats"), dir.clone(), 0, 0))).unwrap();
+    assert_ne!(first, second);
+    JobQueue::update(&path, |q| q.start(first, 4242, None)).unwrap();
+
+    // As after a crash: the queue on disk still says running
+    let mut queue = JobQueue::load(&path).unwrap();
+    assert_eq!(queue.get(first).unwrap().state, JobState::Running);
+    assert_eq!(queue.recover(|_| true), Vec::<u64>::new());
+    assert_eq!(queue.recover(|_| false), vec![first]);
+    let job = queue.get(first).unwrap();
+    assert_eq!((job.state, job.pid), (JobState::Queued, None));
+    assert_eq!(queue.next_job(), Some(first));
+
+    queue.save(&path).unwrap();
+    assert_eq!(JobQueue::load(&path).unwrap(), queue);
+    assert!(!dir.join("queue.json.lock").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn should_stop_a_job_with_the_processes_it_started() {
+    use std::os::unix::process::CommandExt;
+
+    // The shell stands in for the CLI, the sleep for its ffmpeg child
+    let mut child = std::process::Command::new("sh")
+        .args(["-c", "sleep 30 & echo $!; wait"])
+        .stdout(std::process::Stdio::piped())
+        .process_group(0)
+        .spawn()
+        .unwrap();
+    let mut line = String::new();
+    std::io::BufRead::read_line(
+        &mut std::io::BufReader::new(child.stdout.take().unwrap()),
+        &mut line,
+    )
+    .unwrap();
+    let grandchild = line.trim();
+
+    kill_job(child.id()).unwrap();
+    assert!(!child.wait().unwrap().success());
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    // Gone, or a zombie waiting for init to reap it
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", grandchild)).unwrap_or_default();
+    assert!(stat.is_empty() || stat.contains(") Z "), "{}", stat);
+
+    // Failing to signal the group is reported
+    assert!(kill_job(999_999_999).is_err());
+}
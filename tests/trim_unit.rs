@@ -0,0 +1,123 @@
+use fluxara_avc::trim::{
+    check_crossfade, concat_list_entry, cut_filtergraph, keep_segments, parse_ranges,
+    parse_timestamp, starts_on_keyframes, CutList, Trim,
+};
+use std::path::Path;
+
+#[test]
+fn should_parse_timestamps_and_ranges() {
+    assert_eq!(parse_timestamp("90"), Some(90.0));
+    assert_eq!(parse_timestamp("1:30"), Some(90.0));
+    assert_eq!(parse_timestamp("01:02:03.5"), Some(3723.5));
+    assert_eq!(parse_timestamp("1:2:3:4"), None);
+    assert_eq!(parse_timestamp("-5"), None);
+
+    assert_eq!(
+        parse_ranges("0-0:12,1:02:00-"),
+        Some(vec![(0.0, Some(12.0)), (3720.0, None)])
+    );
+    assert_eq!(parse_ranges("10-5"), None);
+    assert_eq!(parse_ranges("10"), None);
+}
+
+#[test]
+fn should_seek_with_input_options() {
+    assert!(Trim::default().input_args().is_empty());
+    let trim = Trim {
+        start: Some(12.0),
+        end: Some(3600.5),
+    };
+    assert_eq!(trim.input_args(), vec!["-ss", "12.000", "-to", "3600.500"]);
+}
+
+#[test]
+fn should_sample_inside_the_kept_range() {
+    // Centres of equal parts, clear of the leader at the start
+    assert_eq!(
+        Trim::default().sample_times(100.0, 4),
+        vec![12.5, 37.5, 62.5, 87.5]
+    );
+    let trim = Trim {
+        start: Some(60.0),
+        end: Some(160.0),
+    };
+    assert_eq!(trim.sample_times(3600.0, 2), vec![85.0, 135.0]);
+    assert_eq!(trim.sample_times(0.0, 2), vec![85.0, 135.0]);
+    // Unknown duration and no end: only the start is known
+    assert_eq!(Trim::default().sample_times(0.0, 5), vec![0.0]);
+}
+
+#[test]
+fn should_keep_what_the_cuts_leave() {
+    let cuts = [
+        (50.0, Some(60.0)),
+        (0.0, Some(10.0)),
+        (55.0, Some(70.0)),
+        (90.0, None),
+    ];
+    assert_eq!(
+        keep_segments(100.0, &cuts),
+        vec![(10.0, 50.0), (70.0, 90.0)]
+    );
+    assert!(keep_segments(100.0, &[(0.0, None)]).is_empty());
+
+    let keyframes = [0.0, 10.0, 20.02, 70.0];
+    assert!(starts_on_keyframes(
+        &[(10.0, 50.0), (70.0, 90.0)],
+        &keyframes,
+        0.02
+    ));
+    assert!(!starts_on_keyframes(&[(15.0, 50.0)], &keyframes, 0.02));
+}
+
+#[test]
+fn should_join_segments_with_audio_crossfade_in_sync() {
+    let segments = [(10.0, 50.0), (70.0, 90.0)];
+    assert_eq!(
+        cut_filtergraph(&segments, true, true, None),
+        "[0:v]trim=start=10.000:end=50.000,setpts=PTS-STARTPTS[v0];\
+         [0:a]atrim=start=10.000:end=50.000,asetpts=PTS-STARTPTS[a0];\
+         [0:v]trim=start=70.000:end=90.000,setpts=PTS-STARTPTS[v1];\
+         [0:a]atrim=start=70.000:end=90.000,asetpts=PTS-STARTPTS[a1];\
+         [v0][v1]concat=n=2:v=1:a=0[v];\
+         [a0][a1]concat=n=2:v=0:a=1[a]"
+    );
+
+    // The first audio segment runs on by the crossfade length
+    let graph = cut_filtergraph(&segments, true, true, Some(0.05));
+    assert!(graph.contains("[0:a]atrim=start=10.000:end=50.050"));
+    assert!(graph.contains("[0:a]atrim=start=70.000:end=90.000"));
+    assert!(graph.ends_with("[a0][a1]acrossfade=d=0.050:c1=tri:c2=tri[a]"));
+
+    assert!(!cut_filtergraph(&segments, true, false, None).contains("[0:a]"));
+    assert_eq!(
+        cut_filtergraph(&segments, false, true, None),
+        "[0:a]atrim=start=10.000:end=50.000,asetpts=PTS-STARTPTS[a0];\
+         [0:a]atrim=start=70.000:end=90.000,asetpts=PTS-STARTPTS[a1];\
+         [a0][a1]concat=n=2:v=0:a=1[a]"
+    );
+}
+
+#[test]
+fn should_reject_crossfades_longer_than_a_segment() {
+    let segments = [(10.0, 50.0), (70.0, 70.04), (80.0, 90.0)];
+    assert!(check_crossfade(&segments, None).is_ok());
+    assert!(check_crossfade(&segments, Some(0.03)).is_ok());
+    let err = check_crossfade(&segments, Some(0.05)).unwrap_err();
+    assert!(err.to_string().contains("00:01:10.000-00:01:10.040"));
+    assert!(check_crossfade(&segments, Some(0.0)).is_err());
+    // A single segment has no joins
+    assert!(check_crossfade(&[(0.0, 0.01)], Some(0.05)).is_ok());
+}
+
+#[test]
+fn should_quote_concat_list_paths() {
+    assert_eq!(
+        concat_list_entry(Path::new("/tmp/it's/part000.mkv")),
+        "file '/tmp/it'\\''s/part000.mkv'\n"
+    );
+    assert_eq!(
+        CutList::intermediate_path(Path::new("/videos/tape.mp4")),
+        Path::new("/videos/.tape.cut.mkv")
+    );
+}
@@ -0,0 +1,1934 @@
+use std::path::PathBuf;
+
+use fluxara_avc::audio::AudioEnhanceOptions;
+use fluxara_avc::ffmpeg::VideoStreamInfo;
+use fluxara_avc::trim::Trim;
+use fluxara_avc::video::aspect::{
+    plan_pixel_aspect, sd_sample_aspect, source_display_aspect, square_width,
+};
+use fluxara_avc::video::autocolor::{parse_signalstats_output, plan_correction, FrameStats};
+use fluxara_avc::video::captions::{
+    decode_captions, parse_readeia608_output, retime_captions, subtitle_file, Caption,
+    SubtitleFormat,
+};
+use fluxara_avc::video::chroma::{chroma_filters, estimate_shift_from_frames};
+use fluxara_avc::video::color::{color_tag_warnings, plan_conversion, ColorRange, ColorStandard};
+use fluxara_avc::video::compare::{compare_filtergraph, pane_size};
+use fluxara_avc::video::crop::{parse_crop, parse_cropdetect_output};
+use fluxara_avc::video::depth::filter_names;
+use fluxara_avc::video::dropout::{
+    before_seek, build_report, parse_dropout_stats, repair_filters, DropoutOptions, DropoutRepair,
+    RepairMode,
+};
+use fluxara_avc::video::film::{cadence_filters, cleanup_filters, film_rate};
+use fluxara_avc::video::interlace::{deinterlace_filter, parse_idet_output, IdetStats};
+use fluxara_avc::video::metrics::{
+    metrics_filtergraph, parse_psnr_stats, parse_ssim_stats, parse_vmaf_log, Metric, MetricsReport,
+};
+use fluxara_avc::video::overscan::{detect_from_frames, overscan_filters};
+use fluxara_avc::video::restore::{resolve_audio_stream, restore_args, restore_filtergraph};
+use fluxara_avc::video::scenes::{chapter_list, parse_scene_cuts, plan_scenes, scene_file_name};
+use fluxara_avc::video::sharpen::noise_protected_filters;
+use fluxara_avc::video::thumbnails::{contact_sheet_filters, parse_grid, thumbnail_file_name};
+use fluxara_avc::video::timecode::{
+    build_vitc_report, log_frame_rate, parse_readvitc_output, readvitc_filters,
+};
+use fluxara_avc::video::{
+    build_video_filters, rescue_audio_filters, AspectHandling, AspectOptions, AutoColorMode,
+    AutoColorOptions, ChapterFormat, ChromaCorrection, CompareLayout, CompareOptions, CropRect,
+    DeinterlaceMethod, DenoiseType, DisplayAspect, DustRemoval, FieldRate, FilmSource, FrameRate,
+    FrameRateMode, ImageFormat, OverscanMask, OverscanMode, ParConvention, PixelFormat,
+    ProcessingDepth, RateControl, RateConversion, RestoreOptions, ScaleFit, ScaleOptions,
+    ScaleTarget, Scaler, ScanType, SharpenMethod, SharpenOptions, ThumbnailOptions, Timecode,
+    VhsRescueOptions, VideoCodec, VideoEncoder, VideoEnhanceOptions,
+};
+
+#[test]
+fn should_build_filters_with_defaults() {
+    let opts = VideoEnhanceOptions::default();
+    let filters = build_video_filters(&opts);
+
+    // Defaults include: bwdif with auto parity, hqdn3d, unsharp, eq
+    assert!(filters
+        .iter()
+        .any(|f| f == "bwdif=mode=send_field:parity=auto:deint=all"));
+    assert!(filters.iter().any(|f| f.starts_with("hqdn3d")));
+    assert!(filters.iter().any(|f| f.starts_with("unsharp")));
+    assert!(filters.iter().any(|f| f.starts_with("eq=")));
+}
+
+#[test]
+fn should_deinterlace_with_detected_parity() {
+    let opts = VideoEnhanceOptions {
+        scan_type: Some(ScanType::Bff),
+        ..Default::default()
+    };
+
+    let filters = build_video_filters(&opts);
+    assert!(filters
+        .iter()
+        .any(|f| f == "bwdif=mode=send_field:parity=bff:deint=all"));
+}
+
+#[test]
+fn should_skip_deinterlace_for_progressive_sources() {
+    let opts = VideoEnhanceOptions {
+        scan_type: Some(ScanType::Progressive),
+        ..Default::default()
+    };
+
+    let filters = build_video_filters(&opts);
+    assert!(!filters.iter().any(|f| f.starts_with("bwdif")));
+    assert!(!filters.iter().any(|f| f.starts_with("fieldmatch")));
+}
+
+#[test]
+fn should_inverse_telecine_film_sources() {
+    let opts = VideoEnhanceOptions {
+        scan_type: Some(ScanType::Telecine),
+        ..Default::default()
+    };
+
+    let filters = build_video_filters(&opts);
+    let fieldmatch = filters.iter().position(|f| f.starts_with("fieldmatch"));
+    let decimate = filters.iter().position(|f| f == "decimate");
+    assert!(fieldmatch.is_some() && decimate.is_some());
+    assert!(fieldmatch < decimate, "fieldmatch must run before decimate");
+    assert!(!filters.iter().any(|f| f.starts_with("bwdif")));
+}
+
+#[test]
+fn should_parse_idet_summary() {
+    let log = "\
+[Parsed_idet_0 @ 0x55d] Repeated Fields: Neither:   980 Top:    10 Bottom:    11
+[Parsed_idet_0 @ 0x55d] Single frame detection: TFF:   700 BFF:     3 Progressive:   250 Undetermined:    48
+[Parsed_idet_0 @ 0x55d] Multi frame detection: TFF:   801 BFF:     0 Progressive:   190 Undetermined:    10
+";
+
+    let stats = parse_idet_output(log).expect("summary should parse");
+    assert_eq!(stats.tff, 801);
+    assert_eq!(stats.bff, 0);
+    assert_eq!(stats.progressive, 190);
+    assert_eq!(stats.undetermined, 10);
+    assert_eq!(stats.repeated_top, 10);
+    assert_eq!(stats.repeated_bottom, 11);
+    assert_eq!(stats.scan_type(), ScanType::Tff);
+}
+
+#[test]
+fn should_classify_scan_types_from_idet_counters() {
+    let progressive = IdetStats {
+        progressive: 950,
+        tff: 20,
+        repeated_neither: 1000,
+        ..Default::default()
+    };
+    assert_eq!(progressive.scan_type(), ScanType::Progressive);
+
+    let bff = IdetStats {
+        bff: 900,
+        progressive: 50,
+        repeated_neither: 1000,
+        ..Default::default()
+    };
+    assert_eq!(bff.scan_type(), ScanType::Bff);
+
+    // 3:2 pulldown: two of every five frames repeat a field
+    let telecine = IdetStats {
+        tff: 400,
+        progressive: 600,
+        repeated_neither: 600,
+        repeated_top: 200,
+        repeated_bottom: 200,
+        ..Default::default()
+    };
+    assert_eq!(telecine.scan_type(), ScanType::Telecine);
+
+    // Counters from the sampled parts are summed: a progressive leader does
+    // not outvote the interlaced programme
+    let mut total = IdetStats {
+        progressive: 200,
+        repeated_neither: 200,
+        ..Default::default()
+    };
+    for _ in 0..4 {
+        total.add(&IdetStats {
+            tff: 190,
+            progressive: 10,
+            repeated_neither: 200,
+            ..Default::default()
+        });
+    }
+    assert_eq!((total.tff, total.progressive), (760, 240));
+    assert_eq!(total.scan_type(), ScanType::Tff);
+
+    assert_eq!(ScanType::from_name("BFF"), Some(ScanType::Bff));
+    assert_eq!(ScanType::from_name("interlaced"), None);
+}
+
+#[test]
+fn should_return_none_without_idet_summary() {
+    assert!(parse_idet_output("frame=  100 fps=0.0 q=-0.0 size=N/A").is_none());
+}
+
+#[test]
+fn should_deinterlace_at_frame_rate_with_selected_method() {
+    let opts = VideoEnhanceOptions {
+        scan_type: Some(ScanType::Tff),
+        deinterlace_method: DeinterlaceMethod::Yadif,
+        field_rate: FieldRate::Frame,
+        ..Default::default()
+    };
+
+    let filters = build_video_filters(&opts);
+    assert!(filters
+        .iter()
+        .any(|f| f == "yadif=mode=send_frame:parity=tff:deint=all"));
+    assert!(!filters.iter().any(|f| f.starts_with("bwdif")));
+
+    assert_eq!(FieldRate::from_name("Frame"), Some(FieldRate::Frame));
+    assert_eq!(FieldRate::from_name("fields"), None);
+}
+
+#[test]
+fn should_build_each_deinterlace_method() {
+    assert_eq!(
+        deinterlace_filter(&DeinterlaceMethod::W3fdif, FieldRate::Field, "auto"),
+        "w3fdif=filter=complex:mode=field:parity=auto:deint=all"
+    );
+    assert_eq!(
+        deinterlace_filter(&DeinterlaceMethod::Estdif, FieldRate::Frame, "bff"),
+        "estdif=mode=frame:parity=bff:deint=all"
+    );
+
+    let nnedi = DeinterlaceMethod::Nnedi {
+        weights: PathBuf::from("/opt/nnedi3_weights.bin"),
+    };
+    assert_eq!(
+        deinterlace_filter(&nnedi, FieldRate::Field, "tff"),
+        "nnedi=weights='/opt/nnedi3_weights.bin':field=tf:deint=all"
+    );
+    assert_eq!(
+        deinterlace_filter(&nnedi, FieldRate::Frame, "auto"),
+        "nnedi=weights='/opt/nnedi3_weights.bin':field=a:deint=all"
+    );
+}
+
+#[test]
+fn should_mask_overscan_with_black_padding() {
+    let opts = VideoEnhanceOptions {
+        overscan: Some(OverscanMask::default()),
+        ..Default::default()
+    };
+
+    let filters = build_video_filters(&opts);
+    let crop = filters.iter().position(|f| f == "crop=iw-16:ih-8:8:0");
+    let pad = filters.iter().position(|f| f == "pad=iw+16:ih+8:8:0:black");
+    let denoise = filters.iter().position(|f| f.starts_with("hqdn3d"));
+    assert!(crop.is_some() && pad.is_some());
+    assert!(pad < denoise, "overscan must be masked before denoising");
+}
+
+#[test]
+fn should_crop_overscan_without_padding() {
+    let mask = OverscanMask {
+        top: 2,
+        bottom: 10,
+        left: 0,
+        right: 4,
+        mode: OverscanMode::Crop,
+    };
+
+    assert_eq!(overscan_filters(&mask), vec!["crop=iw-4:ih-12:0:2"]);
+    assert!(overscan_filters(&OverscanMask {
+        top: 0,
+        bottom: 0,
+        left: 0,
+        right: 0,
+        mode: OverscanMode::Crop,
+    })
+    .is_empty());
+
+    assert_eq!(OverscanMode::from_name("CROP"), Some(OverscanMode::Crop));
+    assert_eq!(OverscanMode::from_name("cropped"), None);
+}
+
+#[test]
+fn should_detect_head_switching_and_dark_edges() {
+    let (width, height) = (128, 96);
+    let mut frame = vec![0u8; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            // Smooth gradient picture with 5 black columns on the left
+            let mut p = if x < 5 { 4 } else { 100 + (y / 8) as u8 };
+            // Head-switching band: 5 torn lines at the bottom
+            if y >= height - 5 {
+                p = if (x + y) % 2 == 0 { 20 } else { 230 };
+            }
+            frame[y * width + x] = p;
+        }
+    }
+    let frames = [frame.clone(), frame].concat();
+
+    let mask = detect_from_frames(&frames, width, height);
+    assert_eq!(mask.bottom, 6, "5 noisy lines rounded up to even");
+    assert_eq!(mask.left, 6, "5 dark columns rounded up to even");
+    assert_eq!(mask.top, 0);
+    assert_eq!(mask.right, 0);
+
+    // A dark scene is not a border: edges as dark as the picture stay
+    let dark = vec![22u8; width * height];
+    assert!(detect_from_frames(&dark, width, height).is_empty());
+}
+
+#[test]
+fn should_parse_last_cropdetect_suggestion() {
+    let log = "\
+[Parsed_cropdetect_0 @ 0x5f1] x1:0 x2:719 y1:62 y2:417 w:720 h:352 x:0 y:64 pts:1001 t:0.033367 crop=720:352:0:64
+[Parsed_cropdetect_0 @ 0x5f1] x1:0 x2:719 y1:58 y2:421 w:720 h:360 x:0 y:60 pts:2002 t:0.066733 crop=720:360:0:60
+frame=   50 fps=0.0 q=-0.0 Lsize=N/A time=00:00:01.66 bitrate=N/A speed=  21x
+";
+
+    let crop = parse_cropdetect_output(log).expect("crop should parse");
+    assert_eq!(
+        crop,
+        CropRect {
+            width: 720,
+            height: 360,
+            x: 0,
+            y: 60
+        }
+    );
+    assert!(parse_cropdetect_output("frame=   50 fps=0.0").is_none());
+}
+
+#[test]
+fn should_union_crop_samples_and_apply_crop_filter() {
+    let letterbox = CropRect {
+        width: 720,
+        height: 352,
+        x: 0,
+        y: 64,
+    };
+    let pillarbox = CropRect {
+        width: 544,
+        height: 480,
+        x: 88,
+        y: 0,
+    };
+    assert_eq!(
+        letterbox.union(&pillarbox),
+        CropRect {
+            width: 720,
+            height: 480,
+            x: 0,
+            y: 0
+        }
+    );
+    assert_eq!(parse_crop("720:352:0:64"), Some(letterbox));
+    assert_eq!(parse_crop("720:352"), None);
+
+    let opts = VideoEnhanceOptions {
+        crop: Some(letterbox),
+        ..Default::default()
+    };
+    let filters = build_video_filters(&opts);
+    assert!(filters.iter().any(|f| f == "crop=720:352:0:64"));
+}
+
+fn sd_stream(height: u32) -> VideoStreamInfo {
+    VideoStreamInfo {
+        width: 720,
+        height,
+        duration: 60.0,
+        frame_rate: None,
+        color_primaries: None,
+        color_space: None,
+        color_transfer: None,
+        color_range: None,
+        sample_aspect: None,
+        display_aspect: None,
+    }
+}
+
+#[test]
+fn should_convert_untagged_sd_to_bt709_for_hd_output() {
+    let conversion = plan_conversion(&sd_stream(480), 1080);
+    assert_eq!(conversion.source, ColorStandard::Bt601Ntsc);
+    assert_eq!(conversion.target, ColorStandard::Bt709);
+    assert_eq!(
+        conversion.filter().as_deref(),
+        Some("colorspace=all=bt709:iall=smpte170m:range=tv:irange=tv")
+    );
+    assert!(conversion
+        .output_args()
+        .windows(2)
+        .any(|w| w[0] == "-colorspace" && w[1] == "bt709"));
+
+    let opts = VideoEnhanceOptions {
+        color_conversion: Some(conversion),
+        scale_width: Some(1440),
+        scale_height: Some(1080),
+        ..Default::default()
+    };
+    let filters = build_video_filters(&opts);
+    let colorspace = filters.iter().position(|f| f.starts_with("colorspace="));
+    let scale = filters.iter().position(|f| f.starts_with("scale="));
+    assert!(colorspace.is_some() && colorspace < scale);
+}
+
+#[test]
+fn should_keep_sd_standard_and_only_tag_when_not_upscaling() {
+    let mut stream = sd_stream(576);
+    stream.color_range = Some("pc".to_string());
+    let conversion = plan_conversion(&stream, 576);
+    assert_eq!(conversion.source, ColorStandard::Bt601Pal);
+    assert_eq!(conversion.target, ColorStandard::Bt601Pal);
+    assert_eq!(conversion.source_range, ColorRange::Pc);
+    // Full range input still needs a range conversion
+    assert_eq!(
+        conversion.filter().as_deref(),
+        Some("colorspace=all=bt470bg:iall=bt470bg:range=tv:irange=pc")
+    );
+
+    stream.color_range = Some("tv".to_string());
+    assert_eq!(plan_conversion(&stream, 576).filter(), None);
+}
+
+#[test]
+fn should_convert_and_tag_from_the_probed_transfer() {
+    let mut stream = sd_stream(576);
+    stream.color_transfer = Some("bt470bg".to_string());
+    // Kept standard: the tagged curve is passed through and tagged as it was
+    let kept = plan_conversion(&stream, 576);
+    assert_eq!(kept.source_transfer, Some("bt470bg"));
+    assert!(kept
+        .output_args()
+        .windows(2)
+        .any(|w| w[0] == "-color_trc" && w[1] == "bt470bg"));
+
+    // To HD the conversion starts from the tagged curve
+    let hd = plan_conversion(&stream, 1080);
+    assert_eq!(
+        hd.filter().as_deref(),
+        Some("colorspace=all=bt709:iall=bt470bg:range=tv:irange=tv:trc=bt709:itrc=bt470bg")
+    );
+    assert!(hd
+        .output_args()
+        .windows(2)
+        .any(|w| w[0] == "-color_trc" && w[1] == "bt709"));
+
+    stream.color_transfer = Some("arib-std-b67".to_string());
+    assert_eq!(plan_conversion(&stream, 576).source_transfer, None);
+}
+
+#[test]
+fn should_warn_on_missing_and_mismatched_color_tags() {
+    let untagged = color_tag_warnings(&sd_stream(480));
+    assert_eq!(untagged.len(), 1);
+    assert!(untagged[0].contains("No colour tags"));
+
+    let mut mistagged = sd_stream(480);
+    mistagged.color_space = Some("bt709".to_string());
+    mistagged.color_primaries = Some("smpte170m".to_string());
+    let warnings = color_tag_warnings(&mistagged);
+    assert!(warnings.iter().any(|w| w.contains("disagree")));
+    assert!(warnings.iter().any(|w| w.contains("raster suggests")));
+
+    let mut tagged = sd_stream(480);
+    tagged.color_space = Some("smpte170m".to_string());
+    tagged.color_primaries = Some("smpte170m".to_string());
+    tagged.color_transfer = Some("smpte170m".to_string());
+    assert!(color_tag_warnings(&tagged).is_empty());
+
+    tagged.color_transfer = Some("bt709".to_string());
+    let warnings = color_tag_warnings(&tagged);
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].contains("Transfer (bt709)"));
+    tagged.color_transfer = Some("smpte2084".to_string());
+    assert!(color_tag_warnings(&tagged)[0].contains("not supported"));
+}
+
+fn frame_stats(time: f64, ymin: f32, ymax: f32, yavg: f32, uavg: f32, vavg: f32) -> FrameStats {
+    FrameStats {
+        time,
+        ymin,
+        ymax,
+        yavg,
+        uavg,
+        vavg,
+    }
+}
+
+#[test]
+fn should_parse_signalstats_metadata() {
+    let log = "\
+[Parsed_metadata_2 @ 0x6a1] frame:0    pts:0       pts_time:0
+[Parsed_metadata_2 @ 0x6a1] lavfi.signalstats.YMIN=20
+[Parsed_metadata_2 @ 0x6a1] lavfi.signalstats.YMAX=220
+[Parsed_metadata_2 @ 0x6a1] lavfi.signalstats.YAVG=110.5
+[Parsed_metadata_2 @ 0x6a1] lavfi.signalstats.UAVG=126
+[Parsed_metadata_2 @ 0x6a1] lavfi.signalstats.VAVG=135
+[Parsed_metadata_2 @ 0x6a1] frame:1    pts:1       pts_time:0.5
+[Parsed_metadata_2 @ 0x6a1] lavfi.signalstats.YMIN=22
+";
+
+    let frames = parse_signalstats_output(log);
+    assert_eq!(frames.len(), 2);
+    assert_eq!(
+        frames[0],
+        frame_stats(0.0, 20.0, 220.0, 110.5, 126.0, 135.0)
+    );
+    assert_eq!(frames[1].time, 0.5);
+    assert_eq!(frames[1].ymin, 22.0);
+}
+
+#[test]
+fn should_stretch_levels_and_neutralise_color_cast_within_limits() {
+    // Washed-out, red-tinted tape: luma 40-200, V well above neutral
+    let frames: Vec<FrameStats> = (0..20)
+        .map(|i| frame_stats(i as f64 * 0.5, 40.0, 200.0, 110.0, 128.0, 140.0))
+        .collect();
+    let opts = AutoColorOptions {
+        strength: 1.0,
+        ..Default::default()
+    };
+
+    let correction = plan_correction(&frames, &opts);
+    assert_eq!(correction.segments.len(), 1);
+    let seg = &correction.segments[0];
+    assert!(seg.imin.iter().all(|&v| v > 0.05), "black point raised");
+    assert!(seg.imax[1] < 1.0, "white point lowered");
+    // Red is reduced relative to green and blue
+    assert!(seg.imax[0] > seg.imax[1] && seg.imax[0] > seg.imax[2]);
+    // Gain limited to max_wb_gain
+    let stretch = |c: usize| 1.0 / (seg.imax[c] - seg.imin[c]);
+    assert!(stretch(1) / stretch(0) <= opts.max_wb_gain * 1.001);
+
+    let filters = correction.filters();
+    assert_eq!(filters.len(), 1);
+    assert!(filters[0].starts_with("colorlevels=rimin="));
+}
+
+#[test]
+fn should_ramp_smoothed_corrections_between_windows() {
+    // Dark first window, bright second: 10 s windows at 0 and 10 s
+    let mut frames: Vec<FrameStats> = (0..20)
+        .map(|i| frame_stats(i as f64 * 0.5, 40.0, 150.0, 90.0, 128.0, 128.0))
+        .collect();
+    frames.extend((20..40).map(|i| frame_stats(i as f64 * 0.5, 16.0, 235.0, 120.0, 128.0, 128.0)));
+
+    let correction = plan_correction(&frames, &AutoColorOptions::default());
+    assert!(correction.interpolate);
+    let (first, second) = (&correction.segments[0], &correction.segments[1]);
+    assert_eq!(second.start, 10.0);
+    // Window values are kept as measured, not blended
+    assert_eq!(second.imin, [0.0; 3]);
+
+    let filters = correction.filters();
+    let expected = format!(
+        "sendcmd=c='0.000-10.000 [expr] colorlevels rimin {:.4}+({:.4})*TI,",
+        first.imin[0],
+        second.imin[0] - first.imin[0]
+    );
+    assert!(filters[0].starts_with(&expected), "{}", filters[0]);
+
+    // Seeking half way into the ramp starts from the ramp's midpoint
+    let seeked = correction.clone().after_seek(5.0);
+    assert_eq!(
+        seeked.segments.iter().map(|s| s.start).collect::<Vec<_>>(),
+        vec![0.0, 5.0]
+    );
+    assert!((seeked.segments[0].imin[0] - first.imin[0] / 2.0).abs() < 1e-6);
+}
+
+#[test]
+fn should_switch_corrections_per_scene_with_sendcmd() {
+    let mut frames: Vec<FrameStats> = (0..10)
+        .map(|i| frame_stats(i as f64 * 0.5, 30.0, 210.0, 60.0, 128.0, 128.0))
+        .collect();
+    frames.extend((10..20).map(|i| frame_stats(i as f64 * 0.5, 60.0, 180.0, 150.0, 120.0, 128.0)));
+    let opts = AutoColorOptions {
+        mode: AutoColorMode::from_name("scene").unwrap(),
+        ..Default::default()
+    };
+    assert_eq!(opts.mode, AutoColorMode::PerScene);
+    assert_eq!(AutoColorMode::from_name("scenes"), None);
+
+    let correction = plan_correction(&frames, &opts);
+    assert_eq!(correction.segments.len(), 2);
+    assert_eq!(correction.segments[0].start, 0.0);
+    assert_eq!(correction.segments[1].start, 5.0);
+
+    let filters = correction.filters();
+    assert_eq!(filters.len(), 2);
+    assert!(filters[0].starts_with("sendcmd=c='5.000 colorlevels rimin "));
+    assert!(filters[1].starts_with("colorlevels="));
+
+    // A seek to 6 s starts on the second scene, at 0
+    let seeked = correction.clone().after_seek(6.0);
+    assert_eq!(seeked.segments.len(), 1);
+    assert_eq!(seeked.segments[0].start, 0.0);
+    assert_eq!(seeked.segments[0].imin, correction.segments[1].imin);
+    let seeked = correction.clone().after_seek(2.0);
+    assert_eq!(
+        seeked.segments.iter().map(|s| s.start).collect::<Vec<_>>(),
+        vec![0.0, 3.0]
+    );
+
+    // Replaces the fixed eq when enabled
+    let opts = VideoEnhanceOptions {
+        auto_color: Some(correction),
+        ..Default::default()
+    };
+    let filters = build_video_filters(&opts);
+    assert!(!filters.iter().any(|f| f.starts_with("eq=")));
+    assert!(filters.iter().any(|f| f.starts_with("colorlevels=")));
+
+    // Slowing PAL to 23.976 fps moves the 5 s scene change to 5.214 s
+    let opts = VideoEnhanceOptions {
+        frame_rate: Some(RateConversion::PAL_TO_NTSC),
+        ..opts
+    };
+    let filters = build_video_filters(&opts);
+    assert!(filters
+        .iter()
+        .any(|f| f.starts_with("sendcmd=c='5.214 colorlevels rimin ")));
+}
+
+#[test]
+fn should_shift_chroma_and_denoise_chroma_only() {
+    let chroma = ChromaCorrection {
+        shift_x: -2,
+        shift_y: -1,
+        denoise_spatial: 6.0,
+        denoise_temporal: 9.0,
+    };
+    assert_eq!(
+        chroma_filters(&chroma),
+        vec![
+            "chromashift=cbh=-2:cbv=-1:crh=-2:crv=-1:edge=smear",
+            "fftdnoiz=sigma=6:planes=6",
+            "atadenoise=1a=0.022:1b=0.045:2a=0.022:2b=0.045:p=6"
+        ]
+    );
+    // Every denoise filter is limited to the chroma planes and leaves the
+    // luma thresholds alone
+    for filter in &chroma_filters(&chroma)[1..] {
+        assert!(
+            filter.ends_with(":planes=6") || filter.ends_with(":p=6"),
+            "{}",
+            filter
+        );
+        assert!(!filter.starts_with("hqdn3d") && !filter.contains("0a="));
+    }
+
+    let opts = VideoEnhanceOptions {
+        chroma: Some(ChromaCorrection {
+            shift_x: 0,
+            shift_y: 0,
+            ..chroma
+        }),
+        ..Default::default()
+    };
+    let filters = build_video_filters(&opts);
+    assert!(!filters.iter().any(|f| f.starts_with("chromashift")));
+    let chroma_dn = filters
+        .iter()
+        .position(|f| f == "fftdnoiz=sigma=6:planes=6");
+    let luma_dn = filters.iter().position(|f| f == "hqdn3d=4:3:6:4.5");
+    assert!(chroma_dn.is_some() && chroma_dn < luma_dn);
+}
+
+#[test]
+fn should_estimate_chroma_delay_from_edges() {
+    let (width, height) = (160, 120);
+    let (cw, ch) = (width / 2, height / 2);
+    let mut frame = vec![0u8; width * height + 2 * cw * ch];
+
+    // Luma: bright block from x=60, y=40 (chroma 30, 20)
+    for y in 0..height {
+        for x in 0..width {
+            frame[y * width + x] = if x >= 60 && y >= 40 { 200 } else { 40 };
+        }
+    }
+    // Chroma: same block, lagging 3 chroma pixels right and 1 line down
+    let (u, v) = frame[width * height..].split_at_mut(cw * ch);
+    for y in 0..ch {
+        for x in 0..cw {
+            let inside = x >= 33 && y >= 21;
+            u[y * cw + x] = if inside { 90 } else { 128 };
+            v[y * cw + x] = if inside { 200 } else { 128 };
+        }
+    }
+
+    assert_eq!(estimate_shift_from_frames(&frame, width, height), (-3, -1));
+}
+
+#[test]
+fn should_report_dropout_ranges_from_signalstats() {
+    let log = "\
+[Parsed_metadata_1 @ 0x1] frame:0    pts:0       pts_time:0
+[Parsed_metadata_1 @ 0x1] lavfi.signalstats.TOUT=0.001
+[Parsed_metadata_1 @ 0x1] lavfi.signalstats.VREP=0
+[Parsed_metadata_1 @ 0x1] frame:1    pts:1001    pts_time:0.0333667
+[Parsed_metadata_1 @ 0x1] lavfi.signalstats.TOUT=0.031
+[Parsed_metadata_1 @ 0x1] frame:2    pts:2002    pts_time:0.0667333
+[Parsed_metadata_1 @ 0x1] lavfi.signalstats.TOUT=0.008
+[Parsed_metadata_1 @ 0x1] frame:3    pts:3003    pts_time:0.1001
+[Parsed_metadata_1 @ 0x1] lavfi.signalstats.TOUT=0.001
+[Parsed_metadata_1 @ 0x1] frame:4    pts:4004    pts_time:0.133467
+[Parsed_metadata_1 @ 0x1] lavfi.signalstats.TOUT=0.002
+[Parsed_metadata_1 @ 0x1] lavfi.signalstats.VREP=0.25
+";
+    let frames = parse_dropout_stats(log);
+    assert_eq!(frames.len(), 5);
+    assert_eq!(frames[4].frame, 4);
+    assert_eq!(frames[4].vrep, 0.25);
+
+    let report = build_report(
+        &frames,
+        &DropoutOptions {
+            merge_gap: 0,
+            ..Default::default()
+        },
+    );
+    assert_eq!(report.total_frames, 5);
+    assert_eq!(report.severe_frames, vec![1]);
+    assert_eq!(report.ranges.len(), 2);
+    assert_eq!(
+        (report.ranges[0].start_frame, report.ranges[0].end_frame),
+        (1, 2)
+    );
+    assert!(report.ranges[0].severe && !report.ranges[1].severe);
+    assert!(report.to_text().contains("00:00:00.033 - 00:00:00.067"));
+}
+
+#[test]
+fn should_replace_damaged_frames_before_deinterlacing() {
+    let repair = DropoutRepair {
+        frames: vec![12, 13, 14, 40],
+        mode: RepairMode::Interpolate,
+        frame_rate: FrameRate::NTSC,
+    };
+    assert_eq!(
+        repair_filters(&repair),
+        vec![
+            "select='not(between(n\\,12\\,14)+eq(n\\,40))'",
+            "minterpolate=fps=30000/1001:mi_mode=mci:mc_mode=aobmc"
+        ]
+    );
+
+    let opts = VideoEnhanceOptions {
+        dropout_repair: Some(DropoutRepair {
+            mode: RepairMode::Neighbour,
+            ..repair
+        }),
+        ..Default::default()
+    };
+    let filters = build_video_filters(&opts);
+    assert_eq!(filters[1], "fps=30000/1001");
+    assert!(filters[2].starts_with("bwdif"));
+
+    assert_eq!(
+        RepairMode::from_name("neighbor"),
+        Some(RepairMode::Neighbour)
+    );
+    assert_eq!(RepairMode::from_name("interp"), None);
+}
+
+#[test]
+fn should_build_encoder_arguments_per_codec() {
+    assert_eq!(
+        VideoEncoder::default().args(),
+        vec!["-c:v", "libx264", "-crf", "18", "-preset", "medium"]
+    );
+
+    let x265 = VideoEncoder {
+        pix_fmt: Some(PixelFormat::Yuv420p10),
+        gop: Some(50),
+        closed_gop: true,
+        profile: Some("main10".to_string()),
+        level: Some("4.1".to_string()),
+        tune: Some("grain".to_string()),
+        ..VideoEncoder::new(VideoCodec::X265)
+    };
+    assert_eq!(
+        x265.args(),
+        vec![
+            "-c:v",
+            "libx265",
+            "-crf",
+            "20",
+            "-preset",
+            "medium",
+            "-tune",
+            "grain",
+            "-pix_fmt",
+            "yuv420p10le",
+            "-g",
+            "50",
+            "-profile:v",
+            "main10",
+            "-x265-params",
+            "open-gop=0:level-idc=4.1"
+        ]
+    );
+
+    let vp9 = VideoEncoder {
+        rate: RateControl::Bitrate("4M".to_string()),
+        ..VideoEncoder::new(VideoCodec::Vp9)
+    };
+    assert_eq!(
+        vp9.args(),
+        vec!["-c:v", "libvpx-vp9", "-b:v", "4M", "-deadline", "good"]
+    );
+    assert_eq!(
+        &VideoEncoder::new(VideoCodec::Aom).args()[2..6],
+        ["-crf", "30", "-b:v", "0"]
+    );
+}
+
+#[test]
+fn should_ignore_rate_and_gop_for_intra_codecs() {
+    let ffv1 = VideoEncoder {
+        gop: Some(50),
+        ..VideoEncoder::new(VideoCodec::Ffv1)
+    };
+    assert_eq!(
+        ffv1.args(),
+        vec![
+            "-c:v",
+            "ffv1",
+            "-g",
+            "1",
+            "-slices",
+            "16",
+            "-slicecrc",
+            "1",
+            "-level",
+            "3"
+        ]
+    );
+
+    assert_eq!(
+        VideoEncoder::new(VideoCodec::ProRes).args(),
+        vec![
+            "-c:v",
+            "prores_ks",
+            "-pix_fmt",
+            "yuv422p10le",
+            "-profile:v",
+            "hq"
+        ]
+    );
+    assert_eq!(
+        VideoEncoder::new(VideoCodec::Dnxhr).args(),
+        vec![
+            "-c:v",
+            "dnxhd",
+            "-pix_fmt",
+            "yuv422p",
+            "-profile:v",
+            "dnxhr_hq"
+        ]
+    );
+    assert_eq!(VideoCodec::from_name("HEVC"), Some(VideoCodec::X265));
+}
+
+#[test]
+fn should_pick_sd_sample_aspect_ratios() {
+    use DisplayAspect::*;
+    use ParConvention::*;
+
+    assert_eq!(sd_sample_aspect(720, 480, Standard, Itu), Some((10, 11)));
+    assert_eq!(sd_sample_aspect(720, 576, Standard, Itu), Some((12, 11)));
+    assert_eq!(sd_sample_aspect(720, 576, Widescreen, Itu), Some((16, 11)));
+    assert_eq!(sd_sample_aspect(720, 480, Widescreen, Mpeg), Some((32, 27)));
+    assert_eq!(sd_sample_aspect(720, 576, Standard, Mpeg), Some((16, 15)));
+    // 704 is the active width under either convention
+    assert_eq!(sd_sample_aspect(704, 480, Standard, Mpeg), Some((10, 11)));
+    assert_eq!(sd_sample_aspect(1920, 1080, Widescreen, Itu), None);
+    assert_eq!(sd_sample_aspect(640, 480, Standard, Itu), None);
+
+    assert_eq!(square_width(704, (10, 11)), 640);
+    assert_eq!(square_width(720, (12, 11)), 786);
+    assert_eq!(square_width(720, (64, 45)), 1024);
+}
+
+#[test]
+fn should_keep_anamorphic_or_resample_to_square_pixels() {
+    let stream = sd_stream(576);
+    let keep = plan_pixel_aspect(&stream, &AspectOptions::default()).unwrap();
+    assert_eq!(keep.filters(), vec!["setsar=12/11"]);
+
+    let square = plan_pixel_aspect(
+        &stream,
+        &AspectOptions {
+            display: Some(DisplayAspect::Widescreen),
+            handling: AspectHandling::SquarePixels,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        square.filters(),
+        vec!["scale=trunc(iw*16/11/2+0.5)*2:ih:flags=lanczos", "setsar=1"]
+    );
+
+    let opts = VideoEnhanceOptions {
+        pixel_aspect: Some(square),
+        scale_width: Some(1440),
+        scale_height: Some(1080),
+        ..Default::default()
+    };
+    let filters = build_video_filters(&opts);
+    let square_at = filters.iter().position(|f| f == "setsar=1").unwrap();
+    assert!(filters[square_at + 1].starts_with("scale=1440:1080"));
+}
+
+#[test]
+fn should_keep_the_display_aspect_the_source_is_flagged_with() {
+    // 16:9 NTSC flagged with the MPEG or ITU widescreen SAR, or only a DAR
+    let mpeg = VideoStreamInfo {
+        sample_aspect: Some((32, 27)),
+        ..sd_stream(480)
+    };
+    let itu = VideoStreamInfo {
+        sample_aspect: Some((40, 33)),
+        ..sd_stream(480)
+    };
+    let dar = VideoStreamInfo {
+        display_aspect: Some((16, 9)),
+        ..sd_stream(576)
+    };
+    assert_eq!(
+        source_display_aspect(&mpeg),
+        Some(DisplayAspect::Widescreen)
+    );
+    assert_eq!(source_display_aspect(&itu), Some(DisplayAspect::Widescreen));
+    assert_eq!(source_display_aspect(&dar), Some(DisplayAspect::Widescreen));
+    assert_eq!(source_display_aspect(&sd_stream(480)), None);
+
+    let defaults = AspectOptions::default();
+    assert_eq!(plan_pixel_aspect(&mpeg, &defaults).unwrap().sar, (40, 33));
+    assert_eq!(plan_pixel_aspect(&dar, &defaults).unwrap().sar, (16, 11));
+    let standard = VideoStreamInfo {
+        sample_aspect: Some((10, 11)),
+        ..sd_stream(480)
+    };
+    assert_eq!(
+        plan_pixel_aspect(&standard, &defaults).unwrap().sar,
+        (10, 11)
+    );
+
+    // An explicit display aspect wins over the flags
+    let forced = AspectOptions {
+        display: Some(DisplayAspect::Standard),
+        ..Default::default()
+    };
+    assert_eq!(plan_pixel_aspect(&mpeg, &forced).unwrap().sar, (10, 11));
+
+    assert_eq!(
+        DisplayAspect::from_name("16:9"),
+        Some(DisplayAspect::Widescreen)
+    );
+    assert_eq!(ParConvention::from_name("MPEG"), Some(ParConvention::Mpeg));
+    assert_eq!(
+        AspectHandling::from_name("square"),
+        Some(AspectHandling::SquarePixels)
+    );
+    assert_eq!(DisplayAspect::from_name("16;9"), None);
+    assert_eq!(ParConvention::from_name("mepg"), None);
+    assert_eq!(AspectHandling::from_name("sqaure"), None);
+}
+
+#[test]
+fn should_parse_rational_frame_rates() {
+    assert_eq!(FrameRate::parse("30000/1001"), Some(FrameRate::NTSC));
+    assert_eq!(FrameRate::parse("29.97"), Some(FrameRate::NTSC));
+    assert_eq!(FrameRate::parse("23.976"), Some(FrameRate::FILM_NTSC));
+    assert_eq!(FrameRate::parse("59.94"), Some(FrameRate::NTSC_FIELDS));
+    assert_eq!(FrameRate::parse("pal"), Some(FrameRate::PAL));
+    assert_eq!(FrameRate::parse("50/2"), Some(FrameRate::PAL));
+    assert_eq!(FrameRate::parse("12.5"), Some(FrameRate::new(25, 2)));
+    assert_eq!(FrameRate::parse("0/0"), None);
+    assert_eq!(FrameRate::parse("fast"), None);
+    assert_eq!(FrameRate::NTSC.to_string(), "30000/1001");
+    assert_eq!(FrameRate::PAL.to_string(), "25");
+}
+
+#[test]
+fn should_convert_frame_rate_by_mode() {
+    let resample = |mode| {
+        RateConversion::Resample {
+            target: FrameRate::PAL_FIELDS,
+            mode,
+        }
+        .video_filters()
+    };
+    assert_eq!(resample(FrameRateMode::DropDuplicate), vec!["fps=50"]);
+    assert_eq!(resample(FrameRateMode::Blend), vec!["framerate=fps=50"]);
+    assert!(resample(FrameRateMode::Interpolate)[0].starts_with("minterpolate=fps=50:mi_mode=mci"));
+    assert_eq!(
+        FrameRateMode::from_name("MCI"),
+        Some(FrameRateMode::Interpolate)
+    );
+    assert_eq!(FrameRateMode::from_name("blended"), None);
+
+    let opts = VideoEnhanceOptions {
+        frame_rate: Some(RateConversion::Resample {
+            target: FrameRate::NTSC,
+            mode: FrameRateMode::DropDuplicate,
+        }),
+        ..Default::default()
+    };
+    let filters = build_video_filters(&opts);
+    let denoise = filters
+        .iter()
+        .position(|f| f.starts_with("hqdn3d"))
+        .unwrap();
+    assert_eq!(filters[denoise + 1], "fps=30000/1001");
+}
+
+#[test]
+fn should_speed_up_ntsc_film_to_pal_with_pitch_corrected_audio() {
+    let conversion = RateConversion::NTSC_TO_PAL;
+    assert!(conversion.changes_speed());
+    assert_eq!(conversion.target(), FrameRate::PAL);
+    // 24000/1001 -> 25: timestamps scale by 24000/25025
+    assert_eq!(
+        conversion.video_filters(),
+        vec!["setpts=PTS*24000/25025", "fps=25"]
+    );
+    assert_eq!(conversion.audio_filters(), vec!["atempo=1.042708"]);
+
+    let back = RateConversion::PAL_TO_NTSC;
+    assert_eq!(back.video_filters()[0], "setpts=PTS*25025/24000");
+    assert_eq!(back.audio_filters(), vec!["atempo=0.959041"]);
+}
+
+#[test]
+fn should_retime_copied_vhs_audio_on_a_speed_change() {
+    // A preset without audio settings copies the audio, unless the speed changes
+    let rescue = VhsRescueOptions {
+        audio: None,
+        ..Default::default()
+    };
+    assert!(rescue_audio_filters(&rescue, None).is_empty());
+    let resample = RateConversion::Resample {
+        target: FrameRate::PAL,
+        mode: FrameRateMode::DropDuplicate,
+    };
+    assert!(rescue_audio_filters(&rescue, Some(&resample)).is_empty());
+    assert_eq!(
+        rescue_audio_filters(&rescue, Some(&RateConversion::PAL_TO_NTSC)),
+        vec!["atempo=0.959041"]
+    );
+
+    // Enhanced audio gets the re-timing after its chain
+    let filters = rescue_audio_filters(
+        &VhsRescueOptions::default(),
+        Some(&RateConversion::PAL_TO_NTSC),
+    );
+    assert!(filters.len() > 1);
+    assert_eq!(filters.last().unwrap(), "atempo=0.959041");
+}
+
+#[test]
+fn should_parse_scale_targets() {
+    assert_eq!(
+        ScaleTarget::parse("1080p"),
+        Some(ScaleTarget {
+            width: 1920,
+            height: 1080
+        })
+    );
+    assert_eq!(ScaleTarget::parse("4K").map(|t| t.width), Some(3840));
+    assert_eq!(
+        ScaleTarget::parse("1440x1080"),
+        Some(ScaleTarget {
+            width: 1440,
+            height: 1080
+        })
+    );
+    assert_eq!(ScaleTarget::parse("719x480"), None);
+    assert_eq!(ScaleTarget::parse("huge"), None);
+    assert_eq!(ScaleFit::from_name("Pad"), Some(ScaleFit::Pad));
+    assert_eq!(ScaleFit::from_name("stretch"), None);
+}
+
+#[test]
+fn should_scale_keeping_aspect_and_pad_to_raster() {
+    let opts = ScaleOptions {
+        target: ScaleTarget::parse("1080p").unwrap(),
+        fit: ScaleFit::Pad,
+        scaler: Scaler::Lanczos,
+    };
+    assert_eq!(
+        opts.filters(),
+        vec![
+            "scale=w='if(gt(dar\\,1920/1080)\\,1920\\,trunc(1080*dar/2)*2)':h='if(gt(dar\\,1920/1080)\\,trunc(1920/dar/2)*2\\,1080)':flags=lanczos+accurate_rnd+full_chroma_int",
+            "setsar=1",
+            "pad=1920:1080:(ow-iw)/2:(oh-ih)/2:black",
+        ]
+    );
+
+    let spline = ScaleOptions {
+        fit: ScaleFit::Fit,
+        scaler: Scaler::Spline36,
+        ..opts
+    };
+    let filters = spline.filters();
+    assert_eq!(filters.len(), 2);
+    assert!(filters[0].starts_with("zscale=w='"));
+    assert!(filters[0].ends_with(":filter=spline36:chromal=left"));
+
+    // Replaces the fixed-size scale
+    let enhance = VideoEnhanceOptions {
+        scale: Some(opts),
+        scale_width: Some(640),
+        scale_height: Some(480),
+        ..Default::default()
+    };
+    let filters = build_video_filters(&enhance);
+    assert!(!filters.iter().any(|f| f.starts_with("scale=640")));
+    assert_eq!(
+        filters.last().unwrap(),
+        "pad=1920:1080:(ow-iw)/2:(oh-ih)/2:black"
+    );
+}
+
+#[test]
+fn should_renumber_dropout_frames_after_seeking() {
+    let repair = DropoutRepair {
+        frames: vec![10, 30, 260, 300],
+        mode: RepairMode::Neighbour,
+        frame_rate: FrameRate::PAL,
+    };
+    // 10 s at 25 fps skips 250 frames
+    assert_eq!(repair.after_seek(10.0).frames, vec![10, 50]);
+}
+
+#[test]
+fn should_report_dropouts_of_a_trimmed_scan_in_source_frames() {
+    let log = "\
+[Parsed_metadata_1 @ 0x1] frame:12   pts:12      pts_time:0.48
+[Parsed_metadata_1 @ 0x1] lavfi.signalstats.TOUT=0.031
+";
+    let mut frames = parse_dropout_stats(log);
+    before_seek(&mut frames, 10.0, FrameRate::PAL);
+    assert_eq!(frames[0].frame, 262);
+    assert!((frames[0].time - 10.48).abs() < 1e-9);
+}
+
+#[test]
+fn should_plan_scenes_with_minimum_length() {
+    let log = "\
+[Parsed_metadata_1 @ 0x55] frame:0    pts:4004  pts_time:12.012
+[Parsed_metadata_1 @ 0x55] lavfi.scene_score=0.512000
+[Parsed_metadata_1 @ 0x55] frame:1    pts:4204  pts_time:14.5
+[Parsed_metadata_1 @ 0x55] lavfi.scene_score=0.410000
+[Parsed_metadata_1 @ 0x55] frame:2    pts:9000  pts_time:30
+[Parsed_metadata_1 @ 0x55] lavfi.scene_score=0.880000
+[Parsed_metadata_1 @ 0x55] frame:3    pts:9900  pts_time:58
+[Parsed_metadata_1 @ 0x55] lavfi.scene_score=0.350000
+";
+    let cuts = parse_scene_cuts(log);
+    assert_eq!(cuts, vec![12.012, 14.5, 30.0, 58.0]);
+
+    // 14.5 is too close to 12.012; the 2 s tail after 58 joins the last scene
+    let scenes = plan_scenes(&cuts, 60.0, 5.0).unwrap();
+    let bounds: Vec<(f64, f64)> = scenes.iter().map(|s| (s.start, s.end)).collect();
+    assert_eq!(bounds, vec![(0.0, 12.012), (12.012, 30.0), (30.0, 60.0)]);
+
+    // No cuts: one scene covering the file
+    assert_eq!(plan_scenes(&[], 60.0, 5.0).unwrap().len(), 1);
+    // Unknown duration: no end for the last scene
+    assert!(plan_scenes(&cuts, 0.0, 5.0).is_err());
+}
+
+#[test]
+fn should_write_chapter_lists() {
+    let scenes = plan_scenes(&[90.5], 200.0, 5.0).unwrap();
+
+    assert_eq!(
+        chapter_list(&scenes, ChapterFormat::Text),
+        "00:00:00.000 Scene 1\n00:01:30.500 Scene 2\n"
+    );
+    assert_eq!(
+        chapter_list(&scenes, ChapterFormat::Ogm),
+        "CHAPTER01=00:00:00.000\nCHAPTER01NAME=Scene 1\n\
+         CHAPTER02=00:01:30.500\nCHAPTER02NAME=Scene 2\n"
+    );
+    let meta = chapter_list(&scenes, ChapterFormat::FfMetadata);
+    assert!(meta.starts_with(";FFMETADATA1\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=90500\n"));
+    assert!(meta.ends_with("START=90500\nEND=200000\ntitle=Scene 2\n"));
+
+    assert_eq!(
+        ChapterFormat::from_name("FFmetadata"),
+        Some(ChapterFormat::FfMetadata)
+    );
+    assert_eq!(
+        scene_file_name(&PathBuf::from("/tapes/xmas 1994.mkv"), 0),
+        "xmas 1994_scene_001.mkv"
+    );
+}
+
+#[test]
+fn should_build_contact_sheet_filters() {
+    let opts = ThumbnailOptions {
+        columns: 4,
+        rows: 3,
+        width: 320,
+        ..Default::default()
+    };
+    // 12 tiles over 10 minutes: one every 50 s, starting 25 s in
+    assert_eq!(
+        contact_sheet_filters(&opts, 600.0),
+        vec![
+            "fps=1/50.000:start_time=25.000",
+            "scale=320:trunc(ow/dar/2)*2:flags=lanczos,setsar=1",
+            "drawtext=text='%{pts\\:hms}':x=6:y=h-th-6:fontsize=20:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=3",
+            "tile=4x3:padding=4:margin=4",
+        ]
+    );
+
+    let plain = ThumbnailOptions {
+        timestamps: false,
+        ..opts
+    };
+    assert!(!contact_sheet_filters(&plain, 600.0)
+        .iter()
+        .any(|f| f.starts_with("drawtext")));
+
+    // A clip shorter than the grid still gets a valid frame rate
+    assert_eq!(
+        contact_sheet_filters(&plain, 0.003)[0],
+        "fps=1/0.040:start_time=0.020"
+    );
+
+    assert_eq!(parse_grid("5X4"), Some((5, 4)));
+    assert_eq!(parse_grid("0x4"), None);
+    assert_eq!(parse_grid("4"), None);
+    assert_eq!(
+        thumbnail_file_name(&PathBuf::from("tape.avi"), 11, ImageFormat::Webp),
+        "tape_scene_012.webp"
+    );
+}
+
+#[test]
+fn should_build_comparison_graph() {
+    let stream = VideoStreamInfo {
+        frame_rate: Some(FrameRate::NTSC),
+        ..sd_stream(480)
+    };
+    let opts = VideoEnhanceOptions {
+        deinterlace: false,
+        sharpen: None,
+        color_adjust: false,
+        ..Default::default()
+    };
+    // 720x480 at 10:11 is 654 square pixels wide
+    assert_eq!(pane_size(&stream, &opts, 480), (654, 480));
+
+    let compare = CompareOptions {
+        labels: false,
+        ..Default::default()
+    };
+    let graph = compare_filtergraph(&opts, &compare, &stream);
+    let parts: Vec<&str> = graph.split(';').collect();
+    assert_eq!(parts[0], "[0:v]split=2[s0][e0]");
+    assert_eq!(
+        parts[1],
+        "[s0]fps=30000/1001,scale=654:480:force_original_aspect_ratio=decrease:flags=bicubic,\
+         pad=654:480:(ow-iw)/2:(oh-ih)/2:black,setsar=1[src]"
+    );
+    assert!(parts[2].starts_with("[e0]hqdn3d="));
+    assert!(parts[2].ends_with(",setsar=1[enh]"));
+    assert_eq!(parts[3], "[src][enh]hstack=inputs=2[v]");
+
+    // Wipe: left of the source, right of the enhanced picture, speed change dropped
+    let wipe = CompareOptions {
+        layout: CompareLayout::Wipe,
+        ..Default::default()
+    };
+    let speed = VideoEnhanceOptions {
+        frame_rate: Some(RateConversion::NTSC_TO_PAL),
+        ..opts
+    };
+    let graph = compare_filtergraph(&speed, &wipe, &stream);
+    assert!(!graph.contains("atempo") && !graph.contains("setpts"));
+    assert!(graph.contains("drawtext=text='Source':x=10:"));
+    assert!(graph.contains("drawtext=text='Enhanced':x=w-tw-10:"));
+    assert!(graph.contains(",crop=326:480:0:0[src]"));
+    assert!(graph.contains(",crop=328:480:326:0[enh]"));
+    assert!(graph.ends_with(
+        "[src][enh]hstack=inputs=2,drawbox=x=325:y=0:w=2:h=480:color=white@0.8:t=fill[v]"
+    ));
+}
+
+#[test]
+fn should_parse_quality_metric_logs() {
+    let psnr = parse_psnr_stats(
+        "n:1 mse_avg:0.00 mse_y:0.00 mse_u:0.00 mse_v:0.00 psnr_avg:inf psnr_y:inf psnr_u:inf psnr_v:inf\n\
+         n:2 mse_avg:1.63 mse_y:2.10 mse_u:0.70 mse_v:0.69 psnr_avg:46.01 psnr_y:44.91 psnr_u:49.68 psnr_v:49.75\n",
+    );
+    assert_eq!(psnr, vec![(0, 100.0), (1, 46.01)]);
+
+    let ssim = parse_ssim_stats(
+        "n:1 Y:0.991 U:0.994 V:0.993 All:0.992 (20.969)\nn:2 Y:0.981 U:0.990 V:0.989 All:0.984 (17.958)\n",
+    );
+    assert_eq!(ssim, vec![(0, 0.992), (1, 0.984)]);
+
+    let vmaf = parse_vmaf_log(
+        r#"{"version":"2.3.1","frames":[{"frameNum":0,"metrics":{"integer_motion":0.0,"vmaf":97.5}},
+            {"frameNum":1,"metrics":{"integer_motion":1.2,"vmaf":93.5}}],
+            "pooled_metrics":{"vmaf":{"min":93.5,"max":97.5,"mean":95.5}}}"#,
+    )
+    .unwrap();
+    assert_eq!(vmaf, vec![(0, 97.5), (1, 93.5)]);
+
+    let report = MetricsReport::from_frames(&psnr, &ssim, &[]);
+    assert_eq!(report.frames.len(), 2);
+    let summary = report.psnr.unwrap();
+    assert_eq!(
+        (summary.mean, summary.min, summary.max),
+        (73.005, 46.01, 100.0)
+    );
+    assert!(report.vmaf.is_none());
+    assert_eq!(
+        report.to_csv(),
+        "frame,psnr,ssim,vmaf\n0,100.000000,0.992000,\n1,46.010000,0.984000,\n"
+    );
+    let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+    assert_eq!(json["frames"][1]["ssim"], 0.984);
+    assert!(json["vmaf"].is_null());
+}
+
+#[test]
+fn should_align_inputs_for_metrics() {
+    let reference = VideoStreamInfo {
+        width: 1920,
+        height: 1080,
+        frame_rate: Some(FrameRate::PAL),
+        ..sd_stream(576)
+    };
+    let psnr_log = PathBuf::from("/tmp/m/psnr.log");
+    let ssim_log = PathBuf::from("/tmp/m/ssim.log");
+
+    let graph = metrics_filtergraph(
+        &reference,
+        &[
+            (Metric::Psnr, psnr_log.as_path()),
+            (Metric::Ssim, ssim_log.as_path()),
+        ],
+    );
+    assert_eq!(
+        graph.split(';').collect::<Vec<_>>(),
+        vec![
+            "[0:v]setpts=PTS-STARTPTS,fps=25,scale=1920:1080:flags=bicubic,format=yuv420p10le,setsar=1[dist]",
+            "[1:v]setpts=PTS-STARTPTS,fps=25,format=yuv420p10le,setsar=1[ref]",
+            "[dist]split=2[d0][d1]",
+            "[ref]split=2[r0][r1]",
+            "[d0][r0]psnr=stats_file=/tmp/m/psnr.log[m0]",
+            "[d1][r1]ssim=stats_file=/tmp/m/ssim.log[m1]",
+        ]
+    );
+
+    let vmaf_log = PathBuf::from("/tmp/m/vmaf.log");
+    let graph = metrics_filtergraph(&reference, &[(Metric::Vmaf, vmaf_log.as_path())]);
+    assert!(graph.ends_with(";[dist][ref]libvmaf=log_fmt=json:log_path=/tmp/m/vmaf.log[m0]"));
+
+    // A log path with graph separators cannot break the graph apart
+    let odd_log = PathBuf::from("/tapes/a;b [1]/psnr.log");
+    assert_eq!(
+        Metric::Psnr.filter(&odd_log),
+        "psnr=stats_file=/tapes/a\\;b \\[1\\]/psnr.log"
+    );
+}
+
+fn text_codes(time: f64, text: &str) -> Vec<(f64, u8, u8)> {
+    text.as_bytes()
+        .chunks(2)
+        .map(|pair| (time, pair[0], *pair.get(1).unwrap_or(&0)))
+        .collect()
+}
+
+#[test]
+fn should_decode_pop_on_and_roll_up_captions() {
+    let mut codes = vec![
+        (1.0, 0x14, 0x2e), // Erase non-displayed memory
+        (1.0, 0x14, 0x2e), // Doubled control code
+        (1.0, 0x14, 0x20), // Resume caption loading
+        (1.0, 0x14, 0x70), // Row 15
+    ];
+    codes.extend(text_codes(1.1, "HELLO"));
+    codes.push((1.2, 0x14, 0x50)); // Next row
+    codes.extend(text_codes(1.2, "CAFe"));
+    codes.push((1.2, 0x12, 0x21)); // É replaces the fallback e
+    codes.push((1.2, 0x11, 0x37)); // ♪
+    codes.push((2.0, 0x94, 0xaf)); // End of caption, with parity bits
+    codes.push((4.0, 0x14, 0x2c)); // Erase displayed memory
+
+    codes.push((5.0, 0x14, 0x25)); // Roll-up, 2 rows
+    codes.extend(text_codes(5.1, "ONE"));
+    codes.push((6.0, 0x14, 0x2d)); // Carriage return
+    codes.extend(text_codes(6.5, "TWO"));
+    codes.push((7.0, 0x14, 0x2d));
+    codes.extend(text_codes(7.5, "ON CC2?"));
+    codes.push((7.6, 0x1c, 0x2c)); // CC2 erase: ignored on CC1
+    codes.push((8.0, 0x14, 0x2c));
+
+    let captions = decode_captions(&codes, 1, 60.0);
+    let cues: Vec<(f64, f64, &str)> = captions
+        .iter()
+        .map(|c| (c.start, c.end, c.text.as_str()))
+        .collect();
+    assert_eq!(
+        cues,
+        vec![
+            (2.0, 4.0, "HELLO\nCAFÉ♪"),
+            (6.0, 7.0, "ONE"),
+            (7.0, 8.0, "TWO"),
+        ]
+    );
+}
+
+#[test]
+fn should_write_and_retime_subtitles() {
+    let log = "\
+[Parsed_metadata_1 @ 0x5] frame:30   pts:30030   pts_time:1.001
+[Parsed_metadata_1 @ 0x5] lavfi.readeia608.0.cc=0x942C
+[Parsed_metadata_1 @ 0x5] lavfi.readeia608.0.line=21
+[Parsed_metadata_1 @ 0x5] frame:31   pts:31031   pts_time:1.03437
+[Parsed_metadata_1 @ 0x5] frame:32   pts:32032   pts_time:1.06773
+[Parsed_metadata_1 @ 0x5] lavfi.readeia608.0.cc=0xC180
+";
+    assert_eq!(
+        parse_readeia608_output(log),
+        vec![(1.001, 0x94, 0x2c), (1.06773, 0xc1, 0x80)]
+    );
+
+    let captions = vec![
+        Caption {
+            start: 5.0,
+            end: 12.5,
+            text: "FIRST".to_string(),
+        },
+        Caption {
+            start: 3723.25,
+            end: 3725.0,
+            text: "SECOND\nLINE".to_string(),
+        },
+    ];
+    assert_eq!(
+        subtitle_file(&captions, SubtitleFormat::Srt),
+        "1\n00:00:05,000 --> 00:00:12,500\nFIRST\n\n2\n01:02:03,250 --> 01:02:05,000\nSECOND\nLINE\n\n"
+    );
+    assert_eq!(
+        subtitle_file(&captions[..1], SubtitleFormat::WebVtt),
+        "WEBVTT\n\n00:00:05.000 --> 00:00:12.500\nFIRST\n\n"
+    );
+
+    // Trimmed to 10 s - 1 h, captions move with the start and are clipped
+    let trim = Trim {
+        start: Some(10.0),
+        end: Some(3600.0),
+    };
+    let retimed = retime_captions(&captions, &trim, None);
+    assert_eq!(retimed.len(), 1);
+    assert_eq!((retimed[0].start, retimed[0].end), (0.0, 2.5));
+
+    // PAL to NTSC film speed slows the timeline down by 25/23.976
+    let slowed = retime_captions(
+        &captions,
+        &Trim::default(),
+        Some(&RateConversion::PAL_TO_NTSC),
+    );
+    assert!((slowed[0].start - 5.0 * 25.0 * 1001.0 / 24000.0).abs() < 1e-9);
+}
+
+#[test]
+fn should_count_timecodes_with_drop_frame() {
+    let tc = Timecode::parse("01:00:00:00").unwrap();
+    assert!(!tc.drop_frame);
+    assert_eq!(tc.to_frames(25), 90_000);
+    assert_eq!(Timecode::from_frames(90_000, 25, false), tc);
+    assert_eq!(Timecode::parse("25:00:00:00").map(|t| t.hours), Some(25));
+    assert_eq!(Timecode::parse("00:61:00:00"), None);
+    assert_eq!(Timecode::parse("garbage"), None);
+
+    // 29.97 drop-frame skips frames 00 and 01 at each minute but every tenth
+    let df = Timecode::parse("00:00:59;29").unwrap();
+    assert!(df.drop_frame);
+    assert_eq!(df.offset(1, 30).to_string(), "00:01:00;02");
+    assert_eq!(
+        Timecode::parse("00:09:59;29")
+            .unwrap()
+            .offset(1, 30)
+            .to_string(),
+        "00:10:00;00"
+    );
+    assert_eq!(
+        Timecode::parse("00:10:00;00").unwrap().to_frames(30),
+        17_982
+    );
+    assert_eq!(
+        Timecode::from_frames(17_982 * 6, 30, true).to_string(),
+        "01:00:00;00"
+    );
+
+    // Going back wraps around midnight
+    assert_eq!(
+        Timecode::parse("00:00:00:00")
+            .unwrap()
+            .offset(-1, 25)
+            .to_string(),
+        "23:59:59:24"
+    );
+    // 10 s at 25 fps is 10 s at 30 fps
+    assert_eq!(
+        Timecode::parse("00:00:10:00")
+            .unwrap()
+            .at_rate(25, 30)
+            .to_string(),
+        "00:00:10:00"
+    );
+}
+
+#[test]
+fn should_report_vitc_discontinuities() {
+    assert_eq!(
+        readvitc_filters(None),
+        vec!["readvitc", "metadata=mode=print:key=lavfi.readvitc.tc_str"]
+    );
+    assert_eq!(
+        readvitc_filters(Some(&PathBuf::from("/tmp/vitc.log")))[1],
+        "metadata=mode=print:key=lavfi.readvitc.tc_str:file=/tmp/vitc.log"
+    );
+    // Separators in the path are escaped for the option and graph parsers
+    assert_eq!(
+        readvitc_filters(Some(&PathBuf::from("/tapes/tape 3, side A:B.log")))[1],
+        "metadata=mode=print:key=lavfi.readvitc.tc_str:file=/tapes/tape 3\\, side A\\\\:B.log"
+    );
+
+    // Frame 0 had no readable VITC, frame 4 jumps back (a rewind and re-record)
+    let log = "\
+[Parsed_metadata_1 @ 0x1] frame:1    pts:1    pts_time:0.04
+[Parsed_metadata_1 @ 0x1] lavfi.readvitc.tc_str=10:00:00:01
+[Parsed_metadata_1 @ 0x1] frame:2    pts:2    pts_time:0.08
+[Parsed_metadata_1 @ 0x1] lavfi.readvitc.tc_str=10:00:00:02
+[Parsed_metadata_1 @ 0x1] frame:3    pts:3    pts_time:0.12
+[Parsed_metadata_1 @ 0x1] lavfi.readvitc.tc_str=10:00:00:03
+[Parsed_metadata_1 @ 0x1] frame:5    pts:5    pts_time:0.2
+[Parsed_metadata_1 @ 0x1] lavfi.readvitc.tc_str=09:59:58:00
+";
+    let frames = parse_readvitc_output(log);
+    assert_eq!(frames.len(), 4);
+    assert_eq!(frames[0].frame, 1);
+    assert_eq!(frames[3].time, 0.2);
+
+    let report = build_vitc_report(&frames, FrameRate::PAL);
+    assert_eq!(report.fps, 25);
+    assert_eq!(report.frames_read, 4);
+    assert_eq!(report.frames_missing, 1);
+    assert_eq!(report.start.unwrap().to_string(), "10:00:00:00");
+    assert_eq!(report.jumps.len(), 1);
+    assert_eq!(report.jumps[0].frame, 5);
+    assert_eq!(report.jumps[0].expected.to_string(), "10:00:00:05");
+    assert_eq!(report.jumps[0].found.to_string(), "09:59:58:00");
+    assert_eq!(
+        report.timecode_at(2.0, FrameRate::PAL).unwrap().to_string(),
+        "10:00:02:00"
+    );
+    assert!(report.to_text().contains("1 discontinuities"));
+    assert_eq!(log_frame_rate(&frames), Some(FrameRate::PAL));
+
+    let empty = build_vitc_report(&[], FrameRate::NTSC);
+    assert_eq!(empty.start, None);
+    assert_eq!(empty.to_text(), "VITC: no timecode found\n");
+    assert_eq!(log_frame_rate(&[]), None);
+}
+
+#[test]
+fn should_take_the_vitc_log_rate_from_the_source_frames() {
+    // An NTSC capture deinterlaced to 59.94 fps still logs the 29.97 fps
+    // source frames
+    let log = "\
+[Parsed_metadata_1 @ 0x1] frame:0    pts:0    pts_time:0
+[Parsed_metadata_1 @ 0x1] lavfi.readvitc.tc_str=01:00:00;00
+[Parsed_metadata_1 @ 0x1] frame:300  pts:300300 pts_time:10.01
+[Parsed_metadata_1 @ 0x1] lavfi.readvitc.tc_str=01:00:10;00
+";
+    let frames = parse_readvitc_output(log);
+    let rate = log_frame_rate(&frames).unwrap();
+    assert_eq!(rate, FrameRate::NTSC);
+    assert!(build_vitc_report(&frames, rate).jumps.is_empty());
+}
+
+#[test]
+fn should_recover_film_cadence() {
+    let super8 = FrameRate::new(18, 1);
+
+    // 3:2 pulldown from NTSC: standard inverse telecine
+    assert_eq!(
+        cadence_filters(FilmSource::Telecine, FrameRate::FILM, Some(FrameRate::NTSC)),
+        vec![
+            "fieldmatch=order=auto:combmatch=full",
+            "yadif=deint=interlaced",
+            "decimate"
+        ]
+    );
+
+    // 18 fps on NTSC runs 1000/1001 slow; repeats are dropped, then the film grid kept
+    assert_eq!(
+        film_rate(super8, Some(FrameRate::NTSC)),
+        FrameRate::new(18000, 1001)
+    );
+    assert_eq!(film_rate(super8, Some(FrameRate::PAL)), super8);
+    let filters = cadence_filters(FilmSource::Telecine, super8, Some(FrameRate::NTSC));
+    assert_eq!(&filters[2..], ["mpdecimate", "fps=18000/1001"]);
+
+    // 24 fps on PAL runs at 25 with 2:2 pulldown; no frame is a repeat
+    assert_eq!(
+        film_rate(FrameRate::FILM, Some(FrameRate::PAL)),
+        FrameRate::FILM
+    );
+    assert_eq!(
+        cadence_filters(FilmSource::Telecine, FrameRate::FILM, Some(FrameRate::PAL)),
+        vec![
+            "fieldmatch=order=auto:combmatch=full",
+            "yadif=deint=interlaced"
+        ]
+    );
+
+    // Scans keep every frame and are played at film speed
+    assert_eq!(
+        cadence_filters(
+            FilmSource::Scan,
+            FrameRate::new(16, 1),
+            Some(FrameRate::NTSC)
+        ),
+        vec!["setpts=N*1/16/TB", "fps=16"]
+    );
+}
+
+#[test]
+fn should_build_film_cleanup_filters() {
+    assert_eq!(FilmSource::from_name("scan"), Some(FilmSource::Scan));
+    assert_eq!(DustRemoval::from_name("strong"), Some(DustRemoval::Strong));
+    assert_eq!(DustRemoval::from_name("heavy"), None);
+
+    let all = cleanup_filters(DustRemoval::Light, true, true, true);
+    assert_eq!(
+        all,
+        vec![
+            "removegrain=m0=2:m1=2:m2=2",
+            "deflicker=size=5:mode=pm",
+            "deshake=rx=16:ry=16:edge=mirror",
+            "hqdn3d=1:3:2:4.5"
+        ]
+    );
+
+    let dust_only = cleanup_filters(DustRemoval::Strong, false, false, false);
+    assert_eq!(
+        dust_only,
+        vec!["removegrain=m0=17:m1=17:m2=17", "tmedian=radius=1"]
+    );
+    assert!(cleanup_filters(DustRemoval::Off, false, false, false).is_empty());
+}
+
+#[test]
+fn should_build_sharpening_filters() {
+    // Defaults match the previous fixed unsharp mask
+    let unsharp = SharpenOptions::default();
+    assert_eq!(unsharp.filters(), vec!["unsharp=5:5:0.8:3:3:0.4"]);
+
+    let custom = SharpenOptions {
+        luma_amount: 0.5,
+        luma_radius: 3,
+        chroma_amount: 0.0,
+        chroma_radius: 20,
+        ..Default::default()
+    };
+    assert_eq!(custom.filters(), vec!["unsharp=7:7:0.5:23:23:0"]);
+
+    let cas = SharpenOptions::new(SharpenMethod::Cas);
+    assert_eq!(cas.filters(), vec!["cas=strength=0.5:planes=1"]);
+    let cas_both = SharpenOptions {
+        luma_amount: 0.7,
+        chroma_amount: 0.3,
+        ..cas
+    };
+    assert_eq!(
+        cas_both.filters(),
+        vec!["cas=strength=0.7:planes=1", "cas=strength=0.3:planes=6"]
+    );
+    assert_eq!(SharpenMethod::from_name("CAS"), Some(SharpenMethod::Cas));
+    assert_eq!(SharpenMethod::from_name("smart"), None);
+}
+
+#[test]
+fn should_skip_sharpening_in_noisy_regions() {
+    let protected = SharpenOptions {
+        protect_noise: true,
+        ..Default::default()
+    };
+    let graph = noise_protected_filters(Some("hqdn3d=4:3:6:4.5"), &protected);
+    assert!(graph.starts_with(
+        "split=2[shp_raw][shp_in];[shp_in]hqdn3d=4:3:6:4.5,split=3[shp_dn][shp_plain][shp_ref];"
+    ));
+    assert!(graph.contains("[shp_raw][shp_ref]blend=all_mode=difference,"));
+    assert!(graph.contains("[shp_dn]unsharp=5:5:0.8:3:3:0.4[shp_sharp]"));
+    assert!(graph.ends_with("[shp_sharp][shp_plain][shp_mask]maskedmerge"));
+
+    // Without a denoise step a probe denoise only builds the mask
+    let probe = noise_protected_filters(None, &protected);
+    assert!(probe.starts_with("split=4[shp_raw][shp_probe][shp_dn][shp_plain];[shp_probe]hqdn3d"));
+
+    // In the enhance chain it replaces the denoise, and no unsharp follows later
+    let opts = VideoEnhanceOptions {
+        sharpen: Some(protected),
+        ..Default::default()
+    };
+    let filters = build_video_filters(&opts);
+    assert!(filters.iter().any(|f| f.starts_with("split=2[shp_raw]")));
+    assert!(!filters
+        .iter()
+        .any(|f| f.starts_with("hqdn3d") || f.starts_with("unsharp")));
+
+    let unsharpened = VideoEnhanceOptions {
+        sharpen: None,
+        ..Default::default()
+    };
+    assert!(!build_video_filters(&unsharpened)
+        .iter()
+        .any(|f| f.contains("unsharp")));
+}
+
+#[test]
+fn should_process_at_high_bit_depth() {
+    let ten = ProcessingDepth::from_bits(10).unwrap();
+    let sixteen = ProcessingDepth::from_bits(16).unwrap();
+    assert_eq!(ProcessingDepth::from_bits(12), None);
+    assert_eq!(ten.input_filters(), vec!["format=yuv422p10le"]);
+
+    // x265/ProRes keep 10-bit 4:2:2, FFV1 keeps the processing depth
+    let x265 = VideoEncoder::new(VideoCodec::X265);
+    let ffv1 = VideoEncoder::new(VideoCodec::Ffv1);
+    let prores = VideoEncoder::new(VideoCodec::ProRes);
+    assert_eq!(ten.output_format(&x265), PixelFormat::Yuv422p10);
+    assert_eq!(ten.output_format(&prores), PixelFormat::Yuv422p10);
+    assert_eq!(sixteen.output_format(&ffv1), PixelFormat::Yuv422p16);
+    assert_eq!(
+        ten.output_filters(PixelFormat::Yuv422p10),
+        vec!["format=yuv422p10le"]
+    );
+    assert_eq!(
+        ten.output_encoder(&x265).args()[..4],
+        ["-c:v", "libx265", "-crf", "20"]
+    );
+    assert!(ten
+        .output_encoder(&x265)
+        .args()
+        .windows(2)
+        .any(|w| w == ["-pix_fmt", "yuv422p10le"]));
+
+    // 8-bit outputs are dithered down; an explicit pix_fmt wins
+    let x264 = VideoEncoder::default();
+    assert_eq!(ten.output_format(&x264), PixelFormat::Yuv420p);
+    assert_eq!(
+        sixteen.output_filters(PixelFormat::Yuv420p),
+        vec!["scale=sws_dither=ed", "format=yuv420p"]
+    );
+    let x264_10 = VideoEncoder {
+        pix_fmt: Some(PixelFormat::Yuv420p10),
+        ..VideoEncoder::default()
+    };
+    assert_eq!(ten.output_format(&x264_10), PixelFormat::Yuv420p10);
+    assert_eq!(
+        sixteen.output_filters(PixelFormat::Yuv420p10),
+        vec!["scale=sws_dither=ed", "format=yuv420p10le"]
+    );
+
+    // The whole chain runs between the upconversion and the output conversion
+    let opts = VideoEnhanceOptions {
+        depth: Some(ten),
+        encoder: x264,
+        ..Default::default()
+    };
+    let filters = build_video_filters(&opts);
+    assert_eq!(filters[0], "format=yuv422p10le");
+    assert_eq!(
+        filters[filters.len() - 2..],
+        ["scale=sws_dither=ed", "format=yuv420p"]
+    );
+}
+
+#[test]
+fn should_keep_high_bit_depth_chains_free_of_8_bit_filters() {
+    let ten = ProcessingDepth::Bits10;
+    // The default colour boost uses filters that keep the depth
+    let opts = VideoEnhanceOptions {
+        depth: Some(ten),
+        ..Default::default()
+    };
+    let filters = build_video_filters(&opts);
+    assert!(ten.check_filters(&filters).is_ok());
+    let names = filter_names(&filters);
+    assert!(!names.contains(&"eq"));
+    assert!(names.contains(&"lutyuv"));
+    assert!(names.contains(&"colorcorrect"));
+    assert!(filter_names(&build_video_filters(&VideoEnhanceOptions::default())).contains(&"eq"));
+
+    // Filters without a high bit depth equivalent are refused
+    for opts in [
+        VideoEnhanceOptions {
+            stabilize: true,
+            ..opts.clone()
+        },
+        VideoEnhanceOptions {
+            denoise: DenoiseType::Nlmeans,
+            ..opts.clone()
+        },
+    ] {
+        assert!(ten.check_filters(&build_video_filters(&opts)).is_err());
+    }
+    let film = cleanup_filters(DustRemoval::Light, true, false, true);
+    let err = ten.check_filters(&film).unwrap_err().to_string();
+    assert!(err.contains("removegrain"), "{}", err);
+    assert!(ten
+        .check_filters(&cleanup_filters(DustRemoval::Off, true, false, true))
+        .is_ok());
+
+    // Pad labels and options are not filter names
+    assert_eq!(
+        filter_names(&["[0:v]split=2[a][b];[a]eq=gamma=1.2[c]".to_string()]),
+        vec!["split", "eq"]
+    );
+}
+
+#[test]
+fn should_convert_colour_with_zscale_at_16_bits() {
+    let conversion = plan_conversion(&sd_stream(480), 1080);
+    let ten = ProcessingDepth::Bits10;
+    let sixteen = ProcessingDepth::Bits16;
+    assert_eq!(conversion.filter_at(Some(ten)), conversion.filter());
+    assert_eq!(conversion.filter_at(None), conversion.filter());
+    // colorspace does not take 16-bit input
+    assert_eq!(
+        conversion.filter_at(Some(sixteen)).as_deref(),
+        Some(
+            "zscale=matrixin=smpte170m:primariesin=smpte170m:transferin=601:rangein=limited:\
+             matrix=bt709:primaries=bt709:transfer=bt709:range=limited"
+        )
+    );
+    assert_eq!(
+        plan_conversion(&sd_stream(480), 480).filter_at(Some(sixteen)),
+        None
+    );
+
+    let opts = VideoEnhanceOptions {
+        depth: Some(sixteen),
+        color_conversion: Some(conversion),
+        ..Default::default()
+    };
+    let filters = build_video_filters(&opts);
+    assert!(filters.iter().any(|f| f.starts_with("zscale=matrixin=")));
+    assert!(!filters.iter().any(|f| f.starts_with("colorspace=")));
+}
+
+#[test]
+fn should_restore_video_and_audio_in_one_pass() {
+    let opts = RestoreOptions {
+        video: Some(VideoEnhanceOptions {
+            sharpen: None,
+            ..Default::default()
+        }),
+        audio: Some(AudioEnhanceOptions {
+            notch_freq: Some(50),
+            ..Default::default()
+        }),
+        video_stream: 1,
+        audio_stream: Some(2),
+        trim: Trim {
+            start: Some(5.0),
+            end: None,
+        },
+        ..Default::default()
+    };
+    let graph = restore_filtergraph(&opts).unwrap();
+    let (video, audio) = graph.split_once(';').unwrap();
+    assert!(video.starts_with("[0:v:1]bwdif="));
+    assert!(video.ends_with("eq=brightness=0.02:saturation=1.1[v]"));
+    assert!(audio.starts_with("[0:a:2]highpass=f=80,"));
+    assert!(audio.contains("equalizer=f=50"));
+    assert!(audio.ends_with("[a]"));
+
+    let args = restore_args(&PathBuf::from("in.avi"), &PathBuf::from("out.mkv"), &opts);
+    assert_eq!(args[..4], ["-ss", "5.000", "-i", "in.avi"]);
+    assert!(args.windows(2).any(|w| w == ["-map", "[v]"]));
+    assert!(args.windows(2).any(|w| w == ["-c:v", "libx264"]));
+    assert!(args.windows(4).any(|w| w == ["-map", "[a]", "-c:a", "aac"]));
+    assert_eq!(args.last().unwrap(), "out.mkv");
+
+    // Copied streams are mapped by index; a speed change still re-times the audio
+    let copy = RestoreOptions {
+        video: None,
+        audio: None,
+        ..Default::default()
+    };
+    assert_eq!(restore_filtergraph(&copy), None);
+    let args = restore_args(&PathBuf::from("in.avi"), &PathBuf::from("out.mkv"), &copy);
+    assert!(args
+        .windows(4)
+        .any(|w| w == ["-map", "0:v:0", "-c:v", "copy"]));
+    assert!(args
+        .windows(4)
+        .any(|w| w == ["-map", "0:a:0?", "-c:a", "copy"]));
+
+    let sped_up = RestoreOptions {
+        video: Some(VideoEnhanceOptions {
+            frame_rate: Some(RateConversion::SpeedChange {
+                source: FrameRate::FILM,
+                target: FrameRate::PAL,
+            }),
+            ..Default::default()
+        }),
+        audio: None,
+        ..Default::default()
+    };
+    assert!(restore_filtergraph(&sped_up)
+        .unwrap()
+        .contains("[0:a:0]atempo="));
+
+    let silent = RestoreOptions {
+        audio_stream: None,
+        ..Default::default()
+    };
+    assert!(!restore_filtergraph(&silent).unwrap().contains("[0:a"));
+    assert!(
+        restore_args(&PathBuf::from("in.avi"), &PathBuf::from("out.mkv"), &silent)
+            .contains(&"-an".to_string())
+    );
+}
+
+#[test]
+fn should_skip_the_audio_chain_for_inputs_without_audio() {
+    let opts = RestoreOptions::default();
+    assert_eq!(resolve_audio_stream(&opts, 2).unwrap(), Some(0));
+    // A silent capture gets no [0:a:0] chain, which ffmpeg would reject
+    assert_eq!(resolve_audio_stream(&opts, 0).unwrap(), None);
+    let second = RestoreOptions {
+        audio_stream: Some(1),
+        ..Default::default()
+    };
+    assert_eq!(resolve_audio_stream(&second, 2).unwrap(), Some(1));
+    assert!(resolve_audio_stream(&second, 1).is_err());
+    let none = RestoreOptions {
+        audio_stream: None,
+        ..Default::default()
+    };
+    assert_eq!(resolve_audio_stream(&none, 1).unwrap(), None);
+}